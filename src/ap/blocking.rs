@@ -0,0 +1,180 @@
+use super::*;
+use crate::ap::DEFAULT_COMMAND_TIMEOUT;
+use crate::blocking::BlockingSocket;
+use crate::error::ClientError;
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Synchronous hostapd client for programs without a tokio runtime. Requests
+/// go straight to the control socket from the calling thread, with the same
+/// commands, parsing and timeouts as [`RequestClient`].
+///
+/// ```no_run
+/// use wifi_ctrl::ap::blocking::Client;
+///
+/// let mut client = Client::open("/var/run/hostapd/wlan1")?;
+/// println!("{:?}", client.get_status()?);
+/// for event in client.events()? {
+///     println!("{:?}", event?);
+/// }
+/// # Ok::<(), wifi_ctrl::error::BlockingError>(())
+/// ```
+pub struct Client {
+    socket_path: PathBuf,
+    socket: BlockingSocket,
+    command_timeout: Duration,
+    attach_options: Vec<String>,
+    /// The country last set with [`Client::set_country`], which hostapd can't
+    /// report
+    country: Option<CountryCode>,
+}
+
+impl Client {
+    /// Connect to the control socket of one interface, e.g.
+    /// `/var/run/hostapd/wlan1`.
+    pub fn open<P: AsRef<Path>>(path: P) -> BlockingResult<Self> {
+        let socket_path = path.as_ref().to_path_buf();
+        let socket = BlockingSocket::open(
+            &socket_path,
+            "hostapd_blocking.sock",
+            DEFAULT_COMMAND_TIMEOUT,
+        )?;
+        Ok(Self {
+            socket_path,
+            socket,
+            command_timeout: DEFAULT_COMMAND_TIMEOUT,
+            attach_options: vec![],
+            country: None,
+        })
+    }
+
+    /// Set how long to wait for a reply to a control command/request before
+    /// giving up with [`ClientError::Timeout`].
+    pub fn set_command_timeout(&mut self, timeout: Duration) {
+        self.command_timeout = timeout;
+        self.socket.set_command_timeout(timeout);
+    }
+
+    /// Options to pass to `ATTACH` when opening [`Self::events`].
+    pub fn add_attach_options(&mut self, options: &[&str]) {
+        for o in options {
+            self.attach_options.push(o.to_string());
+        }
+    }
+
+    /// Open a second socket attached for events. Events are only delivered
+    /// from the moment it's attached.
+    pub fn events(&self) -> BlockingResult<Events> {
+        let mut socket = BlockingSocket::open(
+            &self.socket_path,
+            "hostapd_blocking_events.sock",
+            self.command_timeout,
+        )?;
+        socket.command(&attach_command(&self.attach_options))?;
+        Ok(Events { socket })
+    }
+
+    pub fn send_custom(&mut self, custom: &str) -> BlockingResult<String> {
+        self.socket.request(custom, TryInto::try_into)
+    }
+
+    pub fn get_status(&mut self) -> BlockingResult<Status> {
+        self.socket.request("STATUS", Status::from_response)
+    }
+
+    pub fn get_config(&mut self) -> BlockingResult<Config> {
+        self.socket.request("GET_CONFIG", Config::from_response)
+    }
+
+    pub fn enable(&mut self) -> BlockingResult {
+        self.socket.command("ENABLE")
+    }
+
+    pub fn disable(&mut self) -> BlockingResult {
+        self.socket.command("DISABLE")
+    }
+
+    pub fn set_value(&mut self, key: &str, value: &str) -> BlockingResult {
+        self.socket.command(&format!("SET {key} {value}"))
+    }
+
+    /// As [`RequestClient::set_country`], [`CountryCode::WORLD`] fails with
+    /// [`ClientError::InvalidParameter`].
+    pub fn set_country(&mut self, country: CountryCode) -> BlockingResult {
+        if country.is_world() {
+            return Err(ClientError::InvalidParameter("country").into());
+        }
+        self.set_value("country_code", country.as_str())?;
+        self.set_value("ieee80211d", "1")?;
+        self.country = Some(country);
+        Ok(())
+    }
+
+    /// The country last set with [`Self::set_country`] through this client,
+    /// if any.
+    pub fn get_country(&self) -> Option<CountryCode> {
+        self.country
+    }
+
+    pub fn set_ieee80211h(&mut self, enabled: bool) -> BlockingResult {
+        self.set_value("ieee80211h", if enabled { "1" } else { "0" })
+    }
+}
+
+/// Blocking iterator over the events of an attached socket, from
+/// [`Client::events`]. It waits for the next event indefinitely.
+pub struct Events {
+    socket: BlockingSocket,
+}
+
+impl Iterator for Events {
+    type Item = BlockingResult<Broadcast>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(
+            self.socket
+                .recv_event(None)
+                .map(|data_str| EventSocket::parse(&data_str).1.into()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixDatagram;
+
+    #[test]
+    fn events_follow_the_attach_reply() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wlan1");
+        let daemon = UnixDatagram::bind(&path).unwrap();
+        let fake_daemon = std::thread::spawn(move || {
+            let mut buffer = [0; 64];
+            let (n, from) = daemon.recv_from(&mut buffer).unwrap();
+            assert_eq!(&buffer[..n], b"ATTACH -");
+            let from = from.as_pathname().unwrap();
+            // an event racing ahead of the reply is kept, not taken for it
+            daemon
+                .send_to(b"<3>AP-STA-CONNECTED 00:11:22:33:44:55", from)
+                .unwrap();
+            daemon.send_to(b"OK\n", from).unwrap();
+            daemon
+                .send_to(b"<3>AP-STA-DISCONNECTED 00:11:22:33:44:55", from)
+                .unwrap();
+        });
+        let mut client = Client::open(&path).unwrap();
+        client.add_attach_options(&["-"]);
+        let mut events = client.events().unwrap();
+        fake_daemon.join().unwrap();
+        assert!(matches!(
+            events.next(),
+            Some(Ok(Broadcast::Connected(sta))) if sta.mac.to_string() == "00:11:22:33:44:55"
+        ));
+        assert!(matches!(
+            events.next(),
+            Some(Ok(Broadcast::Disconnected(mac))) if mac.to_string() == "00:11:22:33:44:55"
+        ));
+    }
+}
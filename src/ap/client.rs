@@ -8,9 +8,39 @@ pub(crate) enum Request {
     Enable(oneshot::Sender<Result>),
     Disable(oneshot::Sender<Result>),
     SetValue(String, String, oneshot::Sender<Result>),
+    SetCountry(CountryCode, oneshot::Sender<Result>),
+    GetCountry(oneshot::Sender<Result<Option<CountryCode>>>),
+    /// A request from a client made with [`RequestClient::with_timeout`]
+    WithDeadline(Instant, Box<Request>),
     Shutdown,
 }
 
+impl Request {
+    /// Whether the client stopped waiting for the response, e.g. because its
+    /// future was dropped, so the request needn't be sent at all.
+    pub(crate) fn is_abandoned(&self) -> bool {
+        match self {
+            Request::Custom(_, response) => response.is_closed(),
+            Request::Status(response) => response.is_closed(),
+            Request::Config(response) => response.is_closed(),
+            Request::Enable(response) => response.is_closed(),
+            Request::Disable(response) => response.is_closed(),
+            Request::SetValue(_, _, response) => response.is_closed(),
+            Request::SetCountry(_, response) => response.is_closed(),
+            Request::GetCountry(response) => response.is_closed(),
+            Request::WithDeadline(_, request) => request.is_abandoned(),
+            Request::Shutdown => false,
+        }
+    }
+
+    pub(crate) fn split_deadline(self) -> (Option<Instant>, Request) {
+        match self {
+            Request::WithDeadline(deadline, request) => (Some(deadline), *request),
+            request => (None, request),
+        }
+    }
+}
+
 impl ShutdownSignal for Request {
     fn is_shutdown(&self) -> bool {
         matches!(self, Request::Shutdown)
@@ -20,12 +50,61 @@ impl ShutdownSignal for Request {
 #[derive(Clone)]
 /// Request client wraps the request events, awaiting oneshot channels when appropriate
 pub struct RequestClient {
-    sender: mpsc::Sender<Request>,
+    sender: RequestSender,
+    /// Deadline for each request, counted from when it's made
+    timeout: Option<Duration>,
+}
+
+#[derive(Clone)]
+enum RequestSender {
+    Ap(mpsc::Sender<Request>),
+    /// An interface behind the global control socket; requests are tagged
+    /// with its name
+    Global(mpsc::Sender<GlobalRequest>, Arc<str>),
+}
+
+impl RequestSender {
+    async fn send(&self, request: Request) -> Result {
+        match self {
+            RequestSender::Ap(sender) => sender.send(request).await?,
+            RequestSender::Global(sender, ifname) => {
+                sender
+                    .send(GlobalRequest::Interface(ifname.to_string(), request))
+                    .await?
+            }
+        }
+        Ok(())
+    }
 }
 
 impl RequestClient {
     pub(crate) fn new(sender: mpsc::Sender<Request>) -> RequestClient {
-        RequestClient { sender }
+        RequestClient {
+            sender: RequestSender::Ap(sender),
+            timeout: None,
+        }
+    }
+
+    pub(crate) fn for_interface(
+        sender: mpsc::Sender<GlobalRequest>,
+        ifname: &str,
+    ) -> RequestClient {
+        RequestClient {
+            sender: RequestSender::Global(sender, ifname.into()),
+            timeout: None,
+        }
+    }
+
+    /// A client whose requests each fail with
+    /// [`ClientError::Timeout`](crate::error::ClientError::Timeout) if not
+    /// answered within `timeout`, in place of the runner's command timeout.
+    /// A request still queued when its time runs out is skipped by the runner
+    /// rather than sent late.
+    pub fn with_timeout(&self, timeout: Duration) -> RequestClient {
+        RequestClient {
+            sender: self.sender.clone(),
+            timeout: Some(timeout),
+        }
     }
 
     async fn request<T>(
@@ -33,8 +112,18 @@ impl RequestClient {
         build_request: impl FnOnce(oneshot::Sender<Result<T>>) -> Request,
     ) -> Result<T> {
         let (response, request) = oneshot::channel();
-        self.sender.send(build_request(response)).await?;
-        request.await?
+        let Some(timeout) = self.timeout else {
+            self.sender.send(build_request(response)).await?;
+            return request.await?;
+        };
+        let deadline = Instant::now() + timeout;
+        let request_with_deadline =
+            Request::WithDeadline(deadline, Box::new(build_request(response)));
+        tokio::time::timeout_at(deadline, async {
+            self.sender.send(request_with_deadline).await?;
+            request.await?
+        })
+        .await?
     }
 
     pub async fn send_custom(&self, custom: String) -> Result<String> {
@@ -63,8 +152,37 @@ impl RequestClient {
             .await
     }
 
+    /// Set the regulatory domain (`country_code`) and advertise it in
+    /// beacons (`ieee80211d`). Like other settings it takes effect when the
+    /// interface is next enabled, e.g. after [`Self::disable`] and
+    /// [`Self::enable`]. hostapd only takes a country, so
+    /// [`CountryCode::WORLD`] fails with
+    /// [`ClientError::InvalidParameter`](crate::error::ClientError::InvalidParameter).
+    pub async fn set_country(&self, country: CountryCode) -> Result {
+        self.request(|response| Request::SetCountry(country, response))
+            .await
+    }
+
+    /// The country last set with [`Self::set_country`] through this runner,
+    /// if any. hostapd has no way to report its `country_code`, so one from
+    /// the config file isn't known.
+    pub async fn get_country(&self) -> Result<Option<CountryCode>> {
+        self.request(Request::GetCountry).await
+    }
+
+    /// Turn 802.11h spectrum management (`ieee80211h`) on or off. It's
+    /// needed for DFS channels and requires a country from
+    /// [`Self::set_country`].
+    pub async fn set_ieee80211h(&self, enabled: bool) -> Result {
+        self.set_value("ieee80211h", if enabled { "1" } else { "0" })
+            .await
+    }
+
+    /// Stop the runner. For a client returned by [`GlobalClient::interface`]
+    /// this only drops the global runner's state for the BSS; stop the
+    /// global runner with [`GlobalClient::shutdown`].
     pub async fn shutdown(&self) -> Result {
-        Ok(self.sender.send(Request::Shutdown).await?)
+        self.sender.send(Request::Shutdown).await
     }
 }
 
@@ -72,10 +190,155 @@ impl RequestClient {
 /// Broadcast events, such as a client disconnecting or connecting, may happen at any time.
 pub enum Broadcast {
     Ready,
-    Connected(String),
-    Disconnected(String),
+    /// The access point started beaconing
+    ApEnabled,
+    ApDisabled,
+    /// The interface was brought up, e.g. with [`RequestClient::enable`]
+    InterfaceEnabled,
+    InterfaceDisabled,
+    Connected(StaConnected),
+    /// The station with this MAC address left
+    Disconnected(MacAddr),
+    /// The station failed the 4-way handshake in a way that suggests it has
+    /// the wrong passphrase
+    PossiblePskMismatch(MacAddr),
+    /// The station was turned away because `max_num_sta` stations are
+    /// already associated
+    RejectedMaxSta(MacAddr),
+    /// The station answered a `POLL_STA` request
+    PollOk(MacAddr),
+    Eap(EapEvent),
+    Wps(WpsEvent),
+    CsaFinished(CsaFinished),
+    /// The keepalive found the event socket no longer answering and
+    /// re-attached it; events in between may have been missed.
+    Reattached,
     UnknownEvent(String),
+    /// Only yielded by [`EventStream`]: this many broadcasts were missed
+    /// because the stream fell behind, so any state tracked from them should
+    /// be refreshed, e.g. with [`RequestClient::get_status`].
+    Lagged(u64),
+}
+
+/// The variants of [`Broadcast`] without their data, for
+/// [`EventStream::kinds`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BroadcastKind {
+    Ready,
+    ApEnabled,
+    ApDisabled,
+    InterfaceEnabled,
+    InterfaceDisabled,
+    Connected,
+    Disconnected,
+    PossiblePskMismatch,
+    RejectedMaxSta,
+    PollOk,
+    Eap,
+    Wps,
+    CsaFinished,
+    Reattached,
+    UnknownEvent,
+    Lagged,
+}
+
+impl Broadcast {
+    pub fn kind(&self) -> BroadcastKind {
+        match self {
+            Broadcast::Ready => BroadcastKind::Ready,
+            Broadcast::ApEnabled => BroadcastKind::ApEnabled,
+            Broadcast::ApDisabled => BroadcastKind::ApDisabled,
+            Broadcast::InterfaceEnabled => BroadcastKind::InterfaceEnabled,
+            Broadcast::InterfaceDisabled => BroadcastKind::InterfaceDisabled,
+            Broadcast::Connected(_) => BroadcastKind::Connected,
+            Broadcast::Disconnected(_) => BroadcastKind::Disconnected,
+            Broadcast::PossiblePskMismatch(_) => BroadcastKind::PossiblePskMismatch,
+            Broadcast::RejectedMaxSta(_) => BroadcastKind::RejectedMaxSta,
+            Broadcast::PollOk(_) => BroadcastKind::PollOk,
+            Broadcast::Eap(_) => BroadcastKind::Eap,
+            Broadcast::Wps(_) => BroadcastKind::Wps,
+            Broadcast::CsaFinished(_) => BroadcastKind::CsaFinished,
+            Broadcast::Reattached => BroadcastKind::Reattached,
+            Broadcast::UnknownEvent(_) => BroadcastKind::UnknownEvent,
+            Broadcast::Lagged(_) => BroadcastKind::Lagged,
+        }
+    }
+
+    /// The MAC address of the station a broadcast concerns, if any.
+    pub fn mac(&self) -> Option<MacAddr> {
+        match self {
+            Broadcast::Connected(sta) => Some(sta.mac),
+            Broadcast::Disconnected(mac)
+            | Broadcast::PossiblePskMismatch(mac)
+            | Broadcast::RejectedMaxSta(mac)
+            | Broadcast::PollOk(mac) => Some(*mac),
+            Broadcast::Eap(eap) => eap.mac,
+            Broadcast::Wps(wps) => wps.mac(),
+            _ => None,
+        }
+    }
 }
 
 /// Channel for broadcasting events.
 pub type BroadcastReceiver = broadcast::Receiver<Broadcast>;
+
+/// Broadcasts as a [`Stream`](tokio_stream::Stream), from
+/// [`WifiSetup::event_stream`]. Unlike a bare [`BroadcastReceiver`], falling
+/// behind isn't silent: it's reported as [`Broadcast::Lagged`], which passes
+/// every filter.
+///
+/// ```no_run
+/// use futures::StreamExt;
+/// use wifi_ctrl::ap::WifiSetup;
+///
+/// #[tokio::main]
+/// async fn main() -> wifi_ctrl::Result {
+///     let setup = WifiSetup::new();
+///     // comings and goings of one station
+///     let mut events = setup.event_stream().mac("00:11:22:33:44:55".parse()?);
+///     while let Some(event) = events.next().await {
+///         println!("{event:?}");
+///     }
+///     Ok(())
+/// }
+/// ```
+pub struct EventStream {
+    inner: FilteredStream<Broadcast>,
+}
+
+impl EventStream {
+    pub(crate) fn new(receiver: BroadcastReceiver) -> Self {
+        Self {
+            inner: FilteredStream::new(receiver, Broadcast::Lagged),
+        }
+    }
+
+    /// Only yield broadcasts of the given kinds.
+    pub fn kinds(self, kinds: &[BroadcastKind]) -> Self {
+        let kinds = kinds.to_vec();
+        self.filter(move |broadcast| kinds.contains(&broadcast.kind()))
+    }
+
+    /// Only yield broadcasts about the station with this MAC address, such
+    /// as its connects and disconnects.
+    pub fn mac(self, mac: MacAddr) -> Self {
+        self.filter(move |broadcast| broadcast.mac() == Some(mac))
+    }
+
+    /// Only yield broadcasts for which `filter` returns `true`.
+    pub fn filter(mut self, filter: impl Fn(&Broadcast) -> bool + Send + Sync + 'static) -> Self {
+        self.inner.push_filter(filter);
+        self
+    }
+}
+
+impl tokio_stream::Stream for EventStream {
+    type Item = Broadcast;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Broadcast>> {
+        self.get_mut().inner.poll_next(cx)
+    }
+}
@@ -0,0 +1,160 @@
+use crate::MacAddr;
+
+/// An `AP-STA-CONNECTED` event: a station associated and, on a secured
+/// network, completed its handshake.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaConnected {
+    pub mac: MacAddr,
+    /// The station's P2P device address, when it's a P2P client
+    pub p2p_dev_addr: Option<MacAddr>,
+    /// The `keyid` of the `wpa_psk_file` entry whose passphrase the station
+    /// used
+    pub keyid: Option<String>,
+    /// Hash of the station's DPP public key, when it connected with DPP
+    pub dpp_pkhash: Option<String>,
+}
+
+impl StaConnected {
+    /// Parse the body of an `AP-STA-CONNECTED <mac> [p2p_dev_addr=<addr>]
+    /// [keyid=<id>] [dpp_pkhash=<hash>]` event.
+    pub(crate) fn parse(body: &str) -> Option<Self> {
+        let mut words = body.split_whitespace();
+        let mut sta = StaConnected {
+            mac: words.next()?.parse().ok()?,
+            p2p_dev_addr: None,
+            keyid: None,
+            dpp_pkhash: None,
+        };
+        for (key, value) in words.filter_map(|word| word.split_once('=')) {
+            match key {
+                "p2p_dev_addr" => sta.p2p_dev_addr = value.parse().ok(),
+                "keyid" => sta.keyid = Some(value.to_string()),
+                "dpp_pkhash" => sta.dpp_pkhash = Some(value.to_string()),
+                _ => (),
+            }
+        }
+        Some(sta)
+    }
+}
+
+/// Progress of a station's 802.1X/EAP authentication, from a
+/// `CTRL-EVENT-EAP-*` event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EapStatus {
+    Started,
+    Success,
+    Failure,
+    /// The station stopped answering mid-exchange
+    TimeoutFailure,
+    Retransmit,
+    /// Any other `CTRL-EVENT-EAP-<status>`, e.g. `SUCCESS2`
+    Other(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EapEvent {
+    pub status: EapStatus,
+    /// The station concerned, if the event names one
+    pub mac: Option<MacAddr>,
+}
+
+impl EapEvent {
+    /// Parse a `CTRL-EVENT-EAP-<status> [<mac>]` event, given `<status>` and
+    /// the rest.
+    pub(crate) fn parse(status: &str, body: &str) -> Self {
+        let status = match status {
+            "STARTED" => EapStatus::Started,
+            "SUCCESS" => EapStatus::Success,
+            "FAILURE" => EapStatus::Failure,
+            "TIMEOUT-FAILURE" => EapStatus::TimeoutFailure,
+            "RETRANSMIT" => EapStatus::Retransmit,
+            other => EapStatus::Other(other.to_string()),
+        };
+        EapEvent {
+            status,
+            mac: body
+                .split_whitespace()
+                .next()
+                .and_then(|mac| mac.parse().ok()),
+        }
+    }
+}
+
+/// A `WPS-*` event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WpsEvent {
+    /// Push button mode is active, e.g. after a `WPS_PBC` request
+    PbcActive,
+    /// Push button mode ended
+    PbcDisable,
+    Success,
+    /// The registration failed; the rest of the event gives the WPS message
+    /// and `config_error`
+    Fail(String),
+    Timeout,
+    /// More than one station was pressing its button, so none is admitted
+    OverlapDetected,
+    /// The station with this MAC address was given the network credentials
+    RegSuccess(MacAddr),
+    /// A station looking for a WPS registrar was seen
+    EnrolleeSeen(MacAddr),
+    /// Any other `WPS-<name>`, with its name and the rest of the event
+    Other(String),
+}
+
+impl WpsEvent {
+    /// Parse a `WPS-<name> ...` event, given `<name>` and the rest.
+    pub(crate) fn parse(name: &str, body: &str) -> Self {
+        let mac = body
+            .split_whitespace()
+            .next()
+            .and_then(|mac| mac.parse::<MacAddr>().ok());
+        match (name, mac) {
+            ("PBC-ACTIVE", _) => WpsEvent::PbcActive,
+            ("PBC-DISABLE", _) => WpsEvent::PbcDisable,
+            ("SUCCESS", _) => WpsEvent::Success,
+            ("FAIL", _) => WpsEvent::Fail(body.to_string()),
+            ("TIMEOUT", _) => WpsEvent::Timeout,
+            ("OVERLAP-DETECTED", _) => WpsEvent::OverlapDetected,
+            ("REG-SUCCESS", Some(mac)) => WpsEvent::RegSuccess(mac),
+            ("ENROLLEE-SEEN", Some(mac)) => WpsEvent::EnrolleeSeen(mac),
+            _ if body.is_empty() => WpsEvent::Other(format!("WPS-{name}")),
+            _ => WpsEvent::Other(format!("WPS-{name} {body}")),
+        }
+    }
+
+    /// The station the event concerns, if any.
+    pub fn mac(&self) -> Option<MacAddr> {
+        match self {
+            WpsEvent::RegSuccess(mac) | WpsEvent::EnrolleeSeen(mac) => Some(*mac),
+            _ => None,
+        }
+    }
+}
+
+/// An `AP-CSA-FINISHED` event: a channel switch completed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsaFinished {
+    /// The new frequency in MHz
+    pub freq: u32,
+    /// The new channel needs radar detection
+    pub dfs: bool,
+}
+
+impl CsaFinished {
+    /// Parse the body of an `AP-CSA-FINISHED freq=<freq> dfs=<0|1>` event.
+    pub(crate) fn parse(body: &str) -> Option<Self> {
+        let mut csa = CsaFinished {
+            freq: 0,
+            dfs: false,
+        };
+        for (key, value) in body.split_whitespace().filter_map(|w| w.split_once('=')) {
+            match key {
+                "freq" => csa.freq = value.parse().ok()?,
+                "dfs" => csa.dfs = value == "1",
+                _ => (),
+            }
+        }
+        Some(csa)
+    }
+}
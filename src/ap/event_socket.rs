@@ -4,91 +4,214 @@ use std::time::Duration;
 
 pub(crate) struct EventSocket {
     socket_handle: SocketHandle<1024>,
+    /// Commands registering the socket for events, replayed to re-attach
+    attach_commands: Vec<String>,
 }
 
 #[derive(Debug)]
 pub(crate) enum Event {
-    ApStaConnected(String),
-    ApStaDisconnected(String),
+    ApEnabled,
+    ApDisabled,
+    InterfaceEnabled,
+    InterfaceDisabled,
+    ApStaConnected(StaConnected),
+    ApStaDisconnected(MacAddr),
+    PossiblePskMismatch(MacAddr),
+    RejectedMaxSta(MacAddr),
+    PollOk(MacAddr),
+    Eap(EapEvent),
+    Wps(WpsEvent),
+    CsaFinished(CsaFinished),
     Unknown(String),
 }
 
+impl From<Event> for Broadcast {
+    fn from(event: Event) -> Self {
+        match event {
+            Event::ApEnabled => Broadcast::ApEnabled,
+            Event::ApDisabled => Broadcast::ApDisabled,
+            Event::InterfaceEnabled => Broadcast::InterfaceEnabled,
+            Event::InterfaceDisabled => Broadcast::InterfaceDisabled,
+            Event::ApStaConnected(sta) => Broadcast::Connected(sta),
+            Event::ApStaDisconnected(mac) => Broadcast::Disconnected(mac),
+            Event::PossiblePskMismatch(mac) => Broadcast::PossiblePskMismatch(mac),
+            Event::RejectedMaxSta(mac) => Broadcast::RejectedMaxSta(mac),
+            Event::PollOk(mac) => Broadcast::PollOk(mac),
+            Event::Eap(eap) => Broadcast::Eap(eap),
+            Event::Wps(wps) => Broadcast::Wps(wps),
+            Event::CsaFinished(csa) => Broadcast::CsaFinished(csa),
+            Event::Unknown(msg) => Broadcast::UnknownEvent(msg),
+        }
+    }
+}
+
 impl EventSocket {
-    pub(crate) async fn new<P>(
-        socket: P,
-        request_receiver: &mut mpsc::Receiver<Request>,
+    /// Open the event socket and register it with `ATTACH`, followed by
+    /// `LOG_LEVEL <log_level>` if given (the global control socket has no
+    /// log level of its own). The same commands are replayed if the
+    /// keepalive finds the socket dropped.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) async fn new<S>(
+        control_interface: &ControlInterface,
+        request_receiver: &mut mpsc::Receiver<S>,
         attach_options: &[String],
+        log_level: Option<&str>,
         command_timeout: Duration,
         attach_retries: usize,
         attach_retry_delay: Duration,
-    ) -> SocketResult<(Vec<Request>, Self)>
+    ) -> SocketResult<(Vec<S>, Self)>
     where
-        P: AsRef<std::path::Path> + std::fmt::Debug,
+        S: ShutdownSignal,
     {
         let (mut socket_handle, deferred_requests) = SocketHandle::open(
-            socket,
+            control_interface,
             "hostapd_async.sock",
             request_receiver,
             command_timeout,
         )
         .await?;
 
-        let mut command = "ATTACH".to_string();
-        for o in attach_options {
-            command.push(' ');
-            command.push_str(o);
+        let mut attach_commands = vec![attach_command(attach_options)];
+        if let Some(log_level) = log_level {
+            attach_commands.push(format!("LOG_LEVEL {log_level}"));
+        }
+        for command in &attach_commands {
+            retry_command(
+                &mut socket_handle,
+                command.as_bytes(),
+                attach_retries,
+                attach_retry_delay,
+            )
+            .await?;
         }
-        retry_command(
-            &mut socket_handle,
-            command.as_bytes(),
-            attach_retries,
-            attach_retry_delay,
-        )
-        .await?;
-        retry_command(
-            &mut socket_handle,
-            b"LOG_LEVEL DEBUG",
-            attach_retries,
-            attach_retry_delay,
-        )
-        .await?;
         info!("hostapd event stream registered");
-        Ok((deferred_requests, Self { socket_handle }))
+        Ok((
+            deferred_requests,
+            Self {
+                socket_handle,
+                attach_commands,
+            },
+        ))
+    }
+
+    /// Returns whether the socket had to be re-attached, see
+    /// [`SocketHandle::check_attached`].
+    pub(crate) async fn keepalive(&mut self) -> SocketResult<bool> {
+        self.socket_handle
+            .check_attached(&self.attach_commands)
+            .await
     }
 
     pub(crate) async fn recv(&mut self) -> SocketResult<Event> {
-        let bytes = self.socket_handle.recv().await?;
-        let data_str = String::from_utf8_lossy(bytes);
-        Ok(if let Some(n) = data_str.find("AP-STA-DISCONNECTED") {
-            let index = n + "AP-STA-DISCONNECTED".len();
-            let mac = data_str[index..].trim();
-            Event::ApStaDisconnected(mac.to_string())
-        } else if let Some(n) = data_str.find("AP-STA-CONNECTED") {
-            let index = n + "AP-STA-CONNECTED".len();
-            let mac = &data_str[index..].trim();
-            Event::ApStaConnected(mac.to_string())
-        } else {
-            Event::Unknown(data_str.to_string())
-        })
+        Ok(self.recv_tagged().await?.1)
+    }
+
+    /// Like [`Self::recv`], but also returns the interface named by the
+    /// `IFNAME=` prefix the global control socket puts on interface events.
+    pub(crate) async fn recv_tagged(&mut self) -> SocketResult<(Option<String>, Event)> {
+        // events that turned up on this socket while waiting for a handshake
+        // reply come first
+        let data_str = match self.socket_handle.take_unsolicited() {
+            Some(data_str) => data_str,
+            None => String::from_utf8_lossy(self.socket_handle.recv().await?).into_owned(),
+        };
+        Ok(Self::parse(&data_str))
+    }
+
+    /// Parse an event datagram, which may have come in on the request socket
+    /// (see [`SocketHandle::take_unsolicited`]). The `IFNAME=` and `<level>`
+    /// prefixes are optional.
+    pub(crate) fn parse(data_str: &str) -> (Option<String>, Event) {
+        let (ifname, data_str) = match data_str
+            .strip_prefix("IFNAME=")
+            .and_then(|rest| rest.split_once(' '))
+        {
+            Some((ifname, rest)) => (Some(ifname.to_string()), rest),
+            None => (None, data_str),
+        };
+        // the event without its `<level>` prefix
+        let body = match data_str
+            .strip_prefix('<')
+            .and_then(|rest| rest.split_once('>'))
+        {
+            Some((_, body)) => body,
+            None => data_str,
+        };
+        let body = body.trim_end();
+        let (name, rest) = body.split_once(' ').unwrap_or((body, ""));
+        let mac = || rest.split_whitespace().next()?.parse::<MacAddr>().ok();
+        let event = match name {
+            "AP-ENABLED" => Some(Event::ApEnabled),
+            "AP-DISABLED" => Some(Event::ApDisabled),
+            "INTERFACE-ENABLED" => Some(Event::InterfaceEnabled),
+            "INTERFACE-DISABLED" => Some(Event::InterfaceDisabled),
+            "AP-STA-CONNECTED" => StaConnected::parse(rest).map(Event::ApStaConnected),
+            "AP-STA-DISCONNECTED" => mac().map(Event::ApStaDisconnected),
+            "AP-STA-POSSIBLE-PSK-MISMATCH" => mac().map(Event::PossiblePskMismatch),
+            "AP-REJECTED-MAX-STA" => mac().map(Event::RejectedMaxSta),
+            "AP-STA-POLL-OK" => mac().map(Event::PollOk),
+            "AP-CSA-FINISHED" => CsaFinished::parse(rest).map(Event::CsaFinished),
+            _ => {
+                if let Some(status) = name.strip_prefix("CTRL-EVENT-EAP-") {
+                    Some(Event::Eap(EapEvent::parse(status, rest)))
+                } else {
+                    name.strip_prefix("WPS-")
+                        .map(|name| Event::Wps(WpsEvent::parse(name, rest)))
+                }
+            }
+        };
+        (
+            ifname,
+            event.unwrap_or_else(|| Event::Unknown(data_str.to_string())),
+        )
     }
 }
 
-/// Send a control command, retrying on failure with a fixed delay up to
-/// `retries` times. Returns [`SocketError::AttachFailed`] once the attempts are
-/// exhausted so the runtime doesn't spin forever.
-async fn retry_command<const N: usize>(
-    socket_handle: &mut SocketHandle<N>,
-    command: &[u8],
-    retries: usize,
-    delay: Duration,
-) -> SocketResult {
-    for _ in 0..retries {
-        if socket_handle.command(command).await?.is_ok() {
-            return Ok(());
-        }
-        tokio::time::sleep(delay).await;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_are_parsed() {
+        let (ifname, event) = EventSocket::parse(
+            "IFNAME=wlan1 <3>AP-STA-CONNECTED 00:11:22:33:44:55 keyid=guest dpp_pkhash=abcd",
+        );
+        assert_eq!(ifname.as_deref(), Some("wlan1"));
+        let Event::ApStaConnected(sta) = event else {
+            panic!("unexpected {event:?}");
+        };
+        assert_eq!(sta.mac.to_string(), "00:11:22:33:44:55");
+        assert_eq!(sta.keyid.as_deref(), Some("guest"));
+        assert_eq!(sta.dpp_pkhash.as_deref(), Some("abcd"));
+
+        let (_, event) = EventSocket::parse("<3>AP-STA-POSSIBLE-PSK-MISMATCH 00:11:22:33:44:55");
+        assert!(
+            matches!(event, Event::PossiblePskMismatch(mac) if mac.to_string() == "00:11:22:33:44:55")
+        );
+        let (_, event) = EventSocket::parse("<3>CTRL-EVENT-EAP-TIMEOUT-FAILURE 00:11:22:33:44:55");
+        assert!(matches!(
+            event,
+            Event::Eap(EapEvent {
+                status: EapStatus::TimeoutFailure,
+                mac: Some(_)
+            })
+        ));
+        let (_, event) = EventSocket::parse("<3>WPS-FAIL msg=8 config_error=18");
+        assert!(
+            matches!(event, Event::Wps(WpsEvent::Fail(rest)) if rest == "msg=8 config_error=18")
+        );
+        let (_, event) = EventSocket::parse("<3>AP-CSA-FINISHED freq=5260 dfs=1");
+        assert!(matches!(
+            event,
+            Event::CsaFinished(CsaFinished {
+                freq: 5260,
+                dfs: true
+            })
+        ));
+        let (_, event) = EventSocket::parse("AP-ENABLED");
+        assert!(matches!(event, Event::ApEnabled));
+        // only a whole event name matches
+        let (_, event) = EventSocket::parse("<3>AP-STA-CONNECTED-SOMETHING 00:11:22:33:44:55");
+        assert!(matches!(event, Event::Unknown(_)));
     }
-    Err(error::SocketError::AttachFailed(
-        String::from_utf8_lossy(command).to_string(),
-    ))
 }
@@ -0,0 +1,557 @@
+use super::*;
+use crate::ap::{
+    DEFAULT_ATTACH_RETRIES, DEFAULT_ATTACH_RETRY_DELAY, DEFAULT_CHANNEL_SIZE,
+    DEFAULT_COMMAND_TIMEOUT,
+};
+use crate::error::ClientError;
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+const PATH_DEFAULT_GLOBAL: &str = "/var/run/hostapd/global";
+
+/// How hostapd should bring up an interface or BSS with `ADD`.
+#[derive(Debug, Clone)]
+pub enum InterfaceAdd {
+    /// `ADD bss_config=<phy>:<config file>`: add a BSS to a radio hostapd is
+    /// already running. `ifname` must match the `interface=` line of the
+    /// config file; it names the returned [`RequestClient`].
+    Bss {
+        ifname: String,
+        phy: String,
+        config_file: String,
+    },
+    /// `ADD <ifname> config=<config file>`: bring up a new interface
+    Interface { ifname: String, config_file: String },
+}
+
+impl InterfaceAdd {
+    fn ifname(&self) -> &str {
+        match self {
+            InterfaceAdd::Bss { ifname, .. } | InterfaceAdd::Interface { ifname, .. } => ifname,
+        }
+    }
+
+    fn to_command(&self) -> Result<String> {
+        let ifname = self.ifname();
+        check_token("ifname", ifname)?;
+        match self {
+            InterfaceAdd::Bss {
+                phy, config_file, ..
+            } => {
+                // the phy name ends at the first ':'
+                if phy.is_empty() || phy.chars().any(|c| c == ':' || c.is_control()) {
+                    return Err(ClientError::InvalidParameter("phy"));
+                }
+                if config_file.chars().any(char::is_control) {
+                    return Err(ClientError::InvalidParameter("config_file"));
+                }
+                Ok(format!("ADD bss_config={phy}:{config_file}"))
+            }
+            InterfaceAdd::Interface { config_file, .. } => {
+                if config_file.chars().any(char::is_control) {
+                    return Err(ClientError::InvalidParameter("config_file"));
+                }
+                Ok(format!("ADD {ifname} config={config_file}"))
+            }
+        }
+    }
+}
+
+/// A space-separated command argument must be non-empty and can't contain
+/// whitespace of its own.
+fn check_token(name: &'static str, value: &str) -> Result {
+    if value.is_empty() || value.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(ClientError::InvalidParameter(name));
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+pub(crate) enum GlobalRequest {
+    Interfaces(oneshot::Sender<Result<Vec<String>>>),
+    Add(InterfaceAdd, oneshot::Sender<Result>),
+    Remove(String, oneshot::Sender<Result>),
+    /// `FST-*` commands; the reply is returned as-is
+    Fst(String, oneshot::Sender<Result<String>>),
+    /// An access point request for one interface, sent with an `IFNAME=`
+    /// prefix
+    Interface(String, Request),
+    Shutdown,
+}
+
+impl ShutdownSignal for GlobalRequest {
+    fn is_shutdown(&self) -> bool {
+        // a shutdown from an interface's client only ends that interface
+        matches!(self, GlobalRequest::Shutdown)
+    }
+}
+
+fn parse_interfaces(response: &str) -> ParseResult<Vec<String>> {
+    Ok(response
+        .lines()
+        .map(str::trim)
+        .filter(|ifname| !ifname.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+#[derive(Clone)]
+/// Client for the hostapd global control interface (`hostapd -g <socket>`),
+/// adding and removing interfaces and BSSes at runtime without restarting
+/// hostapd.
+pub struct GlobalClient {
+    sender: mpsc::Sender<GlobalRequest>,
+}
+
+impl GlobalClient {
+    async fn request<T>(
+        &self,
+        build_request: impl FnOnce(oneshot::Sender<Result<T>>) -> GlobalRequest,
+    ) -> Result<T> {
+        let (response, request) = oneshot::channel();
+        self.sender.send(build_request(response)).await?;
+        request.await?
+    }
+
+    /// Names of the interfaces hostapd is running.
+    pub async fn interfaces(&self) -> Result<Vec<String>> {
+        self.request(GlobalRequest::Interfaces).await
+    }
+
+    /// Bring up an interface or BSS, returning a client for it.
+    pub async fn add(&self, interface: InterfaceAdd) -> Result<RequestClient> {
+        let client = self.interface(interface.ifname())?;
+        self.request(|response| GlobalRequest::Add(interface, response))
+            .await?;
+        Ok(client)
+    }
+
+    /// Tear down an interface or BSS.
+    pub async fn remove(&self, ifname: &str) -> Result {
+        check_token("ifname", ifname)?;
+        self.request(|response| GlobalRequest::Remove(ifname.into(), response))
+            .await
+    }
+
+    /// `FST-ATTACH <ifname> <group_id>`: add an interface to a Fast Session
+    /// Transfer group.
+    pub async fn fst_attach(&self, ifname: &str, group_id: &str) -> Result<String> {
+        check_token("ifname", ifname)?;
+        check_token("group_id", group_id)?;
+        self.fst(format!("FST-ATTACH {ifname} {group_id}")).await
+    }
+
+    /// `FST-DETACH <ifname>`: remove an interface from its FST group.
+    pub async fn fst_detach(&self, ifname: &str) -> Result<String> {
+        check_token("ifname", ifname)?;
+        self.fst(format!("FST-DETACH {ifname}")).await
+    }
+
+    /// `FST-MANAGER <command>`, e.g. `list_groups`; the reply is returned
+    /// as-is.
+    pub async fn fst_manager(&self, command: &str) -> Result<String> {
+        self.fst(format!("FST-MANAGER {command}")).await
+    }
+
+    async fn fst(&self, command: String) -> Result<String> {
+        self.request(|response| GlobalRequest::Fst(command, response))
+            .await
+    }
+
+    /// A [`RequestClient`] for an interface or BSS. Its requests share the
+    /// global socket, and its events arrive on the global broadcast channel
+    /// tagged with `ifname`.
+    pub fn interface(&self, ifname: &str) -> Result<RequestClient> {
+        check_token("ifname", ifname)?;
+        Ok(RequestClient::for_interface(self.sender.clone(), ifname))
+    }
+
+    pub async fn shutdown(&self) -> Result {
+        Ok(self.sender.send(GlobalRequest::Shutdown).await?)
+    }
+}
+
+/// An access point [`Broadcast`] from the global runner, tagged with the
+/// interface it came from. `ifname` is `None` for events that aren't about one
+/// interface, such as [`Broadcast::Ready`].
+#[derive(Debug, Clone)]
+pub struct InterfaceBroadcast {
+    pub ifname: Option<String>,
+    pub broadcast: Broadcast,
+}
+
+/// Channel for broadcasting events from every interface of the global runner.
+pub type GlobalBroadcastReceiver = broadcast::Receiver<InterfaceBroadcast>;
+
+/// Setup struct for the hostapd global control interface process.
+pub struct GlobalSetup {
+    /// Struct for handling runtime process
+    wifi: GlobalAp,
+    /// Client for making requests
+    global_client: GlobalClient,
+}
+
+impl GlobalSetup {
+    pub fn new() -> Self {
+        Self::with_capacities(DEFAULT_CHANNEL_SIZE, DEFAULT_CHANNEL_SIZE)
+    }
+
+    /// Like [`Self::new`] but with explicit request and broadcast channel
+    /// capacities (both default to 32).
+    pub fn with_capacities(request_channel_size: usize, broadcast_channel_size: usize) -> Self {
+        let (self_sender, request_receiver) = mpsc::channel(request_channel_size);
+        let global_client = GlobalClient {
+            sender: self_sender.clone(),
+        };
+        let broadcast_sender = broadcast::Sender::new(broadcast_channel_size);
+
+        Self {
+            wifi: GlobalAp {
+                control_interface: ControlInterface::Unix(PATH_DEFAULT_GLOBAL.into()),
+                attach_options: vec![],
+                request_receiver,
+                broadcast_sender,
+                self_sender,
+                command_timeout: DEFAULT_COMMAND_TIMEOUT,
+                attach_retries: DEFAULT_ATTACH_RETRIES,
+                attach_retry_delay: DEFAULT_ATTACH_RETRY_DELAY,
+                keepalive_interval: None,
+                countries: HashMap::new(),
+            },
+            global_client,
+        }
+    }
+
+    pub fn set_socket_path<S: Into<std::path::PathBuf>>(&mut self, path: S) {
+        self.wifi.control_interface = ControlInterface::Unix(path.into());
+    }
+
+    /// Connect through `control_interface` instead of the default Unix socket.
+    pub fn set_control_interface(&mut self, control_interface: ControlInterface) {
+        self.wifi.control_interface = control_interface;
+    }
+
+    pub fn add_attach_options(&mut self, options: &[&str]) {
+        for o in options {
+            self.wifi.attach_options.push(o.to_string());
+        }
+    }
+
+    /// Set how long to wait for a reply to a control command/request before
+    /// giving up with [`ClientError::Timeout`].
+    pub fn set_command_timeout(&mut self, timeout: Duration) {
+        self.wifi.command_timeout = timeout;
+    }
+
+    /// Set how many times to retry the `ATTACH` handshake before giving up
+    /// with [`SocketError::AttachFailed`](crate::error::SocketError::AttachFailed).
+    pub fn set_attach_retries(&mut self, retries: usize) {
+        self.wifi.attach_retries = retries;
+    }
+
+    /// Set how long to wait between attach handshake attempts.
+    pub fn set_attach_retry_delay(&mut self, delay: Duration) {
+        self.wifi.attach_retry_delay = delay;
+    }
+
+    /// Periodically `PING` the event socket and `DETACH` and re-attach it if
+    /// the daemon stops answering, broadcasting [`Broadcast::Reattached`]
+    /// with no `ifname`. As with [`WifiSetup::set_keepalive_interval`], a
+    /// `PONG` doesn't prove the socket is still attached. Off by default.
+    pub fn set_keepalive_interval(&mut self, interval: Duration) {
+        self.wifi.keepalive_interval = Some(interval);
+    }
+
+    pub fn get_broadcast_receiver(&self) -> GlobalBroadcastReceiver {
+        self.wifi.broadcast_sender.subscribe()
+    }
+
+    pub fn get_global_client(&self) -> GlobalClient {
+        self.global_client.clone()
+    }
+
+    pub fn complete(self) -> GlobalAp {
+        self.wifi
+    }
+}
+
+impl Default for GlobalSetup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Instance that runs the hostapd global control interface process
+pub struct GlobalAp {
+    /// Path to the global socket
+    control_interface: ControlInterface,
+    /// Options to pass to the hostapd attach command
+    attach_options: Vec<String>,
+    /// Channel for receiving requests
+    request_receiver: mpsc::Receiver<GlobalRequest>,
+    /// Channel for broadcasting alerts
+    broadcast_sender: broadcast::Sender<InterfaceBroadcast>,
+    /// Channel for sending requests to itself
+    self_sender: mpsc::Sender<GlobalRequest>,
+    /// How long to wait for a reply to a control command/request
+    command_timeout: Duration,
+    /// How many times to retry the ATTACH handshake before giving up
+    attach_retries: usize,
+    /// How long to wait between attach handshake attempts
+    attach_retry_delay: Duration,
+    /// How often to check the event socket is still attached, if at all
+    keepalive_interval: Option<Duration>,
+    /// The country last set on each interface, which hostapd can't report
+    countries: HashMap<String, CountryCode>,
+}
+
+impl GlobalAp {
+    pub async fn run(&mut self) -> SocketResult {
+        info!("Starting Wifi AP global control process");
+        // Attached to the global socket, hostapd forwards the events of every
+        // interface with an IFNAME= prefix
+        let (mut deferred_requests, event_socket) = EventSocket::new(
+            &self.control_interface,
+            &mut self.request_receiver,
+            &self.attach_options,
+            None,
+            self.command_timeout,
+            self.attach_retries,
+            self.attach_retry_delay,
+        )
+        .await?;
+        let (socket_handle, next_deferred_requests) = SocketHandle::open(
+            &self.control_interface,
+            "mapper_hostapd_global_sync.sock",
+            &mut self.request_receiver,
+            self.command_timeout,
+        )
+        .await?;
+        deferred_requests.extend(next_deferred_requests);
+        for request in deferred_requests {
+            self.self_sender
+                .send(request)
+                .await
+                .expect("self_sender should never close as same struct owns both ends");
+        }
+        self.broadcast(None, Broadcast::Ready);
+        self.run_internal(event_socket, socket_handle).await
+    }
+
+    fn broadcast(&self, ifname: Option<String>, broadcast: Broadcast) {
+        let event = InterfaceBroadcast { ifname, broadcast };
+        if self.broadcast_sender.send(event).is_err() {
+            debug!("broadcast listener closed")
+        }
+    }
+
+    async fn run_internal(
+        &mut self,
+        mut event_socket: EventSocket,
+        mut socket_handle: SocketHandle<2048>,
+    ) -> SocketResult {
+        enum EventOrRequest {
+            Event(Option<String>, Event),
+            Request(Option<GlobalRequest>),
+            Keepalive,
+        }
+
+        let mut keepalive = Keepalive::new(self.keepalive_interval);
+        loop {
+            // events that arrived on the request socket in place of a reply
+            let event_or_request = if let Some(msg) = socket_handle.take_unsolicited() {
+                let (ifname, event) = EventSocket::parse(&msg);
+                EventOrRequest::Event(ifname, event)
+            } else {
+                tokio::select!(
+                    event = event_socket.recv_tagged() => {
+                        let (ifname, event) = event?;
+                        EventOrRequest::Event(ifname, event)
+                    },
+                    request = self.request_receiver.recv() => EventOrRequest::Request(request),
+                    _ = keepalive.due() => EventOrRequest::Keepalive,
+                )
+            };
+            match event_or_request {
+                EventOrRequest::Event(ifname, event) => self.broadcast(ifname, event.into()),
+                EventOrRequest::Request(request) => match request {
+                    Some(request) if request.is_shutdown() => return Ok(()),
+                    Some(request) => {
+                        Self::handle_request(&mut socket_handle, &mut self.countries, request)
+                            .await?
+                    }
+                    None => return Err(error::SocketError::ClientChannelClosed),
+                },
+                EventOrRequest::Keepalive => {
+                    if event_socket.keepalive().await? {
+                        self.broadcast(None, Broadcast::Reattached);
+                    }
+                }
+            }
+        }
+    }
+
+    async fn handle_request<const N: usize>(
+        socket_handle: &mut SocketHandle<N>,
+        countries: &mut HashMap<String, CountryCode>,
+        request: GlobalRequest,
+    ) -> SocketResult {
+        match request {
+            GlobalRequest::Interfaces(response) => {
+                debug!("Handling global request: Interfaces");
+                let _ = response.send(
+                    socket_handle
+                        .request("INTERFACES", parse_interfaces)
+                        .await?,
+                );
+            }
+            GlobalRequest::Add(interface, response) => {
+                debug!("Handling global request: Add({interface:?})");
+                match interface.to_command() {
+                    Ok(cmd) => {
+                        let _ = response.send(socket_handle.command(cmd.as_bytes()).await?);
+                    }
+                    Err(e) => {
+                        let _ = response.send(Err(e));
+                    }
+                }
+            }
+            GlobalRequest::Remove(ifname, response) => {
+                debug!("Handling global request: Remove({ifname:?})");
+                let cmd = format!("REMOVE {ifname}");
+                let removed = socket_handle.command(cmd.as_bytes()).await?;
+                if removed.is_ok() {
+                    countries.remove(&ifname);
+                }
+                let _ = response.send(removed);
+            }
+            GlobalRequest::Fst(cmd, response) => {
+                debug!("Handling global request: {cmd:?}");
+                let _ = response.send(socket_handle.request(&cmd, TryInto::try_into).await?);
+            }
+            GlobalRequest::Interface(ifname, Request::Shutdown) => {
+                debug!("Dropping the state of {ifname}");
+                countries.remove(&ifname);
+            }
+            // logged by WifiAp::handle_request, which redacts secrets
+            GlobalRequest::Interface(ifname, request) => {
+                let mut country = countries.get(&ifname).copied();
+                socket_handle.set_interface(Some(&ifname));
+                let handled = WifiAp::handle_request(socket_handle, &mut country, request).await;
+                socket_handle.set_interface(None);
+                if let Some(country) = country {
+                    countries.insert(ifname, country);
+                }
+                handled?
+            }
+            GlobalRequest::Shutdown => (), //shutdown is handled at the scope above
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::socket_handle::tests::FakeDaemon;
+
+    #[test]
+    fn add_bss_command() {
+        let add = InterfaceAdd::Bss {
+            ifname: "wlan0_guest".into(),
+            phy: "phy0".into(),
+            config_file: "/etc/hostapd/guest.conf".into(),
+        };
+        assert_eq!(
+            add.to_command().unwrap(),
+            "ADD bss_config=phy0:/etc/hostapd/guest.conf"
+        );
+    }
+
+    #[test]
+    fn add_interface_command() {
+        let add = InterfaceAdd::Interface {
+            ifname: "wlan1".into(),
+            config_file: "/etc/hostapd/wlan1.conf".into(),
+        };
+        assert_eq!(
+            add.to_command().unwrap(),
+            "ADD wlan1 config=/etc/hostapd/wlan1.conf"
+        );
+    }
+
+    #[test]
+    fn add_rejects_ambiguous_fields() {
+        let add = InterfaceAdd::Bss {
+            ifname: "wlan0_guest".into(),
+            phy: "phy0:x".into(),
+            config_file: "/etc/hostapd/guest.conf".into(),
+        };
+        assert!(matches!(
+            add.to_command(),
+            Err(ClientError::InvalidParameter("phy"))
+        ));
+        let add = InterfaceAdd::Interface {
+            ifname: "wlan 1".into(),
+            config_file: "/etc/hostapd/wlan1.conf".into(),
+        };
+        assert!(matches!(
+            add.to_command(),
+            Err(ClientError::InvalidParameter("ifname"))
+        ));
+    }
+
+    #[tokio::test]
+    async fn interfaces_are_added_driven_and_removed() {
+        let mut daemon = FakeDaemon::start(|command| match command {
+            "INTERFACES" => Some("wlan0\nwlan0_guest\n".into()),
+            _ => Some("OK\n".into()),
+        });
+        let mut setup = GlobalSetup::new();
+        setup.set_socket_path(&daemon.path);
+        let global_client = setup.get_global_client();
+        let mut runner = setup.complete();
+        let runner = tokio::spawn(async move { runner.run().await });
+
+        let guest = global_client
+            .add(InterfaceAdd::Bss {
+                ifname: "wlan0_guest".into(),
+                phy: "phy0".into(),
+                config_file: "/etc/hostapd/guest.conf".into(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            daemon.next_command().await,
+            "ADD bss_config=phy0:/etc/hostapd/guest.conf"
+        );
+        guest.set_country("de".parse().unwrap()).await.unwrap();
+        assert_eq!(
+            daemon.next_command().await,
+            "IFNAME=wlan0_guest SET country_code DE"
+        );
+        assert_eq!(
+            daemon.next_command().await,
+            "IFNAME=wlan0_guest SET ieee80211d 1"
+        );
+        assert_eq!(guest.get_country().await.unwrap().unwrap().as_str(), "DE");
+
+        // shutting down one BSS's client leaves the runner serving the others
+        guest.shutdown().await.unwrap();
+        assert_eq!(
+            global_client.interfaces().await.unwrap(),
+            ["wlan0", "wlan0_guest"]
+        );
+        assert_eq!(daemon.next_command().await, "INTERFACES");
+        global_client.remove("wlan0_guest").await.unwrap();
+        assert_eq!(daemon.next_command().await, "REMOVE wlan0_guest");
+        assert!(matches!(
+            global_client.interface("wlan0 guest"),
+            Err(ClientError::InvalidParameter("ifname"))
+        ));
+
+        global_client.shutdown().await.unwrap();
+        assert!(runner.await.unwrap().is_ok());
+    }
+}
@@ -1,23 +1,35 @@
 use super::*;
 
+use tokio::time::{Duration, Instant};
+
 mod types;
 pub use types::*;
 
+mod event;
+pub use event::*;
+
 mod client;
 pub use client::*;
 
 mod setup;
 pub use setup::*;
 
+mod global;
+pub use global::*;
+
 mod event_socket;
 use event_socket::*;
 
+/// Synchronous client for programs without a tokio runtime
+#[cfg(feature = "blocking")]
+pub mod blocking;
+
 const PATH_DEFAULT_SERVER: &str = "/var/run/hostapd/wlan1";
 
 /// Instance that runs the Wifi process
 pub struct WifiAp {
     /// Path to the socket
-    socket_path: std::path::PathBuf,
+    control_interface: ControlInterface,
     /// Options to pass to the hostapd attach command
     attach_options: Vec<String>,
     /// Channel for receiving requests
@@ -27,20 +39,26 @@ pub struct WifiAp {
     /// Channel for sending requests to itself
     self_sender: mpsc::Sender<Request>,
     /// How long to wait for a reply to a control command/request
-    command_timeout: std::time::Duration,
+    command_timeout: Duration,
     /// How many times to retry the ATTACH/LOG_LEVEL handshake before giving up
     attach_retries: usize,
     /// How long to wait between attach handshake attempts
-    attach_retry_delay: std::time::Duration,
+    attach_retry_delay: Duration,
+    /// How often to check the event socket is still attached, if at all
+    keepalive_interval: Option<Duration>,
+    /// The country last set with [`Request::SetCountry`], which hostapd can't
+    /// report
+    country: Option<CountryCode>,
 }
 
 impl WifiAp {
     pub async fn run(&mut self) -> SocketResult {
         info!("Starting Wifi AP process");
         let (mut deferred_requests, event_socket) = EventSocket::new(
-            &self.socket_path,
+            &self.control_interface,
             &mut self.request_receiver,
             &self.attach_options,
+            Some("DEBUG"),
             self.command_timeout,
             self.attach_retries,
             self.attach_retry_delay,
@@ -49,7 +67,7 @@ impl WifiAp {
         // We start up a separate socket for receiving the "unexpected" events that
         // gets forwarded to us via the event_receiver
         let (socket_handle, next_deferred_requests) = SocketHandle::open(
-            &self.socket_path,
+            &self.control_interface,
             "mapper_hostapd_sync.sock",
             &mut self.request_receiver,
             self.command_timeout,
@@ -80,34 +98,62 @@ impl WifiAp {
         enum EventOrRequest {
             Event(Event),
             Request(Option<Request>),
+            Keepalive,
         }
 
+        let mut keepalive = Keepalive::new(self.keepalive_interval);
         loop {
-            let event_or_request = tokio::select!(
-                event = event_socket.recv() => EventOrRequest::Event(event?),
-                request = self.request_receiver.recv() => EventOrRequest::Request(request),
-            );
+            // events that arrived on the request socket in place of a reply
+            let event_or_request = if let Some(msg) = socket_handle.take_unsolicited() {
+                EventOrRequest::Event(EventSocket::parse(&msg).1)
+            } else {
+                tokio::select!(
+                    event = event_socket.recv() => EventOrRequest::Event(event?),
+                    request = self.request_receiver.recv() => EventOrRequest::Request(request),
+                    _ = keepalive.due() => EventOrRequest::Keepalive,
+                )
+            };
             match event_or_request {
                 EventOrRequest::Event(event) => self.handle_event(event),
                 EventOrRequest::Request(request) => match request {
                     Some(Request::Shutdown) => return Ok(()),
-                    Some(request) => Self::handle_request(&mut socket_handle, request).await?,
+                    Some(request) => {
+                        Self::handle_request(&mut socket_handle, &mut self.country, request).await?
+                    }
                     None => return Err(error::SocketError::ClientChannelClosed),
                 },
+                EventOrRequest::Keepalive => {
+                    if event_socket.keepalive().await? {
+                        self.broadcast(Broadcast::Reattached);
+                    }
+                }
             }
         }
     }
 
     fn handle_event(&self, event_msg: Event) {
-        match event_msg {
-            Event::ApStaConnected(mac) => self.broadcast(Broadcast::Connected(mac)),
-            Event::ApStaDisconnected(mac) => self.broadcast(Broadcast::Disconnected(mac)),
-            Event::Unknown(msg) => self.broadcast(Broadcast::UnknownEvent(msg)),
-        };
+        self.broadcast(event_msg.into())
     }
 
     async fn handle_request<const N: usize>(
         socket_handle: &mut SocketHandle<N>,
+        country: &mut Option<CountryCode>,
+        request: Request,
+    ) -> SocketResult {
+        let (deadline, request) = request.split_deadline();
+        if request.is_abandoned() {
+            debug!("Skipping request abandoned by its client");
+            return Ok(());
+        }
+        socket_handle.set_deadline(deadline);
+        let handled = Self::send_request(socket_handle, country, request).await;
+        socket_handle.set_deadline(None);
+        handled
+    }
+
+    async fn send_request<const N: usize>(
+        socket_handle: &mut SocketHandle<N>,
+        country: &mut Option<CountryCode>,
         request: Request,
     ) -> SocketResult {
         // A SetValue value may be a secret (e.g. wpa_passphrase), so keep it out
@@ -148,8 +194,64 @@ impl WifiAp {
                 let _ =
                     response_channel.send(socket_handle.command(request_string.as_bytes()).await?);
             }
+            Request::SetCountry(new_country, response_channel) => {
+                // hostapd's country_code only takes a pair of letters
+                if new_country.is_world() {
+                    let _ =
+                        response_channel.send(Err(error::ClientError::InvalidParameter("country")));
+                    return Ok(());
+                }
+                let cmd = format!("SET country_code {new_country}");
+                let set = match socket_handle.command(cmd.as_bytes()).await? {
+                    Ok(()) => socket_handle.command(b"SET ieee80211d 1").await?,
+                    Err(e) => Err(e),
+                };
+                if set.is_ok() {
+                    *country = Some(new_country);
+                }
+                let _ = response_channel.send(set);
+            }
+            Request::GetCountry(response_channel) => {
+                let _ = response_channel.send(Ok(*country));
+            }
+            // unwrapped by handle_request
+            Request::WithDeadline(..) => (),
             Request::Shutdown => (), //shutdown is handled at the scope above
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::socket_handle::tests::{connect, serve};
+
+    #[tokio::test]
+    async fn country_is_remembered_and_world_is_rejected() {
+        let (_dir, daemon, mut handle) = connect(Duration::from_secs(1)).await;
+        let mut country = None;
+        let (world, world_set) = oneshot::channel();
+        let (germany, germany_set) = oneshot::channel();
+        let (get, got) = oneshot::channel();
+        let requests = async {
+            for request in [
+                Request::SetCountry(CountryCode::WORLD, world),
+                Request::SetCountry("de".parse().unwrap(), germany),
+                Request::GetCountry(get),
+            ] {
+                WifiAp::handle_request(&mut handle, &mut country, request)
+                    .await
+                    .unwrap();
+            }
+        };
+        let ((), received) = tokio::join!(requests, serve(&daemon, &[Some("OK\n"); 2]));
+        assert_eq!(received, ["SET country_code DE", "SET ieee80211d 1"]);
+        assert!(matches!(
+            world_set.await.unwrap(),
+            Err(error::ClientError::InvalidParameter("country"))
+        ));
+        assert!(germany_set.await.unwrap().is_ok());
+        assert_eq!(got.await.unwrap().unwrap().unwrap().as_str(), "DE");
+    }
+}
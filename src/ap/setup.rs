@@ -5,17 +5,17 @@ use std::time::Duration;
 /// Default time to wait for a reply to a control command/request before giving
 /// up. Chosen to comfortably cover slower hostapd operations while still
 /// unblocking the single-task runtime if a reply never arrives.
-const DEFAULT_COMMAND_TIMEOUT: Duration = Duration::from_secs(3);
+pub(crate) const DEFAULT_COMMAND_TIMEOUT: Duration = Duration::from_secs(3);
 
 /// Default number of `ATTACH`/`LOG_LEVEL` handshake attempts. With
 /// [`DEFAULT_ATTACH_RETRY_DELAY`] between tries this bounds the wait to roughly
 /// a minute, unlike the socket-open path which retries for 5 minutes.
-const DEFAULT_ATTACH_RETRIES: usize = 240;
+pub(crate) const DEFAULT_ATTACH_RETRIES: usize = 240;
 /// Default delay between attach handshake attempts.
-const DEFAULT_ATTACH_RETRY_DELAY: Duration = Duration::from_millis(250);
+pub(crate) const DEFAULT_ATTACH_RETRY_DELAY: Duration = Duration::from_millis(250);
 
 /// Default capacity of the request and broadcast channels.
-const DEFAULT_CHANNEL_SIZE: usize = 32;
+pub(crate) const DEFAULT_CHANNEL_SIZE: usize = 32;
 
 /// Setup struct for the WiFiAp process.
 pub struct WifiSetup {
@@ -41,7 +41,7 @@ impl WifiSetup {
 
         Self {
             wifi: WifiAp {
-                socket_path: PATH_DEFAULT_SERVER.into(),
+                control_interface: ControlInterface::Unix(PATH_DEFAULT_SERVER.into()),
                 attach_options: vec![],
                 request_receiver,
                 broadcast_sender,
@@ -49,13 +49,22 @@ impl WifiSetup {
                 command_timeout: DEFAULT_COMMAND_TIMEOUT,
                 attach_retries: DEFAULT_ATTACH_RETRIES,
                 attach_retry_delay: DEFAULT_ATTACH_RETRY_DELAY,
+                keepalive_interval: None,
+                country: None,
             },
             request_client,
         }
     }
 
     pub fn set_socket_path<S: Into<std::path::PathBuf>>(&mut self, path: S) {
-        self.wifi.socket_path = path.into();
+        self.wifi.control_interface = ControlInterface::Unix(path.into());
+    }
+
+    /// Connect through `control_interface` instead of the default Unix
+    /// socket, e.g. `ControlInterface::Udp` for a hostapd built with
+    /// `CONFIG_CTRL_IFACE=udp` (listening on `127.0.0.1:8877` by default).
+    pub fn set_control_interface(&mut self, control_interface: ControlInterface) {
+        self.wifi.control_interface = control_interface;
     }
 
     pub fn add_attach_options(&mut self, options: &[&str]) {
@@ -82,9 +91,25 @@ impl WifiSetup {
         self.wifi.attach_retry_delay = delay;
     }
 
+    /// Periodically `PING` the event socket and `DETACH` and re-attach it if
+    /// the daemon stops answering, broadcasting [`Broadcast::Reattached`].
+    /// The daemon still answers a monitor it dropped after failing to
+    /// deliver events to it, so this recovers from a stalled or restarted
+    /// daemon, not from that. Off by default.
+    pub fn set_keepalive_interval(&mut self, interval: Duration) {
+        self.wifi.keepalive_interval = Some(interval);
+    }
+
     pub fn get_broadcast_receiver(&self) -> BroadcastReceiver {
         self.wifi.broadcast_sender.subscribe()
     }
+
+    /// Like [`Self::get_broadcast_receiver`], as a [`Stream`](tokio_stream::Stream)
+    /// that can be filtered and reports lag as [`Broadcast::Lagged`].
+    pub fn event_stream(&self) -> EventStream {
+        EventStream::new(self.wifi.broadcast_sender.subscribe())
+    }
+
     pub fn get_request_client(&self) -> RequestClient {
         self.request_client.clone()
     }
@@ -1,4 +1,5 @@
 use super::config::ConfigError;
+use crate::MacAddr;
 use serde::{Deserialize, Serialize};
 
 /// Status of the WiFi Access Point
@@ -37,7 +38,7 @@ pub struct Status {
     #[serde(default)]
     pub bss: Vec<String>,
     #[serde(default)]
-    pub bssid: Vec<String>,
+    pub bssid: Vec<MacAddr>,
     #[serde(default)]
     pub ssid: Vec<String>,
     #[serde(default)]
@@ -89,16 +90,22 @@ impl Status {
     /// assert_eq!(status.freq, 2437);
     /// assert_eq!(status.ssid, vec![r"WiFi-SSID", r#"¯\_(ツ)_/¯"#]);
     /// assert_eq!(status.num_sta, vec![0, 1]);
+    /// assert_eq!(status.bssid[1].to_string(), "cc:7b:5c:4d:ff:5c");
     /// ```
     pub fn from_response(response: &str) -> Result<Self, ConfigError> {
         crate::config::from_str(response)
     }
+
+    /// Encode as hostapd would send it, the inverse of [`Self::from_response`].
+    pub fn to_response(&self) -> Result<String, ConfigError> {
+        crate::config::to_string(self)
+    }
 }
 
 /// Configuration of the WiFi station
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Config {
-    pub bssid: String,
+    pub bssid: MacAddr,
     pub ssid: String,
     pub wps_state: String,
     #[serde(default)] // missing if zero
@@ -132,6 +139,11 @@ impl Config {
     pub fn from_response(response: &str) -> Result<Self, ConfigError> {
         crate::config::from_str(response)
     }
+
+    /// Encode as hostapd would send it, the inverse of [`Self::from_response`].
+    pub fn to_response(&self) -> Result<String, ConfigError> {
+        crate::config::to_string(self)
+    }
 }
 
 #[cfg(test)]
@@ -193,6 +205,35 @@ rsn_pairwise_cipher=CCMP
         assert_eq!(config.ssid, "MY_SSID");
     }
 
+    #[test]
+    fn test_status_round_trips_through_response() {
+        let resp = r#"
+state=ENABLED
+phy=phy0
+freq=2437
+cac_time_left_seconds=N/A
+channel=6
+supported_rates=02 04 0b 16
+bss[0]=wlan0
+bssid[0]=cc:7b:5c:1a:d2:21
+ssid[0]=WiFi-SSID
+num_sta[0]=0
+bss[1]=wlan1
+bssid[1]=cc:7b:5c:4d:ff:5c
+ssid[1]=\xc2\xaf\\_(\xe3\x83\x84)_/\xc2\xaf
+num_sta[1]=1
+        "#;
+        let status = Status::from_response(resp).unwrap();
+        let encoded = status.to_response().unwrap();
+        assert!(encoded.contains("ssid[1]=\\xc2\\xaf\\\\_(\\xe3\\x83\\x84)_/\\xc2\\xaf\n"));
+        // N/A is read as None, which is left out rather than written back
+        assert!(!encoded.contains("cac_time_left_seconds"));
+        let reparsed = Status::from_response(&encoded).unwrap();
+        assert_eq!(reparsed.ssid, status.ssid);
+        assert_eq!(reparsed.num_sta, vec![0, 1]);
+        assert_eq!(reparsed.channel, Some(6));
+    }
+
     #[test]
     fn test_config_open() {
         let resp = r#"
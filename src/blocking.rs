@@ -0,0 +1,130 @@
+use super::*;
+use error::{BlockingError, ClientError, ParseError, SocketError};
+use socket_handle::{is_event, parse_reply};
+use std::collections::VecDeque;
+use std::io::ErrorKind;
+use std::os::unix::net::UnixDatagram;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Synchronous counterpart of [`SocketHandle`], for the blocking clients.
+pub(crate) struct BlockingSocket {
+    #[allow(unused)]
+    /// Temporary directory for socket. If it drops, socket breaks.
+    tmp_dir: tempfile::TempDir,
+    socket: UnixDatagram,
+    buffer: Vec<u8>,
+    /// How long to wait for a reply before giving up on a command/request
+    command_timeout: Duration,
+    /// Event datagrams that turned up while waiting for a reply
+    unsolicited: VecDeque<String>,
+}
+
+impl BlockingSocket {
+    pub(crate) fn open(
+        path: &Path,
+        label: &str,
+        command_timeout: Duration,
+    ) -> BlockingResult<Self> {
+        let tmp_dir = tempfile::tempdir()?;
+        let socket = UnixDatagram::bind(tmp_dir.path().join(label))?;
+        socket.connect(path)?;
+        Ok(Self {
+            tmp_dir,
+            socket,
+            buffer: vec![0; 10240],
+            command_timeout,
+            unsolicited: VecDeque::new(),
+        })
+    }
+
+    pub(crate) fn set_command_timeout(&mut self, timeout: Duration) {
+        self.command_timeout = timeout;
+    }
+
+    fn send(&self, cmd: &[u8]) -> BlockingResult {
+        let n = self.socket.send(cmd)?;
+        if n != cmd.len() {
+            return Err(ClientError::DidNotWriteAllBytes(n, cmd.len()).into());
+        }
+        Ok(())
+    }
+
+    /// Receive a datagram, waiting until `deadline` or forever for `None`.
+    fn recv_until(&mut self, deadline: Option<Instant>) -> BlockingResult<usize> {
+        let timeout = match deadline {
+            Some(deadline) => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    return Err(ClientError::Timeout.into());
+                }
+                Some(remaining)
+            }
+            None => None,
+        };
+        self.socket.set_read_timeout(timeout)?;
+        match self.socket.recv(&mut self.buffer) {
+            Ok(n) => Ok(n),
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                Err(ClientError::Timeout.into())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub(crate) fn command(&mut self, cmd: &str) -> BlockingResult {
+        self.command_matching(cmd, |data| data == "OK")
+    }
+
+    /// Like [`Self::command`] but with a custom set of accepted responses.
+    pub(crate) fn command_matching(
+        &mut self,
+        cmd: &str,
+        accept: impl Fn(&str) -> bool,
+    ) -> BlockingResult {
+        self.request(cmd, |data| {
+            if accept(data) {
+                Ok(())
+            } else {
+                Err(ParseError::NotOK)
+            }
+        })
+    }
+
+    /// Send `req` and parse the reply, setting aside any events that arrive
+    /// first, as [`SocketHandle::request`] does.
+    pub(crate) fn request<'a, T, E, F>(&'a mut self, req: &str, parse: F) -> BlockingResult<T>
+    where
+        ParseError: From<E>,
+        F: FnOnce(&'a str) -> std::result::Result<T, E>,
+    {
+        self.send(req.as_bytes())?;
+        let deadline = Instant::now() + self.command_timeout;
+        let n = loop {
+            let n = self.recv_until(Some(deadline))?;
+            if !is_event(&self.buffer[..n]) {
+                break n;
+            }
+            let event = String::from_utf8_lossy(&self.buffer[..n]).into_owned();
+            debug!("Event in place of a reply: {event}");
+            self.unsolicited.push_back(event);
+        };
+        Ok(parse_reply(&self.buffer[..n], parse)?)
+    }
+
+    /// The next event datagram, waiting until `deadline` or forever for
+    /// `None`.
+    pub(crate) fn recv_event(&mut self, deadline: Option<Instant>) -> BlockingResult<String> {
+        if let Some(event) = self.unsolicited.pop_front() {
+            return Ok(event);
+        }
+        let n = self.recv_until(deadline)?;
+        Ok(String::from_utf8_lossy(&self.buffer[..n]).into_owned())
+    }
+}
+
+impl From<std::io::Error> for BlockingError {
+    fn from(e: std::io::Error) -> Self {
+        BlockingError::Socket(SocketError::Io(e))
+    }
+}
@@ -5,12 +5,18 @@ use serde::de::value::MapDeserializer;
 use serde::de::{self, Error, IntoDeserializer, Visitor};
 use serde::{Deserialize, forward_to_deserialize_any};
 
+mod ser;
+pub use ser::to_string;
+
+mod supplicant_conf;
+pub use supplicant_conf::*;
+
 type Result<T> = std::result::Result<T, ConfigError>;
 
 #[derive(Debug, thiserror::Error, PartialEq, Eq, Clone)]
 pub enum ConfigError {
-    #[error("Missing '=' delimiter in config line")]
-    MissingDelimiterEqual,
+    #[error("line {0}: missing '=' delimiter")]
+    MissingDelimiterEqual(usize),
     #[error("escape code is not made up of valid hex code")]
     InvalidEscape,
     #[error("escape code is incomplete")]
@@ -19,6 +25,18 @@ pub enum ConfigError {
     NonUtf8Escape,
     #[error("Value could not be decoded")]
     SerdeError(String),
+    #[error("line {0}: quoted value is missing its closing '\"'")]
+    UnterminatedQuote(usize),
+    #[error("line {0}: block is never closed")]
+    UnterminatedBlock(usize),
+    #[error("line {0}: '}}' without an open block")]
+    UnexpectedBlockEnd(usize),
+    #[error("line {0}: blocks cannot be nested")]
+    NestedBlock(usize),
+    #[error("line {0}: only network, cred and blob-base64 blocks are supported")]
+    UnknownBlock(usize),
+    #[error("missing or invalid {0} field")]
+    InvalidField(String),
 }
 
 impl Error for ConfigError {
@@ -53,15 +71,19 @@ impl<'de> IntoDeserializer<'de, ConfigError> for Deserializer<'de> {
     }
 }
 
+/// Deserialize the `key=value` response format used by hostapd and
+/// wpa_supplicant (e.g. `STATUS`, `GET_CONFIG`). Values are [`unprintf`]ed and
+/// indexed keys like `bss[0]=` are collected into sequences. The inverse is
+/// [`to_string`].
 pub fn from_str<'a, T>(s: &'a str) -> Result<T>
 where
     T: Deserialize<'a>,
 {
     let mut map: HashMap<&str, Deserializer<'_>> = HashMap::new();
-    for line in s.trim().lines() {
+    for (number, line) in s.trim().lines().enumerate() {
         let (k, v) = line
             .split_once('=')
-            .ok_or(ConfigError::MissingDelimiterEqual)?;
+            .ok_or(ConfigError::MissingDelimiterEqual(number + 1))?;
         let (k, i) = if let Some((k, i)) = k.split_once('[') {
             if let Some((i, "")) = i.rsplit_once(']') {
                 (k, i.parse().map_err(ConfigError::custom)?)
@@ -163,7 +185,46 @@ impl<'de> de::Deserializer<'de> for Deserializer<'de> {
     }
 }
 
-pub(crate) fn unprintf(escaped: &str) -> std::result::Result<String, ConfigError> {
+/// convert to wpa config format, ideally a "quoted string"
+/// in case of new-lines, quotes or emoji fall back to hex encoding the whole thing
+pub(crate) fn conf_escape(raw: &str) -> String {
+    if raw.bytes().all(|b| b.is_ascii_graphic() && b != b'"') {
+        format!("\"{raw}\"")
+    } else {
+        hex::encode(raw)
+    }
+}
+
+/// Escape a string the way hostapd/wpa_supplicant's `printf_encode` does
+/// before putting it in a control interface response; the inverse of
+/// [`unprintf`].
+///
+/// ```
+/// # use wifi_ctrl::config::{printf_encode, unprintf};
+/// let encoded = printf_encode("¯\\_(ツ)_/¯");
+/// assert_eq!(encoded, r"\xc2\xaf\\_(\xe3\x83\x84)_/\xc2\xaf");
+/// assert_eq!(unprintf(&encoded).unwrap(), "¯\\_(ツ)_/¯");
+/// ```
+pub fn printf_encode(raw: &str) -> String {
+    let mut encoded = String::with_capacity(raw.len());
+    for b in raw.bytes() {
+        match b {
+            b'"' => encoded.push_str("\\\""),
+            b'\\' => encoded.push_str("\\\\"),
+            b'\x1b' => encoded.push_str("\\e"),
+            b'\n' => encoded.push_str("\\n"),
+            b'\r' => encoded.push_str("\\r"),
+            b'\t' => encoded.push_str("\\t"),
+            0x20..=0x7e => encoded.push(b as char),
+            _ => encoded.push_str(&format!("\\x{b:02x}")),
+        }
+    }
+    encoded
+}
+
+/// Undo hostapd/wpa_supplicant's `printf_encode`, as applied to SSIDs and
+/// other strings in control interface responses.
+pub fn unprintf(escaped: &str) -> std::result::Result<String, ConfigError> {
     let mut bytes = escaped.as_bytes().iter().copied();
     let mut unescaped = vec![];
     // undo "printf_encode"
@@ -208,4 +269,43 @@ mod tests {
         assert_eq!(status.get("state").unwrap(), "ENABLED");
         assert_eq!(status.get("shrug").unwrap(), r#"¯\_(ツ)_/¯"#);
     }
+
+    #[test]
+    fn test_printf_encode_round_trips() {
+        let raw = "quote\" backslash\\ esc\x1b nl\n cr\r tab\t del\x7f ツ";
+        let encoded = printf_encode(raw);
+        assert!(encoded.bytes().all(|b| (0x20..=0x7e).contains(&b)));
+        assert_eq!(unprintf(&encoded).unwrap(), raw);
+    }
+
+    #[test]
+    fn test_serializer() {
+        #[derive(serde::Serialize, Deserialize, Debug, PartialEq)]
+        struct Resp {
+            state: String,
+            freq: u32,
+            cac_time_left_seconds: Option<u64>,
+            ssid: Vec<String>,
+        }
+        let resp = Resp {
+            state: "ENABLED".into(),
+            freq: 2437,
+            cac_time_left_seconds: None,
+            ssid: vec!["WiFi-SSID".into(), r#"¯\_(ツ)_/¯"#.into()],
+        };
+        let encoded = to_string(&resp).unwrap();
+        assert_eq!(
+            encoded,
+            "state=ENABLED\nfreq=2437\nssid[0]=WiFi-SSID\nssid[1]=\\xc2\\xaf\\\\_(\\xe3\\x83\\x84)_/\\xc2\\xaf\n"
+        );
+        assert_eq!(from_str::<Resp>(&encoded).unwrap(), resp);
+    }
+
+    #[test]
+    fn test_serializer_rejects_nested_values() {
+        let nested: HashMap<&str, HashMap<&str, &str>> =
+            HashMap::from([("outer", HashMap::from([("inner", "value")]))]);
+        assert!(to_string(&nested).is_err());
+        assert!(to_string("not a map").is_err());
+    }
 }
@@ -0,0 +1,411 @@
+use super::{ConfigError, Result, printf_encode};
+
+use serde::Serialize;
+use serde::ser::{self, Impossible};
+
+impl ser::Error for ConfigError {
+    fn custom<T>(msg: T) -> Self
+    where
+        T: std::fmt::Display,
+    {
+        Self::SerdeError(msg.to_string())
+    }
+}
+
+/// Serialize a struct or map into the `key=value` response format parsed by
+/// [`from_str`](super::from_str), applying the same rules in reverse:
+///
+/// * strings are [`printf_encode`]d
+/// * `None` fields are left out
+/// * sequences become indexed keys, `key[0]=...`, `key[1]=...`
+///
+/// ```
+/// use std::collections::BTreeMap;
+///
+/// let mut fields = BTreeMap::new();
+/// fields.insert("state", vec!["ENABLED"]);
+/// fields.insert("ssid", vec!["home", "guest\twifi"]);
+/// let resp = wifi_ctrl::config::to_string(&fields)?;
+/// assert_eq!(resp, "ssid[0]=home\nssid[1]=guest\\twifi\nstate[0]=ENABLED\n");
+///
+/// let parsed: BTreeMap<String, Vec<String>> = wifi_ctrl::config::from_str(&resp)?;
+/// assert_eq!(parsed["ssid"], ["home", "guest\twifi"]);
+/// # Ok::<(), wifi_ctrl::config::ConfigError>(())
+/// ```
+pub fn to_string<T>(value: &T) -> Result<String>
+where
+    T: Serialize + ?Sized,
+{
+    let mut serializer = Serializer::default();
+    value.serialize(&mut serializer)?;
+    Ok(serializer.output)
+}
+
+#[derive(Default)]
+struct Serializer {
+    output: String,
+}
+
+impl Serializer {
+    fn write_field(&mut self, key: &str, field: Field) {
+        match field {
+            Field::Skip => (),
+            Field::Value(value) => {
+                self.output.push_str(&format!("{key}={value}\n"));
+            }
+            Field::Seq(values) => {
+                for (i, value) in values.iter().enumerate() {
+                    self.output.push_str(&format!("{key}[{i}]={value}\n"));
+                }
+            }
+        }
+    }
+}
+
+fn top_level_only<T>() -> Result<T> {
+    Err(ConfigError::SerdeError(
+        "only structs and maps can be serialized".to_owned(),
+    ))
+}
+
+macro_rules! top_level_only {
+    ($($func:ident($($arg:ty),*) -> $ok:ty;)*) => {
+        $(
+            fn $func(self, $(_: $arg),*) -> Result<$ok> {
+                top_level_only()
+            }
+        )*
+    };
+}
+
+impl<'a> ser::Serializer for &'a mut Serializer {
+    type Ok = ();
+    type Error = ConfigError;
+
+    type SerializeSeq = Impossible<(), ConfigError>;
+    type SerializeTuple = Impossible<(), ConfigError>;
+    type SerializeTupleStruct = Impossible<(), ConfigError>;
+    type SerializeTupleVariant = Impossible<(), ConfigError>;
+    type SerializeMap = MapSerializer<'a>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), ConfigError>;
+
+    top_level_only! {
+        serialize_bool(bool) -> ();
+        serialize_i8(i8) -> ();
+        serialize_i16(i16) -> ();
+        serialize_i32(i32) -> ();
+        serialize_i64(i64) -> ();
+        serialize_u8(u8) -> ();
+        serialize_u16(u16) -> ();
+        serialize_u32(u32) -> ();
+        serialize_u64(u64) -> ();
+        serialize_f32(f32) -> ();
+        serialize_f64(f64) -> ();
+        serialize_char(char) -> ();
+        serialize_str(&str) -> ();
+        serialize_bytes(&[u8]) -> ();
+        serialize_none() -> ();
+        serialize_unit() -> ();
+        serialize_unit_struct(&'static str) -> ();
+        serialize_unit_variant(&'static str, u32, &'static str) -> ();
+        serialize_seq(Option<usize>) -> Self::SerializeSeq;
+        serialize_tuple(usize) -> Self::SerializeTuple;
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant;
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant;
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<()> {
+        top_level_only()
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Ok(MapSerializer {
+            serializer: self,
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self> {
+        Ok(self)
+    }
+}
+
+impl ser::SerializeStruct for &mut Serializer {
+    type Ok = ();
+    type Error = ConfigError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        let field = value.serialize(FieldSerializer)?;
+        self.write_field(key, field);
+        Ok(())
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+struct MapSerializer<'a> {
+    serializer: &'a mut Serializer,
+    key: Option<String>,
+}
+
+impl ser::SerializeMap for MapSerializer<'_> {
+    type Ok = ();
+    type Error = ConfigError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        match key.serialize(FieldSerializer)? {
+            Field::Value(key) => {
+                self.key = Some(key);
+                Ok(())
+            }
+            _ => Err(ConfigError::SerdeError("keys must be scalars".to_owned())),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| ConfigError::SerdeError("value without a key".to_owned()))?;
+        let field = value.serialize(FieldSerializer)?;
+        self.serializer.write_field(&key, field);
+        Ok(())
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// What a single struct field or map value turns into.
+enum Field {
+    /// `None`: the key is left out, which the parser reads back as `None`
+    Skip,
+    Value(String),
+    /// Written as indexed `key[i]=` lines
+    Seq(Vec<String>),
+}
+
+struct FieldSerializer;
+
+fn nested<T>() -> Result<T> {
+    Err(ConfigError::SerdeError(
+        "nested structs and maps are not supported".to_owned(),
+    ))
+}
+
+macro_rules! serialize_display {
+    ($($func:ident($ty:ty);)*) => {
+        $(
+            fn $func(self, v: $ty) -> Result<Field> {
+                Ok(Field::Value(v.to_string()))
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for FieldSerializer {
+    type Ok = Field;
+    type Error = ConfigError;
+
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = Impossible<Field, ConfigError>;
+    type SerializeMap = Impossible<Field, ConfigError>;
+    type SerializeStruct = Impossible<Field, ConfigError>;
+    type SerializeStructVariant = Impossible<Field, ConfigError>;
+
+    serialize_display! {
+        serialize_bool(bool);
+        serialize_i8(i8);
+        serialize_i16(i16);
+        serialize_i32(i32);
+        serialize_i64(i64);
+        serialize_u8(u8);
+        serialize_u16(u16);
+        serialize_u32(u32);
+        serialize_u64(u64);
+        serialize_f32(f32);
+        serialize_f64(f64);
+    }
+
+    fn serialize_char(self, v: char) -> Result<Field> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Field> {
+        Ok(Field::Value(printf_encode(v)))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Field> {
+        Ok(Field::Value(hex::encode(v)))
+    }
+
+    fn serialize_none(self) -> Result<Field> {
+        Ok(Field::Skip)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Field> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Field> {
+        Ok(Field::Value(String::new()))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Field> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Field> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Field> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Field> {
+        nested()
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer> {
+        Ok(SeqSerializer {
+            values: Vec::with_capacity(len.unwrap_or_default()),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SeqSerializer> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        nested()
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        nested()
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        nested()
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        nested()
+    }
+}
+
+struct SeqSerializer {
+    values: Vec<String>,
+}
+
+impl SeqSerializer {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        match value.serialize(FieldSerializer)? {
+            Field::Value(value) => {
+                self.values.push(value);
+                Ok(())
+            }
+            // an index can't be skipped without renumbering the rest
+            _ => nested(),
+        }
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Field;
+    type Error = ConfigError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Field> {
+        Ok(Field::Seq(self.values))
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Field;
+    type Error = ConfigError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Field> {
+        Ok(Field::Seq(self.values))
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Field;
+    type Error = ConfigError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Field> {
+        Ok(Field::Seq(self.values))
+    }
+}
@@ -0,0 +1,576 @@
+use super::{ConfigError, Result, conf_escape, unprintf};
+
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// A parsed `wpa_supplicant.conf` file.
+///
+/// The file is kept as an ordered list of [`ConfItem`]s, so comments, blank
+/// lines and the relative order of globals and blocks survive a round trip
+/// through [`FromStr`] and [`Display`]. Whitespace around keys and values is
+/// normalised on output: globals are written as `key=value` and block contents
+/// are indented with a tab, the way wpa_supplicant writes the file itself. A
+/// `#` comment trailing a setting, outside any quotes, is kept with it.
+///
+/// ```
+/// use wifi_ctrl::config::{ConfValue, SupplicantConf};
+///
+/// let mut conf: SupplicantConf = r#"
+/// ctrl_interface=/var/run/wpa_supplicant
+/// ## home network
+/// network={
+///     ssid="home"
+///     psk="correct horse battery"
+/// }
+/// "#.parse()?;
+/// assert_eq!(conf.get("ctrl_interface").unwrap().to_string(), "/var/run/wpa_supplicant");
+///
+/// let network = conf.networks().next().unwrap();
+/// assert_eq!(network.get("ssid"), Some(&ConfValue::Quoted("home".into())));
+///
+/// conf.set("update_config", ConfValue::Raw("1".into()));
+/// assert!(conf.to_string().contains("update_config=1\n"));
+/// # Ok::<(), wifi_ctrl::config::ConfigError>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SupplicantConf {
+    pub items: Vec<ConfItem>,
+}
+
+/// A top-level item of a `wpa_supplicant.conf` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfItem {
+    /// A global setting, comment or blank line
+    Line(ConfLine),
+    /// A `network={ ... }` block
+    Network(ConfBlock),
+    /// A `cred={ ... }` block
+    Cred(ConfBlock),
+    /// A `blob-base64-<name>={ ... }` block
+    Blob(ConfBlob),
+}
+
+/// A single line, either at the top level or inside a block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfLine {
+    Blank,
+    /// Comment text following the `#`, kept verbatim
+    Comment(String),
+    Entry(ConfEntry),
+}
+
+/// A `key=value` setting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfEntry {
+    pub key: String,
+    pub value: ConfValue,
+    /// Text of a `# comment` following the value on the same line
+    pub comment: Option<String>,
+}
+
+impl ConfEntry {
+    pub fn new(key: impl Into<String>, value: ConfValue) -> Self {
+        Self {
+            key: key.into(),
+            value,
+            comment: None,
+        }
+    }
+}
+
+/// The contents of a `network={ ... }` or `cred={ ... }` block.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfBlock {
+    pub lines: Vec<ConfLine>,
+}
+
+/// A named binary blob, such as a certificate, that network blocks refer to
+/// as `blob://<name>`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfBlob {
+    pub name: String,
+    /// The base64 encoded contents, line by line as written in the file
+    pub lines: Vec<String>,
+}
+
+impl ConfBlob {
+    /// The base64 encoded contents as a single string.
+    pub fn base64(&self) -> String {
+        self.lines.concat()
+    }
+}
+
+/// A setting's value as written in the file.
+///
+/// wpa_supplicant reads a `"quoted"` value as a literal string and anything
+/// else as a raw token, which depending on the field is a number, an enum
+/// such as `WPA-PSK`, a hex-encoded string (e.g. an SSID that can't be
+/// quoted) or a `P"..."` printf-escaped string. Raw values are kept verbatim.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfValue {
+    Quoted(String),
+    Raw(String),
+}
+
+impl ConfValue {
+    /// Encode a string field (such as an SSID) the same way the station
+    /// runtime sends it over the control socket: quoted when possible,
+    /// otherwise hex encoded.
+    ///
+    /// ```
+    /// # use wifi_ctrl::config::ConfValue;
+    /// assert_eq!(ConfValue::escaped("home"), ConfValue::Quoted("home".into()));
+    /// assert_eq!(ConfValue::escaped("my home"), ConfValue::Raw("6d7920686f6d65".into()));
+    /// ```
+    pub fn escaped(raw: &str) -> Self {
+        Self::from_conf(&conf_escape(raw))
+    }
+
+    /// Decode a string field, undoing whichever of the quoted, hex or `P"..."`
+    /// encodings was used.
+    ///
+    /// ```
+    /// # use wifi_ctrl::config::ConfValue;
+    /// assert_eq!(ConfValue::Raw("6d7920686f6d65".into()).decode_string().unwrap(), "my home");
+    /// assert_eq!(ConfValue::Raw(r#"P"my\thome""#.into()).decode_string().unwrap(), "my\thome");
+    /// ```
+    pub fn decode_string(&self) -> Result<String> {
+        match self {
+            ConfValue::Quoted(s) => Ok(s.clone()),
+            ConfValue::Raw(s) => {
+                if let Some(escaped) = s.strip_prefix("P\"").and_then(|s| s.strip_suffix('"')) {
+                    unprintf(escaped)
+                } else {
+                    let bytes = hex::decode(s).or(Err(ConfigError::InvalidEscape))?;
+                    String::from_utf8(bytes).or(Err(ConfigError::NonUtf8Escape))
+                }
+            }
+        }
+    }
+
+    fn from_conf(value: &str) -> Self {
+        match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+            Some(quoted) => ConfValue::Quoted(quoted.to_string()),
+            None => ConfValue::Raw(value.to_string()),
+        }
+    }
+}
+
+impl Display for ConfValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfValue::Quoted(s) => write!(f, "\"{s}\""),
+            ConfValue::Raw(s) => write!(f, "{s}"),
+        }
+    }
+}
+
+impl ConfBlock {
+    pub fn entries(&self) -> impl Iterator<Item = &ConfEntry> {
+        self.lines.iter().filter_map(|line| match line {
+            ConfLine::Entry(entry) => Some(entry),
+            _ => None,
+        })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&ConfValue> {
+        get(&self.lines, key)
+    }
+
+    /// Replace the value of `key`, or append it if the block doesn't have one.
+    pub fn set(&mut self, key: &str, value: ConfValue) {
+        set(&mut self.lines, key, value)
+    }
+
+    /// Remove every `key` entry, returning the last value.
+    pub fn remove(&mut self, key: &str) -> Option<ConfValue> {
+        let mut removed = None;
+        self.lines.retain(|line| match line {
+            ConfLine::Entry(entry) if entry.key == key => {
+                removed = Some(entry.value.clone());
+                false
+            }
+            _ => true,
+        });
+        removed
+    }
+}
+
+impl SupplicantConf {
+    pub fn networks(&self) -> impl Iterator<Item = &ConfBlock> {
+        self.items.iter().filter_map(|item| match item {
+            ConfItem::Network(block) => Some(block),
+            _ => None,
+        })
+    }
+
+    pub fn networks_mut(&mut self) -> impl Iterator<Item = &mut ConfBlock> {
+        self.items.iter_mut().filter_map(|item| match item {
+            ConfItem::Network(block) => Some(block),
+            _ => None,
+        })
+    }
+
+    pub fn creds(&self) -> impl Iterator<Item = &ConfBlock> {
+        self.items.iter().filter_map(|item| match item {
+            ConfItem::Cred(block) => Some(block),
+            _ => None,
+        })
+    }
+
+    /// Look up a global setting.
+    pub fn get(&self, key: &str) -> Option<&ConfValue> {
+        self.items.iter().rev().find_map(|item| match item {
+            ConfItem::Line(ConfLine::Entry(entry)) if entry.key == key => Some(&entry.value),
+            _ => None,
+        })
+    }
+
+    /// Replace a global setting, or add it after the existing globals (and
+    /// before the first block) if it isn't set yet.
+    pub fn set(&mut self, key: &str, value: ConfValue) {
+        let existing = self.items.iter_mut().rev().find_map(|item| match item {
+            ConfItem::Line(ConfLine::Entry(entry)) if entry.key == key => Some(entry),
+            _ => None,
+        });
+        if let Some(entry) = existing {
+            entry.value = value;
+            return;
+        }
+        let globals = self
+            .items
+            .iter()
+            .position(|item| !matches!(item, ConfItem::Line(_)))
+            .unwrap_or(self.items.len());
+        // directly after the last global, so comments introducing the blocks
+        // stay attached to them
+        let position = self.items[..globals]
+            .iter()
+            .rposition(|item| matches!(item, ConfItem::Line(ConfLine::Entry(_))))
+            .map_or(0, |last| last + 1);
+        let entry = ConfEntry::new(key, value);
+        self.items
+            .insert(position, ConfItem::Line(ConfLine::Entry(entry)));
+    }
+
+    pub fn push_network(&mut self, network: ConfBlock) {
+        self.items.push(ConfItem::Network(network));
+    }
+
+    pub fn push_cred(&mut self, cred: ConfBlock) {
+        self.items.push(ConfItem::Cred(cred));
+    }
+
+    pub fn blobs(&self) -> impl Iterator<Item = &ConfBlob> {
+        self.items.iter().filter_map(|item| match item {
+            ConfItem::Blob(blob) => Some(blob),
+            _ => None,
+        })
+    }
+}
+
+fn get<'a>(lines: &'a [ConfLine], key: &str) -> Option<&'a ConfValue> {
+    lines.iter().rev().find_map(|line| match line {
+        ConfLine::Entry(entry) if entry.key == key => Some(&entry.value),
+        _ => None,
+    })
+}
+
+fn set(lines: &mut Vec<ConfLine>, key: &str, value: ConfValue) {
+    let existing = lines.iter_mut().rev().find_map(|line| match line {
+        ConfLine::Entry(entry) if entry.key == key => Some(entry),
+        _ => None,
+    });
+    match existing {
+        Some(entry) => entry.value = value,
+        None => lines.push(ConfLine::Entry(ConfEntry::new(key, value))),
+    }
+}
+
+/// Split a setting from its trailing comment. Like wpa_supplicant, a `#`
+/// starts a comment unless it's inside double quotes.
+fn split_comment(line: &str) -> (&str, Option<&str>) {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return (line[..i].trim_end(), Some(&line[i + 1..])),
+            _ => (),
+        }
+    }
+    (line, None)
+}
+
+fn parse_line(line: &str, number: usize) -> Result<ConfLine> {
+    if line.is_empty() {
+        Ok(ConfLine::Blank)
+    } else if let Some(comment) = line.strip_prefix('#') {
+        Ok(ConfLine::Comment(comment.to_string()))
+    } else {
+        let (setting, comment) = split_comment(line);
+        let (key, value) = setting
+            .split_once('=')
+            .ok_or(ConfigError::MissingDelimiterEqual(number))?;
+        let value = value.trim();
+        if value.starts_with('"') && (value.len() < 2 || !value.ends_with('"')) {
+            return Err(ConfigError::UnterminatedQuote(number));
+        }
+        Ok(ConfLine::Entry(ConfEntry {
+            key: key.trim().to_string(),
+            value: ConfValue::from_conf(value),
+            comment: comment.map(str::to_string),
+        }))
+    }
+}
+
+/// The name of the block opened by a `name={` line.
+fn block_name(line: &str) -> Option<&str> {
+    match line.split_once('=') {
+        Some((name, "{")) => Some(name.trim_end()),
+        Some((name, rest)) if rest.trim_start() == "{" => Some(name.trim_end()),
+        _ => None,
+    }
+}
+
+impl FromStr for SupplicantConf {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        enum Open {
+            Network,
+            Cred,
+            Blob(ConfBlob),
+        }
+
+        let mut items = Vec::new();
+        let mut block: Option<(Open, ConfBlock, usize)> = None;
+        for (i, line) in s.lines().enumerate() {
+            let number = i + 1;
+            let line = line.trim();
+            match block.take() {
+                Some((open, contents, _)) if line == "}" => items.push(match open {
+                    Open::Network => ConfItem::Network(contents),
+                    Open::Cred => ConfItem::Cred(contents),
+                    Open::Blob(blob) => ConfItem::Blob(blob),
+                }),
+                Some(_) if block_name(line).is_some() => {
+                    return Err(ConfigError::NestedBlock(number));
+                }
+                // blob contents are base64, not settings
+                Some((Open::Blob(mut blob), contents, start)) => {
+                    if !line.is_empty() {
+                        blob.lines.push(line.to_string());
+                    }
+                    block = Some((Open::Blob(blob), contents, start));
+                }
+                Some((open, mut contents, start)) => {
+                    contents.lines.push(parse_line(line, number)?);
+                    block = Some((open, contents, start));
+                }
+                None if line == "}" => return Err(ConfigError::UnexpectedBlockEnd(number)),
+                None => match block_name(line) {
+                    Some(name) => {
+                        let open = match name {
+                            "network" => Open::Network,
+                            "cred" => Open::Cred,
+                            _ => match name.strip_prefix("blob-base64-") {
+                                Some(name) if !name.is_empty() => Open::Blob(ConfBlob {
+                                    name: name.to_string(),
+                                    lines: Vec::new(),
+                                }),
+                                _ => return Err(ConfigError::UnknownBlock(number)),
+                            },
+                        };
+                        block = Some((open, ConfBlock::default(), number));
+                    }
+                    None => items.push(ConfItem::Line(parse_line(line, number)?)),
+                },
+            }
+        }
+        if let Some((_, _, start)) = block {
+            return Err(ConfigError::UnterminatedBlock(start));
+        }
+        Ok(SupplicantConf { items })
+    }
+}
+
+fn write_line(f: &mut fmt::Formatter<'_>, indent: &str, line: &ConfLine) -> fmt::Result {
+    match line {
+        ConfLine::Blank => writeln!(f),
+        ConfLine::Comment(comment) => writeln!(f, "{indent}#{comment}"),
+        ConfLine::Entry(ConfEntry {
+            key,
+            value,
+            comment: None,
+        }) => writeln!(f, "{indent}{key}={value}"),
+        ConfLine::Entry(ConfEntry {
+            key,
+            value,
+            comment: Some(comment),
+        }) => writeln!(f, "{indent}{key}={value} #{comment}"),
+    }
+}
+
+fn write_block(f: &mut fmt::Formatter<'_>, name: &str, block: &ConfBlock) -> fmt::Result {
+    writeln!(f, "{name}={{")?;
+    for line in &block.lines {
+        write_line(f, "\t", line)?;
+    }
+    writeln!(f, "}}")
+}
+
+impl Display for SupplicantConf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for item in &self.items {
+            match item {
+                ConfItem::Line(line) => write_line(f, "", line)?,
+                ConfItem::Network(block) => write_block(f, "network", block)?,
+                ConfItem::Cred(block) => write_block(f, "cred", block)?,
+                ConfItem::Blob(blob) => {
+                    writeln!(f, "blob-base64-{}={{", blob.name)?;
+                    for line in &blob.lines {
+                        writeln!(f, "{line}")?;
+                    }
+                    writeln!(f, "}}")?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONF: &str = r#"ctrl_interface=DIR=/var/run/wpa_supplicant GROUP=netdev
+update_config=1
+# networks below are managed by the image builder
+
+network={
+	ssid="home"
+	psk="correct horse battery"
+	# keep this one first
+	priority=5
+}
+network={
+	ssid=6d7920686f6d65
+	key_mgmt=NONE
+}
+cred={
+	realm="example.com"
+	username="user"
+	password="secret"
+	domain="example.com"
+}
+"#;
+
+    #[test]
+    fn round_trips_canonical_file() {
+        let conf: SupplicantConf = CONF.parse().unwrap();
+        assert_eq!(conf.to_string(), CONF);
+        assert_eq!(conf.networks().count(), 2);
+        assert_eq!(conf.creds().count(), 1);
+    }
+
+    #[test]
+    fn parses_loose_whitespace() {
+        let conf: SupplicantConf = "
+            update_config = 1
+            network = {
+                ssid = \"home\"
+            }
+        "
+        .parse()
+        .unwrap();
+        assert_eq!(conf.get("update_config"), Some(&ConfValue::Raw("1".into())));
+        let network = conf.networks().next().unwrap();
+        assert_eq!(
+            network.get("ssid").unwrap().decode_string().unwrap(),
+            "home"
+        );
+    }
+
+    #[test]
+    fn decodes_quoted_and_hex_ssids() {
+        let conf: SupplicantConf = CONF.parse().unwrap();
+        let ssids: Vec<String> = conf
+            .networks()
+            .map(|n| n.get("ssid").unwrap().decode_string().unwrap())
+            .collect();
+        assert_eq!(ssids, ["home", "my home"]);
+    }
+
+    #[test]
+    fn set_replaces_or_inserts_before_blocks() {
+        let mut conf: SupplicantConf = CONF.parse().unwrap();
+        conf.set("update_config", ConfValue::Raw("0".into()));
+        conf.set("country", ConfValue::Raw("US".into()));
+        let out = conf.to_string();
+        assert!(out.starts_with(
+            "ctrl_interface=DIR=/var/run/wpa_supplicant GROUP=netdev\nupdate_config=0\n"
+        ));
+        assert!(out.contains("update_config=0\ncountry=US\n# networks below"));
+    }
+
+    #[test]
+    fn rejects_malformed_blocks() {
+        assert_eq!(
+            "network={\n\tssid=\"x\"\n".parse::<SupplicantConf>(),
+            Err(ConfigError::UnterminatedBlock(1))
+        );
+        assert_eq!(
+            "}".parse::<SupplicantConf>(),
+            Err(ConfigError::UnexpectedBlockEnd(1))
+        );
+        assert_eq!(
+            "network={\nnetwork={\n}".parse::<SupplicantConf>(),
+            Err(ConfigError::NestedBlock(2))
+        );
+        assert_eq!(
+            "phase1={\n}".parse::<SupplicantConf>(),
+            Err(ConfigError::UnknownBlock(1))
+        );
+        assert_eq!(
+            "update_config=1\nnetwork={\n\tssid\n}".parse::<SupplicantConf>(),
+            Err(ConfigError::MissingDelimiterEqual(3))
+        );
+        assert_eq!(
+            "network={\n\tssid=\"x\n}".parse::<SupplicantConf>(),
+            Err(ConfigError::UnterminatedQuote(2))
+        );
+    }
+
+    #[test]
+    fn keeps_trailing_comments_outside_quotes() {
+        let conf: SupplicantConf =
+            "network={\n\tssid=\"home #1\"\n\tpsk=\"secret\" # home\n\tpriority=5# first\n}\n"
+                .parse()
+                .unwrap();
+        let network = conf.networks().next().unwrap();
+        assert_eq!(
+            network.get("ssid"),
+            Some(&ConfValue::Quoted("home #1".into()))
+        );
+        assert_eq!(
+            network.get("psk"),
+            Some(&ConfValue::Quoted("secret".into()))
+        );
+        assert_eq!(network.get("priority"), Some(&ConfValue::Raw("5".into())));
+        assert_eq!(
+            conf.to_string(),
+            "network={\n\tssid=\"home #1\"\n\tpsk=\"secret\" # home\n\tpriority=5 # first\n}\n"
+        );
+    }
+
+    #[test]
+    fn round_trips_blobs() {
+        let file = "blob-base64-ca_cert={\nSGVsbG8s\nIHdvcmxk\n}\nnetwork={\n\tca_cert=\"blob://ca_cert\"\n}\n";
+        let conf: SupplicantConf = file.parse().unwrap();
+        let blob = conf.blobs().next().unwrap();
+        assert_eq!(blob.name, "ca_cert");
+        assert_eq!(blob.base64(), "SGVsbG8sIHdvcmxk");
+        assert_eq!(conf.networks().count(), 1);
+        assert_eq!(conf.to_string(), file);
+    }
+}
@@ -0,0 +1,58 @@
+use crate::error::ClientError;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// An ISO 3166-1 alpha-2 country code naming a regulatory domain, such as
+/// `US` or `DE`, or `00` for the world domain the kernel falls back to.
+///
+/// Codes parse in either case and are always re-emitted in uppercase. Only
+/// the form is checked; the kernel rejects a country it has no rules for.
+///
+/// ```
+/// use wifi_ctrl::CountryCode;
+///
+/// let country: CountryCode = "de".parse()?;
+/// assert_eq!(country.to_string(), "DE");
+/// assert!("00".parse::<CountryCode>()?.is_world());
+///
+/// assert!("DEU".parse::<CountryCode>().is_err());
+/// assert!("D1".parse::<CountryCode>().is_err());
+/// # Ok::<(), wifi_ctrl::error::ClientError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CountryCode([u8; 2]);
+
+impl CountryCode {
+    /// The world regulatory domain, `00`.
+    pub const WORLD: CountryCode = CountryCode(*b"00");
+
+    pub fn is_world(&self) -> bool {
+        *self == Self::WORLD
+    }
+
+    pub fn as_str(&self) -> &str {
+        // only ever built from ASCII
+        std::str::from_utf8(&self.0).unwrap_or_default()
+    }
+}
+
+impl FromStr for CountryCode {
+    type Err = ClientError;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s.as_bytes() {
+            b"00" => Ok(Self::WORLD),
+            [a, b] if a.is_ascii_alphabetic() && b.is_ascii_alphabetic() => Ok(CountryCode([
+                a.to_ascii_uppercase(),
+                b.to_ascii_uppercase(),
+            ])),
+            _ => Err(ClientError::InvalidCountryCode),
+        }
+    }
+}
+
+impl Display for CountryCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
@@ -3,6 +3,7 @@ use std::convert::Infallible;
 use thiserror::Error;
 use tokio::sync::mpsc::error::SendError;
 use tokio::sync::oneshot::error::RecvError;
+use tokio::time::error::Elapsed;
 
 /// Error returned by [access point](crate::ap::WifiAp::run) and [station](crate::sta::WifiStation::run) runners if there is
 /// a problem with the control socket. e.g. if `wpa_supplicant` is restarted
@@ -28,6 +29,21 @@ pub enum SocketError {
     AttachFailed(String),
 }
 
+/// Error returned by the blocking clients ([station](crate::sta::blocking::Client)
+/// and [access point](crate::ap::blocking::Client)), which talk to the control
+/// socket from the calling thread instead of going through a runner
+#[cfg(feature = "blocking")]
+#[derive(Error, Debug)]
+pub enum BlockingError {
+    /// IO error from control socket
+    #[error(transparent)]
+    Socket(#[from] SocketError),
+    /// The request failed or its response couldn't be parsed, as with the
+    /// async clients
+    #[error(transparent)]
+    Client(#[from] ClientError),
+}
+
 /// Error returned by [access point](crate::ap::RequestClient) and [station](crate::sta::RequestClient) clients if there is
 /// a problem with the request e.g. asking to select a network you have not created a config for
 #[derive(Error, Debug, Clone)]
@@ -35,6 +51,15 @@ pub enum ClientError {
     /// Request failed  e.g. asking to select a network you have not created a config for
     #[error("Supplicant reported request failed")]
     Failed,
+    /// The daemon turned the request down with a specific reason, such as
+    /// `FAIL-BUSY` or `UNKNOWN COMMAND`, rather than a bare `FAIL`
+    #[error("{error} (daemon replied {response:?})")]
+    Daemon {
+        #[source]
+        error: DaemonError,
+        /// The reply as received
+        response: String,
+    },
     /// Error parsing the response from the socket. This is probably a bug in the [`wifi_ctrl`](crate) code.
     #[error("error {error} parsing response: \n{failed_response}")]
     ParsingResponse {
@@ -58,9 +83,80 @@ pub enum ClientError {
     /// a double-quote, which cannot be safely encoded for wpa_supplicant
     #[error("PSK is not a valid WPA passphrase")]
     InvalidPsk,
-    /// A BSSID was not a well-formed `xx:xx:xx:xx:xx:xx` MAC address
-    #[error("BSSID is not a valid MAC address")]
-    InvalidBssid,
+    /// A MAC address, such as a BSSID, was not in the `xx:xx:xx:xx:xx:xx` form
+    #[error("not a valid MAC address")]
+    InvalidMacAddr,
+    /// A country code was not two letters, or `00` for the world domain
+    #[error("not a valid ISO 3166 alpha-2 country code")]
+    InvalidCountryCode,
+    /// A request parameter could not be safely encoded into a control command
+    #[error("invalid {0} parameter")]
+    InvalidParameter(&'static str),
+    /// The pending select was resolved early by
+    /// [`cancel_select`](crate::sta::RequestClient::cancel_select)
+    #[error("Select cancelled")]
+    Cancelled,
+}
+
+impl ClientError {
+    /// Whether the same request may well succeed if tried again later: a
+    /// timeout, or a [`DaemonError`] that [is retryable](DaemonError::is_retryable).
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ClientError::Timeout => true,
+            ClientError::Daemon { error, .. } => error.is_retryable(),
+            _ => false,
+        }
+    }
+}
+
+/// A sub error of [`ClientError`] classifying a failure reply from
+/// wpa_supplicant or hostapd.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum DaemonError {
+    /// `FAIL-BUSY`: the daemon is in the middle of something, e.g. a scan
+    #[error("daemon busy")]
+    Busy,
+    /// `UNKNOWN COMMAND`: the daemon doesn't support the command, typically
+    /// because it's an older version or built without the feature
+    #[error("unknown command")]
+    UnknownCommand,
+    /// `FAIL-CHANNEL-UNAVAILABLE`: the requested channel can't be used right now
+    #[error("channel unavailable")]
+    ChannelUnavailable,
+    /// `FAIL-INVALID-RANGE`: a parameter was out of range
+    #[error("parameter out of range")]
+    InvalidRange,
+    /// `FAIL-NOT-FOUND`: the requested item doesn't exist
+    #[error("not found")]
+    NotFound,
+    /// Any other `FAIL-<reason>` reply
+    #[error("request failed")]
+    Other,
+}
+
+impl DaemonError {
+    /// Classify a failure reply, or `None` if `reply` isn't one. A bare
+    /// `FAIL` is left to [`ClientError::Failed`].
+    pub(crate) fn from_reply(reply: &str) -> Option<Self> {
+        if reply == "UNKNOWN COMMAND" {
+            return Some(DaemonError::UnknownCommand);
+        }
+        let reason = reply.strip_prefix("FAIL-")?;
+        Some(match reason {
+            "BUSY" => DaemonError::Busy,
+            "CHANNEL-UNAVAILABLE" => DaemonError::ChannelUnavailable,
+            "INVALID-RANGE" => DaemonError::InvalidRange,
+            "NOT-FOUND" => DaemonError::NotFound,
+            _ => DaemonError::Other,
+        })
+    }
+
+    /// Whether the condition is likely to clear by itself, so the request is
+    /// worth retrying later: [`Self::Busy`] and [`Self::ChannelUnavailable`].
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, DaemonError::Busy | DaemonError::ChannelUnavailable)
+    }
 }
 
 /// A sub error of [`ClientError`] returned when there is a problem parsing the response from
@@ -99,3 +195,10 @@ impl From<RecvError> for ClientError {
         ClientError::RunnerNotRunning
     }
 }
+
+// Happens when a request made with a deadline runs out of time
+impl From<Elapsed> for ClientError {
+    fn from(_: Elapsed) -> Self {
+        ClientError::Timeout
+    }
+}
@@ -0,0 +1,76 @@
+use super::*;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use tokio_stream::Stream;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+
+type Filter<B> = Box<dyn Fn(&B) -> bool + Send + Sync>;
+
+/// A broadcast receiver as a stream that reports lag as an item, built with
+/// `lagged`, instead of dropping it. Shared by the station and access point
+/// `EventStream`s.
+pub(crate) struct FilteredStream<B> {
+    inner: BroadcastStream<B>,
+    /// Every filter must pass for a broadcast to be yielded; lag always is
+    filters: Vec<Filter<B>>,
+    lagged: fn(u64) -> B,
+}
+
+impl<B: Clone + Send + 'static> FilteredStream<B> {
+    pub(crate) fn new(receiver: broadcast::Receiver<B>, lagged: fn(u64) -> B) -> Self {
+        Self {
+            inner: BroadcastStream::new(receiver),
+            filters: Vec::new(),
+            lagged,
+        }
+    }
+
+    pub(crate) fn push_filter(&mut self, filter: impl Fn(&B) -> bool + Send + Sync + 'static) {
+        self.filters.push(Box::new(filter));
+    }
+
+    pub(crate) fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<B>> {
+        loop {
+            match ready!(Pin::new(&mut self.inner).poll_next(cx)) {
+                None => return Poll::Ready(None),
+                Some(Err(BroadcastStreamRecvError::Lagged(n))) => {
+                    return Poll::Ready(Some((self.lagged)(n)));
+                }
+                Some(Ok(broadcast)) if self.filters.iter().all(|f| f(&broadcast)) => {
+                    return Poll::Ready(Some(broadcast));
+                }
+                Some(Ok(_)) => (),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ap::{Broadcast, EventStream, StaConnected};
+    use tokio_stream::StreamExt;
+
+    #[tokio::test]
+    async fn lag_is_reported_and_passes_filters() {
+        let (sender, receiver) = broadcast::channel(2);
+        let mac: MacAddr = "AA:BB:CC:DD:EE:FF".parse().unwrap();
+        let mut events = EventStream::new(receiver).mac(mac);
+        for broadcast in [
+            Broadcast::Connected(StaConnected::parse("aa:bb:cc:dd:ee:ff").unwrap()),
+            Broadcast::Ready,
+            Broadcast::Connected(StaConnected::parse("00:11:22:33:44:55").unwrap()),
+            Broadcast::Disconnected(mac),
+        ] {
+            sender.send(broadcast).unwrap();
+        }
+        drop(sender);
+        assert!(matches!(events.next().await, Some(Broadcast::Lagged(2))));
+        assert!(matches!(
+            events.next().await,
+            Some(Broadcast::Disconnected(other)) if other == mac
+        ));
+        assert!(events.next().await.is_none());
+    }
+}
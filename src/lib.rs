@@ -1,7 +1,8 @@
 //! Tokio-based runtimes for communicating with hostapd and wpa-supplicant.
 //!
 //! Use [`sta`] to run a WiFi station (network client) against `wpa_supplicant`,
-//! and [`ap`] to run an access point against `hostapd`.
+//! [`ap`] to run an access point against `hostapd`, and [`p2p`] for Wi-Fi
+//! Direct through `wpa_supplicant`.
 //!
 //! # Quick Start
 //!
@@ -40,21 +41,47 @@ use tokio::sync::{broadcast, mpsc, oneshot};
 
 /// WiFi Access Point runtime and types
 pub mod ap;
+/// Configuration formats shared by hostapd and wpa_supplicant, including
+/// `wpa_supplicant.conf` files
+pub mod config;
 /// Crate-wide error types
 pub mod error;
+/// Wi-Fi Direct (P2P) runtime and types
+pub mod p2p;
 /// WiFi Station (network client) runtime and types
 pub mod sta;
 
-pub(crate) mod config;
+#[cfg(feature = "blocking")]
+pub(crate) mod blocking;
+mod country_code;
+pub use country_code::CountryCode;
+mod event_stream;
+mod mac_addr;
+pub use mac_addr::MacAddr;
 pub(crate) mod socket_handle;
+use event_stream::FilteredStream;
+mod transport;
+pub use transport::ControlInterface;
 
-use socket_handle::SocketHandle;
+use socket_handle::{Keepalive, SocketHandle, attach_command, retry_command};
 pub type Result<T = ()> = std::result::Result<T, error::ClientError>;
 pub type SocketResult<T = ()> = std::result::Result<T, error::SocketError>;
 pub type ParseResult<T = ()> = std::result::Result<T, error::ParseError>;
+#[cfg(feature = "blocking")]
+pub type BlockingResult<T = ()> = std::result::Result<T, error::BlockingError>;
 
 use log::{debug, info, warn};
 
 pub(crate) trait ShutdownSignal {
     fn is_shutdown(&self) -> bool;
 }
+
+/// An interface name to put in a command, which mustn't be able to smuggle
+/// in further arguments.
+pub(crate) fn check_ifname(ifname: &str) -> Result<&str> {
+    if ifname.is_empty() || !ifname.bytes().all(|b| b.is_ascii_graphic()) {
+        Err(error::ClientError::InvalidParameter("ifname"))
+    } else {
+        Ok(ifname)
+    }
+}
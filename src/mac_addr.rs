@@ -0,0 +1,150 @@
+use crate::error::ClientError;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// A 48-bit MAC address, such as a station's address or an access point's
+/// BSSID.
+///
+/// Addresses parse from the colon-separated `xx:xx:xx:xx:xx:xx` form hostapd
+/// and wpa_supplicant use, in either case, and are always re-emitted in
+/// canonical lowercase via [`Display`]. That keeps caller input from being
+/// echoed into an unquoted command position and makes addresses from
+/// different events compare equal. With serde they (de)serialize as that
+/// string.
+///
+/// ```
+/// use wifi_ctrl::MacAddr;
+///
+/// let mac: MacAddr = "02:1A:11:F0:00:01".parse()?;
+/// assert_eq!(mac.to_string(), "02:1a:11:f0:00:01");
+/// assert_eq!(mac, MacAddr::from([0x02, 0x1a, 0x11, 0xf0, 0x00, 0x01]));
+/// assert_eq!(mac.oui(), [0x02, 0x1a, 0x11]);
+/// // a randomized address
+/// assert!(mac.is_locally_administered());
+/// assert!(!mac.is_multicast());
+///
+/// assert!("02:1a:11:f0:00".parse::<MacAddr>().is_err());
+/// # Ok::<(), wifi_ctrl::error::ClientError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MacAddr([u8; 6]);
+
+impl MacAddr {
+    /// The broadcast address `ff:ff:ff:ff:ff:ff`.
+    pub const BROADCAST: MacAddr = MacAddr([0xff; 6]);
+
+    pub fn octets(&self) -> [u8; 6] {
+        self.0
+    }
+
+    /// The Organizationally Unique Identifier: the first three octets, which
+    /// name the vendor unless the address is locally administered.
+    pub fn oui(&self) -> [u8; 3] {
+        [self.0[0], self.0[1], self.0[2]]
+    }
+
+    /// The group bit is set: the address names a group of stations rather
+    /// than a single one.
+    pub fn is_multicast(&self) -> bool {
+        self.0[0] & 0x01 != 0
+    }
+
+    pub fn is_broadcast(&self) -> bool {
+        *self == Self::BROADCAST
+    }
+
+    /// The address wasn't assigned by the vendor, e.g. it was randomized for
+    /// privacy.
+    pub fn is_locally_administered(&self) -> bool {
+        self.0[0] & 0x02 != 0
+    }
+}
+
+impl From<[u8; 6]> for MacAddr {
+    fn from(mac: [u8; 6]) -> Self {
+        MacAddr(mac)
+    }
+}
+
+impl From<MacAddr> for [u8; 6] {
+    fn from(mac: MacAddr) -> Self {
+        mac.0
+    }
+}
+
+impl FromStr for MacAddr {
+    type Err = ClientError;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        let mut mac = [0u8; 6];
+        let mut octets = s.split(':');
+        for byte in mac.iter_mut() {
+            let octet = octets.next().ok_or(ClientError::InvalidMacAddr)?;
+            // from_str_radix alone would admit signs and whitespace
+            if octet.len() != 2 || !octet.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(ClientError::InvalidMacAddr);
+            }
+            *byte = u8::from_str_radix(octet, 16).map_err(|_| ClientError::InvalidMacAddr)?;
+        }
+        if octets.next().is_some() {
+            return Err(ClientError::InvalidMacAddr);
+        }
+        Ok(MacAddr(mac))
+    }
+}
+
+impl Display for MacAddr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(f, "{a:02x}:{b:02x}:{c:02x}:{d:02x}:{e:02x}:{g:02x}")
+    }
+}
+
+impl Serialize for MacAddr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for MacAddr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn address_bits_are_decoded() {
+        let vendor: MacAddr = "cc:7b:5c:1a:d2:21".parse().unwrap();
+        assert!(!vendor.is_locally_administered());
+        assert!(!vendor.is_multicast());
+        assert_eq!(vendor.oui(), [0xcc, 0x7b, 0x5c]);
+
+        let multicast: MacAddr = "01:00:5e:00:00:fb".parse().unwrap();
+        assert!(multicast.is_multicast());
+        assert!(!multicast.is_broadcast());
+        assert!(MacAddr::BROADCAST.is_multicast());
+        assert_eq!(MacAddr::BROADCAST.to_string(), "ff:ff:ff:ff:ff:ff");
+    }
+
+    #[test]
+    fn serde_uses_the_colon_form() {
+        let fields: BTreeMap<String, MacAddr> =
+            crate::config::from_str("bssid=CC:7B:5C:1A:D2:21\n").unwrap();
+        let bssid = fields["bssid"];
+        assert_eq!(bssid.octets(), [0xcc, 0x7b, 0x5c, 0x1a, 0xd2, 0x21]);
+        assert_eq!(
+            crate::config::to_string(&fields).unwrap(),
+            "bssid=cc:7b:5c:1a:d2:21\n"
+        );
+
+        let bad: Result<BTreeMap<String, MacAddr>, _> = crate::config::from_str("bssid=nope\n");
+        assert!(bad.is_err());
+    }
+}
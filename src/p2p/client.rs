@@ -0,0 +1,298 @@
+use super::*;
+
+#[derive(Debug)]
+pub(crate) enum Request {
+    Custom(String, oneshot::Sender<Result<String>>),
+    Find(Option<Duration>, oneshot::Sender<Result>),
+    StopFind(oneshot::Sender<Result>),
+    Connect(MacAddr, P2pConnect, oneshot::Sender<Result<Option<String>>>),
+    GroupAdd(GroupAdd, oneshot::Sender<Result>),
+    GroupRemove(String, oneshot::Sender<Result>),
+    Invite(Invite, oneshot::Sender<Result>),
+    Peers(oneshot::Sender<Result<Vec<MacAddr>>>),
+    Peer(MacAddr, oneshot::Sender<Result<Peer>>),
+    PersistentGroups(oneshot::Sender<Result<Vec<NetworkResult>>>),
+    RemovePersistentGroup(usize, oneshot::Sender<Result>),
+    /// A request from a client made with [`RequestClient::with_timeout`]
+    WithDeadline(Instant, Box<Request>),
+    Shutdown,
+}
+
+impl Request {
+    /// Whether the client stopped waiting for the response, e.g. because its
+    /// future was dropped, so the request needn't be sent at all.
+    pub(crate) fn is_abandoned(&self) -> bool {
+        match self {
+            Request::Custom(_, response) => response.is_closed(),
+            Request::Find(_, response) => response.is_closed(),
+            Request::StopFind(response) => response.is_closed(),
+            Request::Connect(_, _, response) => response.is_closed(),
+            Request::GroupAdd(_, response) => response.is_closed(),
+            Request::GroupRemove(_, response) => response.is_closed(),
+            Request::Invite(_, response) => response.is_closed(),
+            Request::Peers(response) => response.is_closed(),
+            Request::Peer(_, response) => response.is_closed(),
+            Request::PersistentGroups(response) => response.is_closed(),
+            Request::RemovePersistentGroup(_, response) => response.is_closed(),
+            Request::WithDeadline(_, request) => request.is_abandoned(),
+            Request::Shutdown => false,
+        }
+    }
+
+    pub(crate) fn split_deadline(self) -> (Option<Instant>, Request) {
+        match self {
+            Request::WithDeadline(deadline, request) => (Some(deadline), *request),
+            request => (None, request),
+        }
+    }
+}
+
+impl ShutdownSignal for Request {
+    fn is_shutdown(&self) -> bool {
+        matches!(self, Request::Shutdown)
+    }
+}
+
+#[derive(Clone)]
+/// Request client wraps the request events, awaiting oneshot channels when appropriate
+pub struct RequestClient {
+    sender: mpsc::Sender<Request>,
+    /// Deadline for each request, counted from when it's made
+    timeout: Option<Duration>,
+}
+
+impl RequestClient {
+    pub(crate) fn new(sender: mpsc::Sender<Request>) -> RequestClient {
+        RequestClient {
+            sender,
+            timeout: None,
+        }
+    }
+
+    /// A client whose requests each fail with
+    /// [`ClientError::Timeout`](crate::error::ClientError::Timeout) if not
+    /// answered within `timeout`, in place of the runner's command timeout.
+    /// A request still queued when its time runs out is skipped by the runner
+    /// rather than sent late.
+    pub fn with_timeout(&self, timeout: Duration) -> RequestClient {
+        RequestClient {
+            sender: self.sender.clone(),
+            timeout: Some(timeout),
+        }
+    }
+
+    async fn request<T>(
+        &self,
+        build_request: impl FnOnce(oneshot::Sender<Result<T>>) -> Request,
+    ) -> Result<T> {
+        let (response, request) = oneshot::channel();
+        let Some(timeout) = self.timeout else {
+            self.sender.send(build_request(response)).await?;
+            return request.await?;
+        };
+        let deadline = Instant::now() + timeout;
+        let request_with_deadline =
+            Request::WithDeadline(deadline, Box::new(build_request(response)));
+        tokio::time::timeout_at(deadline, async {
+            self.sender.send(request_with_deadline).await?;
+            request.await?
+        })
+        .await?
+    }
+
+    pub async fn send_custom(&self, custom: String) -> Result<String> {
+        self.request(|response| Request::Custom(custom, response))
+            .await
+    }
+
+    /// Start looking for peers, reported as [`Broadcast::DeviceFound`], until
+    /// `timeout` (whole seconds) has passed or [`Self::p2p_stop_find`] is
+    /// called. Either way [`Broadcast::FindStopped`] follows.
+    pub async fn p2p_find(&self, timeout: Option<Duration>) -> Result {
+        self.request(|response| Request::Find(timeout, response))
+            .await
+    }
+
+    pub async fn p2p_stop_find(&self) -> Result {
+        self.request(Request::StopFind).await
+    }
+
+    /// Start GO negotiation with `peer`, or join its group with
+    /// [`P2pConnect::join`]. Returns the PIN wpa_supplicant generated for
+    /// [`WpsMethod::DisplayPin(None)`](WpsMethod::DisplayPin), to be shown to
+    /// the user. The outcome is broadcast as [`Broadcast::GoNegSuccess`] or
+    /// [`Broadcast::GoNegFailure`], then [`Broadcast::GroupStarted`].
+    pub async fn p2p_connect(&self, peer: MacAddr, connect: P2pConnect) -> Result<Option<String>> {
+        self.request(|response| Request::Connect(peer, connect, response))
+            .await
+    }
+
+    /// Start a group as its owner, broadcasting [`Broadcast::GroupStarted`].
+    pub async fn p2p_group_add(&self, group_add: GroupAdd) -> Result {
+        self.request(|response| Request::GroupAdd(group_add, response))
+            .await
+    }
+
+    /// Leave or end the group running on `ifname`, broadcasting
+    /// [`Broadcast::GroupRemoved`]; `*` removes every group.
+    pub async fn p2p_group_remove(&self, ifname: &str) -> Result {
+        self.request(|response| Request::GroupRemove(ifname.into(), response))
+            .await
+    }
+
+    pub async fn p2p_invite(&self, invite: Invite) -> Result {
+        self.request(|response| Request::Invite(invite, response))
+            .await
+    }
+
+    /// Device addresses of the peers found so far.
+    pub async fn p2p_peers(&self) -> Result<Vec<MacAddr>> {
+        self.request(Request::Peers).await
+    }
+
+    pub async fn p2p_peer(&self, address: MacAddr) -> Result<Peer> {
+        self.request(|response| Request::Peer(address, response))
+            .await
+    }
+
+    /// The networks storing persistent groups, which can be re-established
+    /// with [`Persistent::Reinvoke`] or [`Invite::Persistent`].
+    pub async fn list_persistent_groups(&self) -> Result<Vec<NetworkResult>> {
+        self.request(Request::PersistentGroups).await
+    }
+
+    pub async fn remove_persistent_group(&self, network_id: usize) -> Result {
+        self.request(|response| Request::RemovePersistentGroup(network_id, response))
+            .await
+    }
+
+    pub async fn shutdown(&self) -> Result {
+        self.sender.send(Request::Shutdown).await?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Broadcast events, such as a peer being found or a group starting, may happen at any time.
+pub enum Broadcast {
+    Ready,
+    DeviceFound(DeviceFound),
+    /// The peer with this device address is no longer around
+    DeviceLost(MacAddr),
+    /// [`RequestClient::p2p_find`] has finished
+    FindStopped,
+    GoNegRequest(GoNegRequest),
+    GoNegSuccess(GoNegSuccess),
+    /// GO negotiation failed with this P2P status code
+    GoNegFailure(i32),
+    GroupStarted(GroupStarted),
+    GroupRemoved(GroupRemoved),
+    InvitationReceived(InvitationReceived),
+    /// The keepalive found the event socket no longer answering and
+    /// re-attached it; events in between may have been missed.
+    Reattached,
+    UnknownEvent(String),
+    /// Only yielded by [`EventStream`]: this many broadcasts were missed
+    /// because the stream fell behind, so any state tracked from them should
+    /// be refreshed, e.g. with [`RequestClient::p2p_peers`].
+    Lagged(u64),
+}
+
+/// The variants of [`Broadcast`] without their data, for
+/// [`EventStream::kinds`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BroadcastKind {
+    Ready,
+    DeviceFound,
+    DeviceLost,
+    FindStopped,
+    GoNegRequest,
+    GoNegSuccess,
+    GoNegFailure,
+    GroupStarted,
+    GroupRemoved,
+    InvitationReceived,
+    Reattached,
+    UnknownEvent,
+    Lagged,
+}
+
+impl Broadcast {
+    pub fn kind(&self) -> BroadcastKind {
+        match self {
+            Broadcast::Ready => BroadcastKind::Ready,
+            Broadcast::DeviceFound(_) => BroadcastKind::DeviceFound,
+            Broadcast::DeviceLost(_) => BroadcastKind::DeviceLost,
+            Broadcast::FindStopped => BroadcastKind::FindStopped,
+            Broadcast::GoNegRequest(_) => BroadcastKind::GoNegRequest,
+            Broadcast::GoNegSuccess(_) => BroadcastKind::GoNegSuccess,
+            Broadcast::GoNegFailure(_) => BroadcastKind::GoNegFailure,
+            Broadcast::GroupStarted(_) => BroadcastKind::GroupStarted,
+            Broadcast::GroupRemoved(_) => BroadcastKind::GroupRemoved,
+            Broadcast::InvitationReceived(_) => BroadcastKind::InvitationReceived,
+            Broadcast::Reattached => BroadcastKind::Reattached,
+            Broadcast::UnknownEvent(_) => BroadcastKind::UnknownEvent,
+            Broadcast::Lagged(_) => BroadcastKind::Lagged,
+        }
+    }
+}
+
+/// Channel for broadcasting events.
+pub type BroadcastReceiver = broadcast::Receiver<Broadcast>;
+
+/// Broadcasts as a [`Stream`](tokio_stream::Stream), from
+/// [`WifiSetup::event_stream`]. Unlike a bare [`BroadcastReceiver`], falling
+/// behind isn't silent: it's reported as [`Broadcast::Lagged`], which passes
+/// every filter.
+///
+/// ```no_run
+/// use futures::StreamExt;
+/// use wifi_ctrl::p2p::{BroadcastKind, WifiSetup};
+///
+/// #[tokio::main]
+/// async fn main() -> wifi_ctrl::Result {
+///     let setup = WifiSetup::new();
+///     // peers coming and going
+///     let mut events = setup
+///         .event_stream()
+///         .kinds(&[BroadcastKind::DeviceFound, BroadcastKind::DeviceLost]);
+///     while let Some(event) = events.next().await {
+///         println!("{event:?}");
+///     }
+///     Ok(())
+/// }
+/// ```
+pub struct EventStream {
+    inner: FilteredStream<Broadcast>,
+}
+
+impl EventStream {
+    pub(crate) fn new(receiver: BroadcastReceiver) -> Self {
+        Self {
+            inner: FilteredStream::new(receiver, Broadcast::Lagged),
+        }
+    }
+
+    /// Only yield broadcasts of the given kinds.
+    pub fn kinds(self, kinds: &[BroadcastKind]) -> Self {
+        let kinds = kinds.to_vec();
+        self.filter(move |broadcast| kinds.contains(&broadcast.kind()))
+    }
+
+    /// Only yield broadcasts for which `filter` returns `true`.
+    pub fn filter(mut self, filter: impl Fn(&Broadcast) -> bool + Send + Sync + 'static) -> Self {
+        self.inner.push_filter(filter);
+        self
+    }
+}
+
+impl tokio_stream::Stream for EventStream {
+    type Item = Broadcast;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Broadcast>> {
+        self.get_mut().inner.poll_next(cx)
+    }
+}
@@ -0,0 +1,137 @@
+use super::*;
+
+pub(crate) struct EventSocket {
+    socket_handle: SocketHandle<1024>,
+    /// Commands registering the socket for events, replayed to re-attach
+    attach_commands: Vec<String>,
+}
+
+#[derive(Debug)]
+pub(crate) enum Event {
+    DeviceFound(DeviceFound),
+    DeviceLost(MacAddr),
+    FindStopped,
+    GoNegRequest(GoNegRequest),
+    GoNegSuccess(GoNegSuccess),
+    GoNegFailure(i32),
+    GroupStarted(GroupStarted),
+    GroupRemoved(GroupRemoved),
+    InvitationReceived(InvitationReceived),
+    Unknown(String),
+}
+
+impl From<Event> for Broadcast {
+    fn from(event: Event) -> Self {
+        match event {
+            Event::DeviceFound(device) => Broadcast::DeviceFound(device),
+            Event::DeviceLost(address) => Broadcast::DeviceLost(address),
+            Event::FindStopped => Broadcast::FindStopped,
+            Event::GoNegRequest(request) => Broadcast::GoNegRequest(request),
+            Event::GoNegSuccess(success) => Broadcast::GoNegSuccess(success),
+            Event::GoNegFailure(status) => Broadcast::GoNegFailure(status),
+            Event::GroupStarted(group) => Broadcast::GroupStarted(group),
+            Event::GroupRemoved(group) => Broadcast::GroupRemoved(group),
+            Event::InvitationReceived(invitation) => Broadcast::InvitationReceived(invitation),
+            Event::Unknown(msg) => Broadcast::UnknownEvent(msg),
+        }
+    }
+}
+
+impl EventSocket {
+    /// Open the event socket and register it with `ATTACH`, retrying until
+    /// wpa_supplicant answers `OK`. The same command is replayed if the
+    /// keepalive finds the socket dropped.
+    pub(crate) async fn new<S>(
+        control_interface: &ControlInterface,
+        request_receiver: &mut mpsc::Receiver<S>,
+        attach_options: &[String],
+        command_timeout: Duration,
+        attach_retries: usize,
+        attach_retry_delay: Duration,
+    ) -> SocketResult<(Vec<S>, Self)>
+    where
+        S: ShutdownSignal,
+    {
+        let (mut socket_handle, deferred_requests) = SocketHandle::open(
+            control_interface,
+            "wpa_p2p_async.sock",
+            request_receiver,
+            command_timeout,
+        )
+        .await?;
+        let attach_commands = vec![attach_command(attach_options)];
+        for command in &attach_commands {
+            retry_command(
+                &mut socket_handle,
+                command.as_bytes(),
+                attach_retries,
+                attach_retry_delay,
+            )
+            .await?;
+        }
+        info!("wpa_ctrl p2p event stream registered");
+        Ok((
+            deferred_requests,
+            Self {
+                socket_handle,
+                attach_commands,
+            },
+        ))
+    }
+
+    /// Returns whether the socket had to be re-attached, see
+    /// [`SocketHandle::check_attached`].
+    pub(crate) async fn keepalive(&mut self) -> SocketResult<bool> {
+        self.socket_handle
+            .check_attached(&self.attach_commands)
+            .await
+    }
+
+    pub(crate) async fn recv(&mut self) -> SocketResult<Event> {
+        // events that turned up on this socket while waiting for a handshake
+        // reply come first
+        let data_str = match self.socket_handle.take_unsolicited() {
+            Some(data_str) => data_str,
+            None => String::from_utf8_lossy(self.socket_handle.recv().await?).into_owned(),
+        };
+        debug!("wpa_ctrl p2p event: {data_str}");
+        Ok(Self::parse(&data_str))
+    }
+
+    /// Parse an event datagram, which may have come in on the request socket
+    /// (see [`SocketHandle::take_unsolicited`]).
+    pub(crate) fn parse(data_str: &str) -> Event {
+        // the event without its `<level>` prefix
+        let body = match data_str
+            .strip_prefix('<')
+            .and_then(|rest| rest.split_once('>'))
+        {
+            Some((_, body)) => body,
+            None => data_str,
+        };
+        let (name, body) = body.split_once(' ').unwrap_or((body.trim_end(), ""));
+        let event = match name {
+            "P2P-DEVICE-FOUND" => DeviceFound::parse(body).map(Event::DeviceFound),
+            "P2P-DEVICE-LOST" => body
+                .trim()
+                .strip_prefix("p2p_dev_addr=")
+                .and_then(|address| address.parse().ok())
+                .map(Event::DeviceLost),
+            "P2P-FIND-STOPPED" => Some(Event::FindStopped),
+            "P2P-GO-NEG-REQUEST" => GoNegRequest::parse(body).map(Event::GoNegRequest),
+            "P2P-GO-NEG-SUCCESS" => GoNegSuccess::parse(body).map(Event::GoNegSuccess),
+            "P2P-GO-NEG-FAILURE" => body
+                .split_whitespace()
+                .find_map(|word| word.strip_prefix("status="))
+                .and_then(|status| status.parse().ok())
+                .map(Event::GoNegFailure),
+            "P2P-GROUP-STARTED" => GroupStarted::parse(body).map(Event::GroupStarted),
+            "P2P-GROUP-REMOVED" => GroupRemoved::parse(body).map(Event::GroupRemoved),
+            "P2P-INVITATION-RECEIVED" => {
+                InvitationReceived::parse(body).map(Event::InvitationReceived)
+            }
+            _ => None,
+        };
+        event.unwrap_or_else(|| Event::Unknown(data_str.trim_end().into()))
+    }
+}
@@ -0,0 +1,263 @@
+use crate::error::{ClientError, ParseError};
+use crate::sta::NetworkResult;
+
+use super::*;
+
+use std::fmt::Display;
+use tokio::time::{Duration, Instant};
+
+mod types;
+pub use types::*;
+
+mod client;
+pub use client::*;
+
+mod setup;
+pub use setup::*;
+
+mod event_socket;
+use event_socket::*;
+
+/// The P2P device interface wpa_supplicant creates alongside `wlan2` when the
+/// driver supports a dedicated one; otherwise P2P runs on `wlan2` itself.
+const PATH_DEFAULT_SERVER: &str = "/var/run/wpa_supplicant/p2p-dev-wlan2";
+
+/// Instance that runs the Wi-Fi Direct process
+pub struct WifiP2p {
+    /// Path to the socket
+    control_interface: ControlInterface,
+    /// Options to pass to the wpa_supplicant attach command
+    attach_options: Vec<String>,
+    /// Channel for receiving requests
+    request_receiver: mpsc::Receiver<Request>,
+    /// Channel for broadcasting alerts
+    broadcast_sender: broadcast::Sender<Broadcast>,
+    /// Channel for sending requests to itself
+    self_sender: mpsc::Sender<Request>,
+    /// How long to wait for a reply to a control command/request
+    command_timeout: Duration,
+    /// How many times to retry the attach handshake before giving up
+    attach_retries: usize,
+    /// How long to wait between attach handshake attempts
+    attach_retry_delay: Duration,
+    /// How often to check the event socket is still attached, if at all
+    keepalive_interval: Option<Duration>,
+}
+
+impl WifiP2p {
+    pub async fn run(&mut self) -> SocketResult {
+        info!("Starting Wifi P2P process");
+        let (socket_handle, mut deferred_requests) = SocketHandle::open(
+            &self.control_interface,
+            "mapper_wpa_p2p_sync.sock",
+            &mut self.request_receiver,
+            self.command_timeout,
+        )
+        .await?;
+        // We start up a separate socket for receiving the "unexpected" events
+        let (next_deferred_requests, event_socket) = EventSocket::new(
+            &self.control_interface,
+            &mut self.request_receiver,
+            &self.attach_options,
+            self.command_timeout,
+            self.attach_retries,
+            self.attach_retry_delay,
+        )
+        .await?;
+        deferred_requests.extend(next_deferred_requests);
+        for request in deferred_requests {
+            self.self_sender
+                .send(request)
+                .await
+                .expect("self_sender should never close as same struct owns both ends");
+        }
+        self.broadcast(Broadcast::Ready);
+        self.run_internal(event_socket, socket_handle).await
+    }
+
+    fn broadcast(&self, event: Broadcast) {
+        if self.broadcast_sender.send(event).is_err() {
+            debug!("broadcast listener closed")
+        }
+    }
+
+    async fn run_internal(
+        &mut self,
+        mut event_socket: EventSocket,
+        mut socket_handle: SocketHandle<4096>,
+    ) -> SocketResult {
+        enum EventOrRequest {
+            Event(Event),
+            Request(Option<Request>),
+            Keepalive,
+        }
+
+        let mut keepalive = Keepalive::new(self.keepalive_interval);
+        loop {
+            // events that arrived on the request socket in place of a reply
+            let event_or_request = if let Some(msg) = socket_handle.take_unsolicited() {
+                EventOrRequest::Event(EventSocket::parse(&msg))
+            } else {
+                tokio::select!(
+                    event = event_socket.recv() => EventOrRequest::Event(event?),
+                    request = self.request_receiver.recv() => EventOrRequest::Request(request),
+                    _ = keepalive.due() => EventOrRequest::Keepalive,
+                )
+            };
+            match event_or_request {
+                EventOrRequest::Event(event) => self.broadcast(event.into()),
+                EventOrRequest::Request(request) => match request {
+                    Some(Request::Shutdown) => return Ok(()),
+                    Some(request) => Self::handle_request(&mut socket_handle, request).await?,
+                    None => return Err(error::SocketError::ClientChannelClosed),
+                },
+                EventOrRequest::Keepalive => {
+                    if event_socket.keepalive().await? {
+                        self.broadcast(Broadcast::Reattached);
+                    }
+                }
+            }
+        }
+    }
+
+    async fn handle_request<const N: usize>(
+        socket_handle: &mut SocketHandle<N>,
+        request: Request,
+    ) -> SocketResult {
+        let (deadline, request) = request.split_deadline();
+        if request.is_abandoned() {
+            debug!("Skipping request abandoned by its client");
+            return Ok(());
+        }
+        socket_handle.set_deadline(deadline);
+        let handled = Self::send_request(socket_handle, request).await;
+        socket_handle.set_deadline(None);
+        handled
+    }
+
+    async fn send_request<const N: usize>(
+        socket_handle: &mut SocketHandle<N>,
+        request: Request,
+    ) -> SocketResult {
+        debug!("Handling request: {request:?}");
+        match request {
+            Request::Custom(custom, response) => {
+                let data_str = socket_handle.request(&custom, TryInto::try_into).await?;
+                debug!("Custom request response: {data_str:?}");
+                let _ = response.send(data_str);
+            }
+            Request::Find(timeout, response) => {
+                let cmd = match timeout {
+                    Some(timeout) => format!("P2P_FIND {}", timeout.as_secs().max(1)),
+                    None => "P2P_FIND".to_string(),
+                };
+                let _ = response.send(socket_handle.command(cmd.as_bytes()).await?);
+            }
+            Request::StopFind(response) => {
+                let _ = response.send(socket_handle.command(b"P2P_STOP_FIND").await?);
+            }
+            Request::Connect(peer, connect, response) => match connect.to_command(peer) {
+                Ok(cmd) => {
+                    let reply = socket_handle.request(&cmd, P2pConnect::parse_reply).await?;
+                    let _ = response.send(reply);
+                }
+                Err(e) => {
+                    let _ = response.send(Err(e));
+                }
+            },
+            Request::GroupAdd(group_add, response) => {
+                let cmd = group_add.to_command();
+                let _ = response.send(socket_handle.command(cmd.as_bytes()).await?);
+            }
+            Request::GroupRemove(ifname, response) => match check_ifname(&ifname) {
+                Ok(ifname) => {
+                    let cmd = format!("P2P_GROUP_REMOVE {ifname}");
+                    let _ = response.send(socket_handle.command(cmd.as_bytes()).await?);
+                }
+                Err(e) => {
+                    let _ = response.send(Err(e));
+                }
+            },
+            Request::Invite(invite, response) => match invite.to_command() {
+                Ok(cmd) => {
+                    let _ = response.send(socket_handle.command(cmd.as_bytes()).await?);
+                }
+                Err(e) => {
+                    let _ = response.send(Err(e));
+                }
+            },
+            Request::Peers(response) => {
+                let peers = socket_handle
+                    .request("P2P_PEERS", Peer::list_from_response)
+                    .await?;
+                let _ = response.send(peers);
+            }
+            Request::Peer(address, response) => {
+                let peer = socket_handle
+                    .request(&format!("P2P_PEER {address}"), Peer::from_response)
+                    .await?;
+                let _ = response.send(peer);
+            }
+            Request::PersistentGroups(response) => {
+                let groups = NetworkResult::request_results(socket_handle)
+                    .await?
+                    .map(|networks| {
+                        networks
+                            .into_iter()
+                            .filter(|network| network.flags.contains("[P2P-PERSISTENT]"))
+                            .collect()
+                    });
+                let _ = response.send(groups);
+            }
+            Request::RemovePersistentGroup(id, response) => {
+                let cmd = format!("REMOVE_NETWORK {id}");
+                let _ = response.send(socket_handle.command(cmd.as_bytes()).await?);
+            }
+            // unwrapped by handle_request
+            Request::WithDeadline(..) => (),
+            Request::Shutdown => (), //shutdown is handled at the scope above
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::socket_handle::tests::FakeDaemon;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn requests_are_sent_and_events_streamed() {
+        let mut daemon = FakeDaemon::start(|_| Some("OK\n".into()));
+        let mut setup = WifiSetup::new();
+        setup.set_socket_path(&daemon.path);
+        let mut events = setup
+            .event_stream()
+            .kinds(&[BroadcastKind::DeviceLost, BroadcastKind::FindStopped]);
+        let client = setup.get_request_client();
+        let mut runner = setup.complete();
+        let runner = tokio::spawn(async move { runner.run().await });
+
+        client.p2p_find(Some(Duration::from_secs(5))).await.unwrap();
+        assert_eq!(daemon.next_command().await, "P2P_FIND 5");
+
+        // only the kinds asked for come through
+        daemon
+            .send_event("<3>P2P-GROUP-REMOVED p2p-wlan2-0 GO reason=REQUESTED")
+            .await;
+        daemon
+            .send_event("<3>P2P-DEVICE-LOST p2p_dev_addr=02:00:00:00:01:00")
+            .await;
+        daemon.send_event("<3>P2P-FIND-STOPPED").await;
+        let peer: MacAddr = "02:00:00:00:01:00".parse().unwrap();
+        assert!(matches!(
+            events.next().await,
+            Some(Broadcast::DeviceLost(lost)) if lost == peer
+        ));
+        assert!(matches!(events.next().await, Some(Broadcast::FindStopped)));
+
+        client.shutdown().await.unwrap();
+        assert!(runner.await.unwrap().is_ok());
+    }
+}
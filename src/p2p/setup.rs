@@ -0,0 +1,113 @@
+use super::*;
+use crate::sta::{
+    DEFAULT_ATTACH_RETRIES, DEFAULT_ATTACH_RETRY_DELAY, DEFAULT_CHANNEL_SIZE,
+    DEFAULT_COMMAND_TIMEOUT,
+};
+
+/// Setup struct for the Wi-Fi Direct process.
+pub struct WifiSetup {
+    /// Struct for handling runtime process
+    wifi: WifiP2p,
+    /// Client for making requests
+    request_client: RequestClient,
+}
+
+impl WifiSetup {
+    pub fn new() -> Self {
+        Self::with_capacities(DEFAULT_CHANNEL_SIZE, DEFAULT_CHANNEL_SIZE)
+    }
+
+    /// Like [`Self::new`] but with explicit request and broadcast channel
+    /// capacities (both default to 32).
+    pub fn with_capacities(request_channel_size: usize, broadcast_channel_size: usize) -> Self {
+        // setup the channel for client requests
+        let (self_sender, request_receiver) = mpsc::channel(request_channel_size);
+        let request_client = RequestClient::new(self_sender.clone());
+        // setup the sender for broadcasts; receivers subscribe on demand
+        let broadcast_sender = broadcast::Sender::new(broadcast_channel_size);
+
+        Self {
+            wifi: WifiP2p {
+                control_interface: ControlInterface::Unix(PATH_DEFAULT_SERVER.into()),
+                attach_options: vec![],
+                request_receiver,
+                broadcast_sender,
+                self_sender,
+                command_timeout: DEFAULT_COMMAND_TIMEOUT,
+                attach_retries: DEFAULT_ATTACH_RETRIES,
+                attach_retry_delay: DEFAULT_ATTACH_RETRY_DELAY,
+                keepalive_interval: None,
+            },
+            request_client,
+        }
+    }
+
+    /// Set the path of the P2P device's control socket, e.g.
+    /// `/var/run/wpa_supplicant/p2p-dev-wlan0`, or of the station interface
+    /// if the driver has no dedicated P2P device.
+    pub fn set_socket_path<S: Into<std::path::PathBuf>>(&mut self, path: S) {
+        self.wifi.control_interface = ControlInterface::Unix(path.into());
+    }
+
+    /// Connect through `control_interface` instead of the default Unix
+    /// socket, e.g. `ControlInterface::Udp` for a wpa_supplicant built with
+    /// `CONFIG_CTRL_IFACE=udp`.
+    pub fn set_control_interface(&mut self, control_interface: ControlInterface) {
+        self.wifi.control_interface = control_interface;
+    }
+
+    pub fn add_attach_options(&mut self, options: &[&str]) {
+        for o in options {
+            self.wifi.attach_options.push(o.to_string());
+        }
+    }
+
+    /// Set how long to wait for a reply to a control command/request before
+    /// giving up with [`ClientError::Timeout`](crate::error::ClientError::Timeout).
+    pub fn set_command_timeout(&mut self, timeout: Duration) {
+        self.wifi.command_timeout = timeout;
+    }
+
+    /// Set how many times to retry the `ATTACH` handshake before giving up
+    /// with [`SocketError::AttachFailed`](crate::error::SocketError::AttachFailed).
+    pub fn set_attach_retries(&mut self, retries: usize) {
+        self.wifi.attach_retries = retries;
+    }
+
+    /// Set how long to wait between attach handshake attempts.
+    pub fn set_attach_retry_delay(&mut self, delay: Duration) {
+        self.wifi.attach_retry_delay = delay;
+    }
+
+    /// Periodically `PING` the event socket and `DETACH` and re-attach it if
+    /// wpa_supplicant stops answering, broadcasting [`Broadcast::Reattached`].
+    /// As with [`sta::WifiSetup::set_keepalive_interval`](crate::sta::WifiSetup::set_keepalive_interval),
+    /// a `PONG` doesn't prove the socket is still attached. Off by default.
+    pub fn set_keepalive_interval(&mut self, interval: Duration) {
+        self.wifi.keepalive_interval = Some(interval);
+    }
+
+    pub fn get_broadcast_receiver(&self) -> BroadcastReceiver {
+        self.wifi.broadcast_sender.subscribe()
+    }
+
+    /// Like [`Self::get_broadcast_receiver`], as a [`Stream`](tokio_stream::Stream)
+    /// that can be filtered and reports lag as [`Broadcast::Lagged`].
+    pub fn event_stream(&self) -> EventStream {
+        EventStream::new(self.wifi.broadcast_sender.subscribe())
+    }
+
+    pub fn get_request_client(&self) -> RequestClient {
+        self.request_client.clone()
+    }
+
+    pub fn complete(self) -> WifiP2p {
+        self.wifi
+    }
+}
+
+impl Default for WifiSetup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
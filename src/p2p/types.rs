@@ -0,0 +1,625 @@
+use super::*;
+use crate::config::{ConfigError, unprintf};
+
+/// How the WPS exchange of a [`P2pConnect`] is authorized.
+#[derive(Debug, Clone, Default)]
+pub enum WpsMethod {
+    /// Push button: accept the peer without a PIN
+    #[default]
+    Pbc,
+    /// Show a PIN for the peer to enter. wpa_supplicant generates one if
+    /// `None`, and [`RequestClient::p2p_connect`] returns it.
+    DisplayPin(Option<String>),
+    /// Enter the PIN shown by the peer
+    KeypadPin(String),
+}
+
+impl WpsMethod {
+    fn to_command(&self) -> Result<String> {
+        fn check(pin: &str) -> Result<&str> {
+            if matches!(pin.len(), 4 | 8) && pin.bytes().all(|b| b.is_ascii_digit()) {
+                Ok(pin)
+            } else {
+                Err(ClientError::InvalidParameter("pin"))
+            }
+        }
+        Ok(match self {
+            WpsMethod::Pbc => "pbc".to_string(),
+            WpsMethod::DisplayPin(None) => "pin display".to_string(),
+            WpsMethod::DisplayPin(Some(pin)) => format!("{} display", check(pin)?),
+            WpsMethod::KeypadPin(pin) => format!("{} keypad", check(pin)?),
+        })
+    }
+}
+
+/// Whether a group is persistent, i.e. stored as a network so it can be
+/// re-established later without provisioning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Persistent {
+    /// Store the group as a new persistent group
+    New,
+    /// Re-establish the stored persistent group with this network id, see
+    /// [`RequestClient::list_persistent_groups`]
+    Reinvoke(usize),
+}
+
+impl Display for Persistent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Persistent::New => write!(f, "persistent"),
+            Persistent::Reinvoke(id) => write!(f, "persistent={id}"),
+        }
+    }
+}
+
+/// Options for [`RequestClient::p2p_connect`].
+#[derive(Debug, Clone, Default)]
+pub struct P2pConnect {
+    pub method: WpsMethod,
+    /// How much we want to be group owner, 0-15, in GO negotiation;
+    /// wpa_supplicant's `p2p_go_intent` if unset
+    pub go_intent: Option<u8>,
+    pub persistent: Option<Persistent>,
+    /// Join a group the peer already runs instead of negotiating a new one
+    pub join: bool,
+    /// Operating frequency in MHz to prefer for the group
+    pub freq: Option<u32>,
+}
+
+impl P2pConnect {
+    pub fn new(method: WpsMethod) -> Self {
+        Self {
+            method,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn to_command(&self, peer: MacAddr) -> Result<String> {
+        let mut cmd = format!("P2P_CONNECT {peer} {}", self.method.to_command()?);
+        if let Some(persistent) = self.persistent {
+            cmd.push_str(&format!(" {persistent}"));
+        }
+        if self.join {
+            cmd.push_str(" join");
+        }
+        if let Some(go_intent) = self.go_intent {
+            if go_intent > 15 {
+                return Err(ClientError::InvalidParameter("go_intent"));
+            }
+            cmd.push_str(&format!(" go_intent={go_intent}"));
+        }
+        if let Some(freq) = self.freq {
+            cmd.push_str(&format!(" freq={freq}"));
+        }
+        Ok(cmd)
+    }
+
+    /// The reply to `P2P_CONNECT`: `OK`, or the PIN wpa_supplicant generated.
+    pub(crate) fn parse_reply(reply: &str) -> ParseResult<Option<String>> {
+        if reply == "OK" {
+            Ok(None)
+        } else if !reply.is_empty() && reply.bytes().all(|b| b.is_ascii_digit()) {
+            Ok(Some(reply.to_string()))
+        } else {
+            Err(ParseError::NotOK)
+        }
+    }
+}
+
+/// Options for [`RequestClient::p2p_group_add`], which starts a group with us
+/// as group owner without negotiating with a peer first.
+#[derive(Debug, Clone, Default)]
+pub struct GroupAdd {
+    pub persistent: Option<Persistent>,
+    /// Operating frequency in MHz
+    pub freq: Option<u32>,
+}
+
+impl GroupAdd {
+    pub(crate) fn to_command(&self) -> String {
+        let mut cmd = "P2P_GROUP_ADD".to_string();
+        if let Some(persistent) = self.persistent {
+            cmd.push_str(&format!(" {persistent}"));
+        }
+        if let Some(freq) = self.freq {
+            cmd.push_str(&format!(" freq={freq}"));
+        }
+        cmd
+    }
+}
+
+/// An invitation sent with [`RequestClient::p2p_invite`].
+#[derive(Debug, Clone)]
+pub enum Invite {
+    /// Re-establish a stored persistent group with a peer that was a member
+    Persistent { id: usize, peer: MacAddr },
+    /// Invite a peer into a group that's already running on `ifname`
+    Group { ifname: String, peer: MacAddr },
+}
+
+impl Invite {
+    pub(crate) fn to_command(&self) -> Result<String> {
+        Ok(match self {
+            Invite::Persistent { id, peer } => format!("P2P_INVITE persistent={id} peer={peer}"),
+            Invite::Group { ifname, peer } => {
+                format!("P2P_INVITE group={} peer={peer}", check_ifname(ifname)?)
+            }
+        })
+    }
+}
+
+/// The role we have in a group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupRole {
+    /// Group owner, running the group as an access point
+    Go,
+    Client,
+}
+
+impl GroupRole {
+    fn parse(role: &str) -> Option<Self> {
+        match role {
+            "GO" => Some(GroupRole::Go),
+            "client" => Some(GroupRole::Client),
+            _ => None,
+        }
+    }
+}
+
+/// A peer from `P2P_PEER`, see [`RequestClient::p2p_peer`].
+#[derive(Debug, Clone)]
+pub struct Peer {
+    /// P2P device address
+    pub address: MacAddr,
+    pub device_name: String,
+    /// Primary device type, e.g. `1-0050F204-1` for a computer
+    pub pri_dev_type: String,
+    pub manufacturer: String,
+    pub model_name: String,
+    /// WPS config methods the peer supports, as a bitmask
+    pub config_methods: u16,
+    pub dev_capab: u8,
+    pub group_capab: u8,
+    /// Signal level in dBm
+    pub level: i32,
+    pub listen_freq: u32,
+    /// Frequency of the group the peer is in, 0 if none
+    pub oper_freq: u32,
+}
+
+impl Peer {
+    /// Parse a `P2P_PEER` response: the device address, then `key=value`
+    /// lines.
+    pub(crate) fn from_response(response: &str) -> ParseResult<Peer> {
+        let mut lines = response.lines();
+        let address = lines
+            .next()
+            .and_then(|address| address.parse().ok())
+            .ok_or_else(|| ConfigError::InvalidField("address".to_string()))?;
+        let mut peer = Peer {
+            address,
+            device_name: String::new(),
+            pri_dev_type: String::new(),
+            manufacturer: String::new(),
+            model_name: String::new(),
+            config_methods: 0,
+            dev_capab: 0,
+            group_capab: 0,
+            level: 0,
+            listen_freq: 0,
+            oper_freq: 0,
+        };
+        for (key, value) in lines.filter_map(|line| line.split_once('=')) {
+            match key {
+                "device_name" => peer.device_name = value.to_string(),
+                "pri_dev_type" => peer.pri_dev_type = value.to_string(),
+                "manufacturer" => peer.manufacturer = value.to_string(),
+                "model_name" => peer.model_name = value.to_string(),
+                "config_methods" => peer.config_methods = u16::from_str_radix(hex(value), 16)?,
+                "dev_capab" => peer.dev_capab = u8::from_str_radix(hex(value), 16)?,
+                "group_capab" => peer.group_capab = u8::from_str_radix(hex(value), 16)?,
+                "level" => peer.level = value.parse()?,
+                "listen_freq" => peer.listen_freq = value.parse()?,
+                "oper_freq" => peer.oper_freq = value.parse()?,
+                _ => (),
+            }
+        }
+        Ok(peer)
+    }
+
+    /// Parse a `P2P_PEERS` response: a device address per line.
+    pub(crate) fn list_from_response(response: &str) -> ParseResult<Vec<MacAddr>> {
+        let mut peers = Vec::new();
+        for line in response.lines() {
+            match line.parse() {
+                Ok(address) => peers.push(address),
+                Err(_) => warn!("Invalid peer address: {line}"),
+            }
+        }
+        Ok(peers)
+    }
+}
+
+/// The digits of a `0x`-prefixed hex value such as `config_methods=0x188`.
+fn hex(value: &str) -> &str {
+    value.trim_start_matches("0x")
+}
+
+/// A `P2P-DEVICE-FOUND` event: a peer seen while [finding](RequestClient::p2p_find).
+#[derive(Debug, Clone)]
+pub struct DeviceFound {
+    /// P2P device address
+    pub address: MacAddr,
+    pub name: String,
+    pub pri_dev_type: String,
+    pub config_methods: u16,
+    pub dev_capab: u8,
+    pub group_capab: u8,
+    /// First time the peer is seen, rather than an update
+    pub new: bool,
+}
+
+impl DeviceFound {
+    /// Parse the body of a `P2P-DEVICE-FOUND <addr> p2p_dev_addr=<addr>
+    /// pri_dev_type=<type> name='<name>' ...` event.
+    pub(crate) fn parse(body: &str) -> Option<Self> {
+        let words = words(body);
+        let mut device = DeviceFound {
+            address: words.first()?.parse().ok()?,
+            name: String::new(),
+            pri_dev_type: String::new(),
+            config_methods: 0,
+            dev_capab: 0,
+            group_capab: 0,
+            new: false,
+        };
+        for (key, value) in fields(&words) {
+            match key {
+                "p2p_dev_addr" => device.address = value.parse().ok()?,
+                "name" => device.name = value.to_string(),
+                "pri_dev_type" => device.pri_dev_type = value.to_string(),
+                "config_methods" => {
+                    device.config_methods = u16::from_str_radix(hex(value), 16).ok()?
+                }
+                "dev_capab" => device.dev_capab = u8::from_str_radix(hex(value), 16).ok()?,
+                "group_capab" => device.group_capab = u8::from_str_radix(hex(value), 16).ok()?,
+                "new" => device.new = value == "1",
+                _ => (),
+            }
+        }
+        Some(device)
+    }
+}
+
+/// A `P2P-GO-NEG-REQUEST` event: a peer wants to form a group with us. Answer
+/// with [`RequestClient::p2p_connect`].
+#[derive(Debug, Clone)]
+pub struct GoNegRequest {
+    pub peer: MacAddr,
+    /// The WPS device password id the peer asked for, e.g. 4 for PBC
+    pub dev_passwd_id: u16,
+    /// The peer's GO intent
+    pub go_intent: u8,
+}
+
+impl GoNegRequest {
+    /// Parse the body of a `P2P-GO-NEG-REQUEST <addr> dev_passwd_id=<id>
+    /// go_intent=<intent>` event.
+    pub(crate) fn parse(body: &str) -> Option<Self> {
+        let words = words(body);
+        let mut request = GoNegRequest {
+            peer: words.first()?.parse().ok()?,
+            dev_passwd_id: 0,
+            go_intent: 0,
+        };
+        for (key, value) in fields(&words) {
+            match key {
+                "dev_passwd_id" => request.dev_passwd_id = value.parse().ok()?,
+                "go_intent" => request.go_intent = value.parse().ok()?,
+                _ => (),
+            }
+        }
+        Some(request)
+    }
+}
+
+/// A `P2P-GO-NEG-SUCCESS` event: GO negotiation settled our role, and the
+/// group is being formed.
+#[derive(Debug, Clone)]
+pub struct GoNegSuccess {
+    pub role: GroupRole,
+    pub freq: u32,
+    /// The peer's P2P device address
+    pub peer_dev: MacAddr,
+    /// The peer's address in the group
+    pub peer_iface: MacAddr,
+}
+
+impl GoNegSuccess {
+    /// Parse the body of a `P2P-GO-NEG-SUCCESS role=<role> freq=<freq>
+    /// peer_dev=<addr> peer_iface=<addr> ...` event.
+    pub(crate) fn parse(body: &str) -> Option<Self> {
+        let words = words(body);
+        let (mut role, mut freq, mut peer_dev, mut peer_iface) = (None, 0, None, None);
+        for (key, value) in fields(&words) {
+            match key {
+                "role" => role = GroupRole::parse(value),
+                "freq" => freq = value.parse().ok()?,
+                "peer_dev" => peer_dev = value.parse().ok(),
+                "peer_iface" => peer_iface = value.parse().ok(),
+                _ => (),
+            }
+        }
+        Some(GoNegSuccess {
+            role: role?,
+            freq,
+            peer_dev: peer_dev?,
+            peer_iface: peer_iface?,
+        })
+    }
+}
+
+/// A `P2P-GROUP-STARTED` event: we're now in a group running on its own
+/// interface.
+#[derive(Clone)]
+pub struct GroupStarted {
+    /// The group interface, e.g. `p2p-wlan0-0`
+    pub ifname: String,
+    pub role: GroupRole,
+    pub ssid: String,
+    pub freq: u32,
+    /// The group's passphrase, only reported to the group owner
+    pub passphrase: Option<String>,
+    /// The group owner's P2P device address
+    pub go_dev_addr: MacAddr,
+    pub persistent: bool,
+}
+
+// The passphrase lets anyone join the group, so keep it out of logs.
+impl std::fmt::Debug for GroupStarted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GroupStarted")
+            .field("ifname", &self.ifname)
+            .field("role", &self.role)
+            .field("ssid", &self.ssid)
+            .field("freq", &self.freq)
+            .field(
+                "passphrase",
+                &self.passphrase.as_ref().map(|_| "<redacted>"),
+            )
+            .field("go_dev_addr", &self.go_dev_addr)
+            .field("persistent", &self.persistent)
+            .finish()
+    }
+}
+
+impl GroupStarted {
+    /// Parse the body of a `P2P-GROUP-STARTED <ifname> <GO|client>
+    /// ssid="<ssid>" freq=<freq> [passphrase="<passphrase>"]
+    /// go_dev_addr=<addr> [PERSISTENT]` event.
+    pub(crate) fn parse(body: &str) -> Option<Self> {
+        let words = words(body);
+        let mut group = GroupStarted {
+            ifname: words.first()?.to_string(),
+            role: GroupRole::parse(words.get(1)?)?,
+            ssid: String::new(),
+            freq: 0,
+            passphrase: None,
+            go_dev_addr: MacAddr::from([0; 6]),
+            persistent: words.iter().any(|word| word == "[PERSISTENT]"),
+        };
+        for (key, value) in fields(&words) {
+            match key {
+                "ssid" => group.ssid = unprintf(value).unwrap_or_else(|_| value.to_string()),
+                "freq" => group.freq = value.parse().ok()?,
+                "passphrase" => group.passphrase = Some(value.to_string()),
+                "go_dev_addr" => group.go_dev_addr = value.parse().ok()?,
+                _ => (),
+            }
+        }
+        Some(group)
+    }
+}
+
+/// A `P2P-GROUP-REMOVED` event.
+#[derive(Debug, Clone)]
+pub struct GroupRemoved {
+    pub ifname: String,
+    pub role: GroupRole,
+    /// Why, e.g. `REQUESTED` or `GO_ENDING_SESSION`
+    pub reason: String,
+}
+
+impl GroupRemoved {
+    /// Parse the body of a `P2P-GROUP-REMOVED <ifname> <GO|client>
+    /// reason=<reason>` event.
+    pub(crate) fn parse(body: &str) -> Option<Self> {
+        let words = words(body);
+        let reason = fields(&words)
+            .find(|(key, _)| *key == "reason")
+            .map(|(_, reason)| reason.to_string())
+            .unwrap_or_default();
+        Some(GroupRemoved {
+            ifname: words.first()?.to_string(),
+            role: GroupRole::parse(words.get(1)?)?,
+            reason,
+        })
+    }
+}
+
+/// A `P2P-INVITATION-RECEIVED` event: a peer invited us into a group.
+#[derive(Debug, Clone)]
+pub struct InvitationReceived {
+    /// The inviting peer
+    pub peer: MacAddr,
+    /// Our stored persistent group the invitation re-establishes, if any
+    pub persistent_id: Option<usize>,
+}
+
+impl InvitationReceived {
+    /// Parse the body of a `P2P-INVITATION-RECEIVED sa=<addr>
+    /// [persistent=<id>] ...` event.
+    pub(crate) fn parse(body: &str) -> Option<Self> {
+        let words = words(body);
+        let (mut peer, mut persistent_id) = (None, None);
+        for (key, value) in fields(&words) {
+            match key {
+                "sa" => peer = value.parse().ok(),
+                "persistent" => persistent_id = value.parse().ok(),
+                _ => (),
+            }
+        }
+        Some(InvitationReceived {
+            peer: peer?,
+            persistent_id,
+        })
+    }
+}
+
+/// Split an event body into words, keeping a `'...'` or `"..."` value, which
+/// may hold spaces, in one word with its quotes removed. Backslash escapes
+/// inside quotes are left as they are.
+fn words(body: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quote = None;
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {
+                word.push(c);
+                if c == '\\' {
+                    word.extend(chars.next());
+                }
+            }
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None if c.is_whitespace() => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            None => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+fn fields(words: &[String]) -> impl Iterator<Item = (&str, &str)> {
+    words.iter().filter_map(|word| word.split_once('='))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connect_commands_are_built_and_validated() {
+        let peer: MacAddr = "02:00:00:00:01:00".parse().unwrap();
+        let connect = P2pConnect {
+            go_intent: Some(15),
+            persistent: Some(Persistent::Reinvoke(2)),
+            ..P2pConnect::new(WpsMethod::DisplayPin(None))
+        };
+        assert_eq!(
+            connect.to_command(peer).unwrap(),
+            "P2P_CONNECT 02:00:00:00:01:00 pin display persistent=2 go_intent=15"
+        );
+        let join = P2pConnect {
+            join: true,
+            ..P2pConnect::new(WpsMethod::KeypadPin("12345670".into()))
+        };
+        assert_eq!(
+            join.to_command(peer).unwrap(),
+            "P2P_CONNECT 02:00:00:00:01:00 12345670 keypad join"
+        );
+        let bad_pin = P2pConnect::new(WpsMethod::KeypadPin("1234 go_intent=0".into()));
+        assert!(bad_pin.to_command(peer).is_err());
+        let bad_intent = P2pConnect {
+            go_intent: Some(16),
+            ..Default::default()
+        };
+        assert!(bad_intent.to_command(peer).is_err());
+        assert_eq!(
+            P2pConnect::parse_reply("12345670").unwrap().as_deref(),
+            Some("12345670")
+        );
+        assert_eq!(P2pConnect::parse_reply("OK").unwrap(), None);
+        let invite = Invite::Group {
+            ifname: "p2p-wlan0-0 persistent=1".into(),
+            peer,
+        };
+        assert!(invite.to_command().is_err());
+    }
+
+    #[test]
+    fn events_are_parsed() {
+        let device = DeviceFound::parse(
+            "02:00:00:00:01:00 p2p_dev_addr=02:00:00:00:02:00 pri_dev_type=1-0050F204-1 \
+             name='Living Room TV' config_methods=0x188 dev_capab=0x25 group_capab=0x0 new=1",
+        )
+        .unwrap();
+        assert_eq!(device.address.to_string(), "02:00:00:00:02:00");
+        assert_eq!(device.name, "Living Room TV");
+        assert_eq!(device.config_methods, 0x188);
+        assert!(device.new);
+
+        let group = GroupStarted::parse(
+            "p2p-wlan0-0 GO ssid=\"DIRECT-ab my group\" freq=2437 \
+             passphrase=\"secret12\" go_dev_addr=02:00:00:00:01:00 [PERSISTENT]",
+        )
+        .unwrap();
+        assert_eq!(group.role, GroupRole::Go);
+        assert_eq!(group.ssid, "DIRECT-ab my group");
+        assert_eq!(group.passphrase.as_deref(), Some("secret12"));
+        assert!(group.persistent);
+        assert!(!format!("{group:?}").contains("secret12"));
+
+        let success = GoNegSuccess::parse(
+            "role=client freq=2412 ht40=0 peer_dev=02:00:00:00:01:00 \
+             peer_iface=02:00:00:00:01:01 wps_method=Display",
+        )
+        .unwrap();
+        assert_eq!(success.role, GroupRole::Client);
+        assert_eq!(success.peer_iface.to_string(), "02:00:00:00:01:01");
+
+        let removed = GroupRemoved::parse("p2p-wlan0-0 client reason=GO_ENDING_SESSION").unwrap();
+        assert_eq!(removed.reason, "GO_ENDING_SESSION");
+
+        assert!(matches!(
+            EventSocket::parse("<3>P2P-GO-NEG-FAILURE status=1 "),
+            Event::GoNegFailure(1)
+        ));
+        assert!(matches!(
+            EventSocket::parse("<3>P2P-DEVICE-LOST p2p_dev_addr=02:00:00:00:01:00"),
+            Event::DeviceLost(_)
+        ));
+        assert!(matches!(
+            EventSocket::parse("<3>P2P-FIND-STOPPED"),
+            Event::FindStopped
+        ));
+        assert!(matches!(
+            EventSocket::parse("<3>P2P-GROUP-STARTED malformed"),
+            Event::Unknown(_)
+        ));
+    }
+
+    #[test]
+    fn peers_are_parsed() {
+        let peer = Peer::from_response(
+            "02:00:00:00:01:00\npri_dev_type=10-0050F204-5\ndevice_name=Printer\n\
+             manufacturer=ACME\nconfig_methods=0x80\ndev_capab=0x25\ngroup_capab=0x0\n\
+             level=-42\nlisten_freq=2412\noper_freq=0\n",
+        )
+        .unwrap();
+        assert_eq!(peer.device_name, "Printer");
+        assert_eq!(peer.level, -42);
+        assert_eq!(peer.dev_capab, 0x25);
+        assert!(Peer::from_response("FAIL").is_err());
+        let peers = Peer::list_from_response("02:00:00:00:01:00\n02:00:00:00:02:00").unwrap();
+        assert_eq!(peers.len(), 2);
+    }
+}
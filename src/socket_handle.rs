@@ -1,46 +1,52 @@
 use super::*;
-use error::{ClientError, ParseError};
+use error::{ClientError, DaemonError, ParseError};
+use std::collections::VecDeque;
 use std::io::ErrorKind;
 use std::time::Duration;
-use tokio::net::UnixDatagram;
+use tokio::time::Instant;
+use transport::{ControlInterface, Transport};
 
 pub(crate) struct SocketHandle<const N: usize> {
-    #[allow(unused)]
-    /// Temporary directory for socket. If it drops, socket breaks.
-    tmp_dir: tempfile::TempDir,
     /// Socket for synchronous messages
-    pub(crate) socket: UnixDatagram,
+    transport: Box<dyn Transport>,
     buffer: [u8; N],
     /// How long to wait for a reply before giving up on a command/request
     command_timeout: Duration,
+    /// Deadline of the request being handled, overriding `command_timeout`
+    deadline: Option<Instant>,
+    /// `IFNAME=<ifname> ` prefix directing commands at one interface through
+    /// the wpa_supplicant global control socket
+    interface_prefix: Option<String>,
+    /// Event datagrams that turned up while waiting for a reply, held for
+    /// the event pipeline
+    unsolicited: VecDeque<String>,
 }
 
 const RETRY_MINUTES: u64 = 5;
 
 impl<const N: usize> SocketHandle<N> {
-    pub(crate) async fn open<P, S>(
-        path: P,
+    pub(crate) async fn open<S>(
+        control_interface: &ControlInterface,
         label: &str,
         request_channel: &mut mpsc::Receiver<S>,
         command_timeout: Duration,
     ) -> SocketResult<(Self, Vec<S>)>
     where
-        P: AsRef<std::path::Path> + std::fmt::Debug,
         S: ShutdownSignal,
     {
-        let tmp_dir = tempfile::tempdir()?;
-        let connect_from = tmp_dir.path().join(label);
-        let socket = UnixDatagram::bind(connect_from)?;
-        let socket_debug = &format!("{path:?}");
+        let socket_debug = &match control_interface {
+            ControlInterface::Unix(path) => format!("{path:?}"),
+            ControlInterface::Udp(addr) => format!("udp:{addr}"),
+        };
         // loop around waiting for the socket for up to 5 minutes
         let mut deferred_requests = Vec::new();
         let deferred_requests_handle = &mut deferred_requests;
-        let socket = tokio::select!(
+        let transport = tokio::select!(
             resp = async move  {
                 let mut loop_count = 0;
-                let s: SocketResult<UnixDatagram> = loop {
-                    match socket.connect(&path) {
-                        Ok(()) => break Ok(socket),
+                let s: SocketResult<Box<dyn Transport>> = loop {
+                    match control_interface.connect(label, command_timeout).await {
+                        Ok(transport) => break Ok(transport),
                         Err(e) => {
                             // if socket is there but permission denied, fail fast
                             if e.kind() == ErrorKind::PermissionDenied {
@@ -74,20 +80,72 @@ impl<const N: usize> SocketHandle<N> {
 
         Ok((
             Self {
-                tmp_dir,
-                socket: socket?,
+                transport: transport?,
                 buffer: [0; N],
                 command_timeout,
+                deadline: None,
+                interface_prefix: None,
+                unsolicited: VecDeque::new(),
             },
             deferred_requests,
         ))
     }
 
+    /// Direct subsequent commands at `ifname` (or back at the socket's own
+    /// interface with `None`) by prefixing them with `IFNAME=<ifname>`.
+    pub(crate) fn set_interface(&mut self, ifname: Option<&str>) {
+        self.interface_prefix = ifname.map(|ifname| format!("IFNAME={ifname} "));
+    }
+
+    /// Wait for replies until `deadline` rather than for the command timeout,
+    /// until cleared with `None`. Set while handling a request from a client
+    /// made with `RequestClient::with_timeout`.
+    pub(crate) fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    fn reply_deadline(&self) -> Instant {
+        self.deadline
+            .unwrap_or_else(|| Instant::now() + self.command_timeout)
+    }
+
+    pub(crate) async fn send(&mut self, cmd: &[u8]) -> SocketResult<Result> {
+        let prefixed;
+        let cmd = match &self.interface_prefix {
+            Some(prefix) => {
+                prefixed = [prefix.as_bytes(), cmd].concat();
+                &prefixed
+            }
+            None => cmd,
+        };
+        let n = std::future::poll_fn(|cx| self.transport.poll_send(cx, cmd)).await?;
+        if n != cmd.len() {
+            return Ok(Err(ClientError::DidNotWriteAllBytes(n, cmd.len())));
+        }
+        Ok(Ok(()))
+    }
+
     pub(crate) async fn recv(&mut self) -> SocketResult<&[u8]> {
-        let n = self.socket.recv(&mut self.buffer).await?;
+        let n = self.recv_len().await?;
         Ok(&self.buffer[..n])
     }
 
+    /// Take the oldest event that arrived in place of a reply, see
+    /// [`Self::parse_resp`].
+    pub(crate) fn take_unsolicited(&mut self) -> Option<String> {
+        self.unsolicited.pop_front()
+    }
+
+    async fn recv_len(&mut self) -> SocketResult<usize> {
+        let n = std::future::poll_fn(|cx| {
+            let mut buf = tokio::io::ReadBuf::new(&mut self.buffer);
+            std::task::ready!(self.transport.poll_recv(cx, &mut buf))?;
+            std::task::Poll::Ready(Ok::<_, std::io::Error>(buf.filled().len()))
+        })
+        .await?;
+        Ok(n)
+    }
+
     pub(crate) async fn command(&mut self, cmd: &[u8]) -> SocketResult<Result> {
         self.command_matching(cmd, |data| data == "OK").await
     }
@@ -99,9 +157,8 @@ impl<const N: usize> SocketHandle<N> {
         cmd: &[u8],
         accept: impl Fn(&str) -> bool,
     ) -> SocketResult<Result> {
-        let n = self.socket.send(cmd).await?;
-        if n != cmd.len() {
-            return Ok(Err(ClientError::DidNotWriteAllBytes(n, cmd.len())));
+        if let Err(e) = self.send(cmd).await? {
+            return Ok(Err(e));
         }
         let parse = |data: &str| {
             if accept(data) {
@@ -110,10 +167,10 @@ impl<const N: usize> SocketHandle<N> {
                 Err(ParseError::NotOK)
             }
         };
-        let timeout = self.command_timeout;
+        let deadline = self.reply_deadline();
         tokio::select!(
             resp = self.parse_resp(parse) => resp,
-            _ = tokio::time::sleep(timeout) =>
+            _ = tokio::time::sleep_until(deadline) =>
                 Ok(Err(ClientError::Timeout)),
         )
     }
@@ -127,37 +184,502 @@ impl<const N: usize> SocketHandle<N> {
         ParseError: From<E>,
         F: FnOnce(&'a str) -> std::result::Result<T, E>,
     {
-        let n = self.socket.send(req.as_bytes()).await?;
-        if n != req.len() {
-            return Ok(Err(ClientError::DidNotWriteAllBytes(n, req.len())));
+        if let Err(e) = self.send(req.as_bytes()).await? {
+            return Ok(Err(e));
         }
-        let timeout = self.command_timeout;
+        let deadline = self.reply_deadline();
         tokio::select!(
             resp = self.parse_resp(parse) => resp,
-            _ = tokio::time::sleep(timeout) =>
+            _ = tokio::time::sleep_until(deadline) =>
                 Ok(Err(ClientError::Timeout)),
         )
     }
 
+    /// `PING` an attached event socket, replaying `attach_commands` if no
+    /// `PONG` comes back. A `PONG` only proves the daemon is answering, not
+    /// that this socket is still attached: wpa_supplicant and hostapd answer
+    /// a monitor they've dropped just the same. Without one the socket may
+    /// still be attached to a daemon that is merely slow, so it's `DETACH`ed
+    /// first rather than attached twice, which would deliver every event
+    /// twice. Returns whether the socket was re-attached; a failed re-attach
+    /// is retried on the next check.
+    pub(crate) async fn check_attached(
+        &mut self,
+        attach_commands: &[String],
+    ) -> SocketResult<bool> {
+        // a reply that turns up after its command timed out would otherwise
+        // be read as the reply to the next one: an `OK` left over from an
+        // earlier re-attach by this `PING`, or a late `PONG` by the `ATTACH`
+        let Err(e) = self.command_discarding(b"PING", "PONG", "OK").await? else {
+            return Ok(false);
+        };
+        warn!("Event socket keepalive failed ({e}), re-attaching");
+        // `FAIL` just means the daemon had already dropped the socket
+        if let Err(e) = self.command_discarding(b"DETACH", "OK", "PONG").await? {
+            debug!("DETACH before re-attaching failed: {e}");
+        }
+        for command in attach_commands {
+            if let Err(e) = self
+                .command_discarding(command.as_bytes(), "OK", "PONG")
+                .await?
+            {
+                warn!("Re-attaching with {command:?} failed: {e}");
+                return Ok(false);
+            }
+        }
+        info!("Event socket re-attached");
+        Ok(true)
+    }
+
+    /// Like [`Self::command_matching`] for a reply of exactly `accept`, but
+    /// skipping any `stale` replies to earlier commands that come first.
+    async fn command_discarding(
+        &mut self,
+        cmd: &[u8],
+        accept: &str,
+        stale: &str,
+    ) -> SocketResult<Result> {
+        if let Err(e) = self.send(cmd).await? {
+            return Ok(Err(e));
+        }
+        let deadline = self.reply_deadline();
+        let reply = async {
+            loop {
+                let n = self.recv_reply().await?;
+                let reply = &self.buffer[..n];
+                if reply.strip_suffix(b"\n").unwrap_or(reply) != stale.as_bytes() {
+                    break SocketResult::Ok(n);
+                }
+                debug!("Discarding stale {stale:?} reply");
+            }
+        };
+        let n = tokio::select!(
+            n = reply => n?,
+            _ = tokio::time::sleep_until(deadline) => return Ok(Err(ClientError::Timeout)),
+        );
+        Ok(parse_reply(&self.buffer[..n], |data| {
+            if data == accept {
+                Ok(())
+            } else {
+                Err(ParseError::NotOK)
+            }
+        }))
+    }
+
+    /// Read and parse the reply to the command just sent, see
+    /// [`Self::recv_reply`].
     async fn parse_resp<'a, T, E, F>(&'a mut self, parse: F) -> SocketResult<Result<T>>
     where
         ParseError: From<E>,
         F: FnOnce(&'a str) -> std::result::Result<T, E>,
     {
-        let bytes = self.recv().await?;
-        let str = std::str::from_utf8(bytes).map(|r| r.trim_end_matches('\n'));
-        Ok(str
-            .map_err(Into::<ParseError>::into)
-            .and_then(|s| parse(s).map_err(Into::<ParseError>::into))
-            .map_err(|error| {
-                if str == Ok("FAIL") {
-                    ClientError::Failed
-                } else {
-                    ClientError::ParsingResponse {
-                        error,
-                        failed_response: String::from_utf8_lossy(bytes).to_string(),
+        let n = self.recv_reply().await?;
+        Ok(parse_reply(&self.buffer[..n], parse))
+    }
+
+    /// Read the reply to the command just sent into the buffer. Event
+    /// datagrams (from an attached socket, or echoed by the daemon) can
+    /// arrive first; they're set aside for [`Self::take_unsolicited`] rather
+    /// than mistaken for the reply, which would leave every later request
+    /// reading the reply to the one before it.
+    async fn recv_reply(&mut self) -> SocketResult<usize> {
+        loop {
+            let n = self.recv_len().await?;
+            if !is_event(&self.buffer[..n]) {
+                return Ok(n);
+            }
+            let event = String::from_utf8_lossy(&self.buffer[..n]).into_owned();
+            debug!("Event in place of a reply: {event}");
+            self.unsolicited.push_back(event);
+        }
+    }
+}
+
+/// Parse a reply datagram, mapping a bare `FAIL` to [`ClientError::Failed`]
+/// and other failure replies (`FAIL-BUSY`, `UNKNOWN COMMAND`, ...) to
+/// [`ClientError::Daemon`].
+pub(crate) fn parse_reply<'a, T, E, F>(bytes: &'a [u8], parse: F) -> Result<T>
+where
+    ParseError: From<E>,
+    F: FnOnce(&'a str) -> std::result::Result<T, E>,
+{
+    let str = std::str::from_utf8(bytes).map(|r| r.trim_end_matches('\n'));
+    str.map_err(Into::<ParseError>::into)
+        .and_then(|s| parse(s).map_err(Into::<ParseError>::into))
+        .map_err(|error| {
+            let reply = str.unwrap_or_default();
+            if reply == "FAIL" {
+                ClientError::Failed
+            } else if let Some(daemon_error) = DaemonError::from_reply(reply) {
+                ClientError::Daemon {
+                    error: daemon_error,
+                    response: reply.to_string(),
+                }
+            } else {
+                ClientError::ParsingResponse {
+                    error,
+                    failed_response: String::from_utf8_lossy(bytes).to_string(),
+                }
+            }
+        })
+}
+
+/// `ATTACH`, followed by any `options` (e.g. hostapd's event filters).
+pub(crate) fn attach_command(options: &[String]) -> String {
+    let mut command = "ATTACH".to_string();
+    for o in options {
+        command.push(' ');
+        command.push_str(o);
+    }
+    command
+}
+
+/// Send a control command, retrying on failure with a fixed delay up to
+/// `retries` times. Returns [`SocketError::AttachFailed`] once the attempts are
+/// exhausted so the runtime doesn't spin forever.
+pub(crate) async fn retry_command<const N: usize>(
+    socket_handle: &mut SocketHandle<N>,
+    command: &[u8],
+    retries: usize,
+    delay: Duration,
+) -> SocketResult {
+    for _ in 0..retries {
+        if socket_handle.command(command).await?.is_ok() {
+            return Ok(());
+        }
+        tokio::time::sleep(delay).await;
+    }
+    Err(error::SocketError::AttachFailed(
+        String::from_utf8_lossy(command).to_string(),
+    ))
+}
+
+/// Timer for checking an attached event socket is still attached, see
+/// [`SocketHandle::check_attached`].
+pub(crate) struct Keepalive {
+    interval: Option<tokio::time::Interval>,
+}
+
+impl Keepalive {
+    pub(crate) fn new(period: Option<Duration>) -> Self {
+        let interval = period.map(|period| {
+            let mut interval = tokio::time::interval_at(Instant::now() + period, period);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            interval
+        });
+        Self { interval }
+    }
+
+    /// Resolves when the next check is due; never if keepalive is off.
+    pub(crate) async fn due(&mut self) {
+        match self.interval.as_mut() {
+            Some(interval) => {
+                interval.tick().await;
+            }
+            None => std::future::pending().await,
+        }
+    }
+}
+
+/// Whether a datagram is an event, `<level>` followed by the message and
+/// possibly tagged `IFNAME=<ifname> ` by a global control socket, rather than
+/// a reply.
+pub(crate) fn is_event(data: &[u8]) -> bool {
+    let data = match data.strip_prefix(b"IFNAME=") {
+        Some(rest) => match rest.iter().position(|&b| b == b' ') {
+            Some(i) => &rest[i + 1..],
+            None => return false,
+        },
+        None => data,
+    };
+    match data.strip_prefix(b"<") {
+        Some(rest) => {
+            let digits = rest.iter().take_while(|b| b.is_ascii_digit()).count();
+            digits > 0 && rest.get(digits) == Some(&b'>')
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    #[test]
+    fn events_are_told_apart_from_replies() {
+        assert!(is_event(b"<3>CTRL-EVENT-SCAN-RESULTS "));
+        assert!(is_event(
+            b"IFNAME=wlan0 <3>CTRL-EVENT-CONNECTED - Connection"
+        ));
+        assert!(is_event(b"<2>AP-STA-CONNECTED 00:11:22:33:44:55"));
+        assert!(!is_event(b"OK\n"));
+        assert!(!is_event(b"FAIL\n"));
+        assert!(!is_event(b"bssid=00:11:22:33:44:55\nssid=<hidden>\n"));
+        assert!(!is_event(b"<>"));
+        assert!(!is_event(b"IFNAME=wlan0"));
+    }
+
+    #[test]
+    fn failure_replies_are_classified() {
+        let ok = |data: &str| {
+            if data == "OK" {
+                Ok(())
+            } else {
+                Err(ParseError::NotOK)
+            }
+        };
+        assert!(matches!(
+            parse_reply(b"FAIL\n", ok),
+            Err(ClientError::Failed)
+        ));
+        for (reply, expected) in [
+            ("FAIL-BUSY\n", DaemonError::Busy),
+            ("UNKNOWN COMMAND\n", DaemonError::UnknownCommand),
+            (
+                "FAIL-CHANNEL-UNAVAILABLE\n",
+                DaemonError::ChannelUnavailable,
+            ),
+            ("FAIL-INVALID-RANGE\n", DaemonError::InvalidRange),
+            ("FAIL-NOT-FOUND\n", DaemonError::NotFound),
+            ("FAIL-CHANNEL-UNSUPPORTED\n", DaemonError::Other),
+        ] {
+            match parse_reply(reply.as_bytes(), ok) {
+                Err(ClientError::Daemon { error, response }) => {
+                    assert_eq!(error, expected);
+                    assert_eq!(response, reply.trim_end());
+                }
+                other => panic!("{reply:?} parsed as {other:?}"),
+            }
+        }
+        assert!(matches!(
+            parse_reply(b"PONG\n", ok),
+            Err(ClientError::ParsingResponse { .. })
+        ));
+        assert!(DaemonError::Busy.is_retryable());
+        assert!(!DaemonError::UnknownCommand.is_retryable());
+    }
+
+    /// A socket handle connected to a fake daemon socket
+    pub(crate) async fn connect(
+        command_timeout: Duration,
+    ) -> (
+        tempfile::TempDir,
+        tokio::net::UnixDatagram,
+        SocketHandle<1024>,
+    ) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wlan0");
+        let daemon = tokio::net::UnixDatagram::bind(&path).unwrap();
+        let (_sender, mut requests) = mpsc::channel::<Shutdown>(1);
+        let (handle, _) = SocketHandle::open(
+            &ControlInterface::Unix(path),
+            "test.sock",
+            &mut requests,
+            command_timeout,
+        )
+        .await
+        .unwrap();
+        (dir, daemon, handle)
+    }
+
+    /// Answer each command the fake daemon receives with `reply`, or not at
+    /// all for `None`; returns the commands received.
+    pub(crate) async fn serve(
+        daemon: &tokio::net::UnixDatagram,
+        replies: &[Option<&str>],
+    ) -> Vec<String> {
+        let mut buffer = [0; 64];
+        let mut received = Vec::new();
+        for reply in replies {
+            let (n, client) = daemon.recv_from(&mut buffer).await.unwrap();
+            received.push(String::from_utf8_lossy(&buffer[..n]).into_owned());
+            if let Some(reply) = reply {
+                let client = client.as_pathname().unwrap();
+                daemon.send_to(reply.as_bytes(), client).await.unwrap();
+            }
+        }
+        received
+    }
+
+    /// A fake daemon serving a control socket to a whole runner. The attach
+    /// handshake is acknowledged and its socket kept as the monitor to send
+    /// events to; every other command is recorded and answered by `reply`,
+    /// or not at all for `None`.
+    pub(crate) struct FakeDaemon {
+        _dir: tempfile::TempDir,
+        pub(crate) path: std::path::PathBuf,
+        socket: Arc<tokio::net::UnixDatagram>,
+        monitor: tokio::sync::watch::Receiver<Option<std::path::PathBuf>>,
+        commands: mpsc::UnboundedReceiver<String>,
+    }
+
+    impl FakeDaemon {
+        pub(crate) fn start(reply: impl Fn(&str) -> Option<String> + Send + 'static) -> Self {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("ctrl");
+            let socket = Arc::new(tokio::net::UnixDatagram::bind(&path).unwrap());
+            let (monitor_sender, monitor) = tokio::sync::watch::channel(None);
+            let (command_sender, commands) = mpsc::unbounded_channel();
+            let server = socket.clone();
+            tokio::spawn(async move {
+                let mut buffer = [0; 4096];
+                while let Ok((n, client)) = server.recv_from(&mut buffer).await {
+                    let command = String::from_utf8_lossy(&buffer[..n]).into_owned();
+                    let client = client.as_pathname().unwrap().to_path_buf();
+                    let handshake = ["ATTACH", "LEVEL ", "LOG_LEVEL "];
+                    let answer = if handshake.iter().any(|c| command.starts_with(c)) {
+                        monitor_sender.send_replace(Some(client.clone()));
+                        Some("OK\n".to_string())
+                    } else {
+                        let answer = reply(&command);
+                        let _ = command_sender.send(command);
+                        answer
+                    };
+                    if let Some(answer) = answer {
+                        let _ = server.send_to(answer.as_bytes(), &client).await;
                     }
                 }
-            }))
+            });
+            Self {
+                _dir: dir,
+                path,
+                socket,
+                monitor,
+                commands,
+            }
+        }
+
+        /// The next command received, other than the attach handshake.
+        pub(crate) async fn next_command(&mut self) -> String {
+            self.commands.recv().await.unwrap()
+        }
+
+        /// Send `event` to the attached monitor, once there is one.
+        pub(crate) async fn send_event(&mut self, event: &str) {
+            let monitor = self
+                .monitor
+                .wait_for(Option::is_some)
+                .await
+                .unwrap()
+                .clone();
+            let monitor = monitor.unwrap();
+            self.socket
+                .send_to(event.as_bytes(), monitor)
+                .await
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn events_before_the_reply_are_set_aside() {
+        let (_dir, daemon, mut handle) = connect(Duration::from_secs(1)).await;
+        let fake_daemon = async {
+            let mut buffer = [0; 64];
+            let (_, client) = daemon.recv_from(&mut buffer).await.unwrap();
+            let client = client.as_pathname().unwrap();
+            for reply in [
+                "<3>CTRL-EVENT-CONNECTED - Connection",
+                "<3>CTRL-EVENT-DISCONNECTED",
+                "PONG\n",
+            ] {
+                daemon.send_to(reply.as_bytes(), client).await.unwrap();
+            }
+        };
+        let (reply, ()) = tokio::join!(
+            handle.request("PING", |s| Ok::<_, ParseError>(s.to_string())),
+            fake_daemon
+        );
+        assert_eq!(reply.unwrap().unwrap(), "PONG");
+        assert_eq!(
+            handle.take_unsolicited().as_deref(),
+            Some("<3>CTRL-EVENT-CONNECTED - Connection")
+        );
+        assert_eq!(
+            handle.take_unsolicited().as_deref(),
+            Some("<3>CTRL-EVENT-DISCONNECTED")
+        );
+        assert_eq!(handle.take_unsolicited(), None);
+    }
+
+    #[tokio::test]
+    async fn answered_ping_needs_no_reattach() {
+        let (_dir, daemon, mut handle) = connect(Duration::from_secs(1)).await;
+        let attach = ["ATTACH".to_string()];
+        let (reattached, received) = tokio::join!(
+            handle.check_attached(&attach),
+            serve(&daemon, &[Some("PONG\n")])
+        );
+        assert!(!reattached.unwrap());
+        assert_eq!(received, ["PING"]);
+    }
+
+    #[tokio::test]
+    async fn unanswered_ping_replays_attach_commands() {
+        let (_dir, daemon, mut handle) = connect(Duration::from_millis(50)).await;
+        let attach = ["ATTACH".to_string(), "LOG_LEVEL DEBUG".to_string()];
+        let (reattached, received) = tokio::join!(
+            handle.check_attached(&attach),
+            serve(&daemon, &[None, Some("FAIL\n"), Some("OK\n"), Some("OK\n")])
+        );
+        assert!(reattached.unwrap());
+        assert_eq!(received, ["PING", "DETACH", "ATTACH", "LOG_LEVEL DEBUG"]);
+    }
+
+    #[tokio::test]
+    async fn slow_daemon_is_detached_before_reattaching() {
+        let (_dir, daemon, mut handle) = connect(Duration::from_millis(50)).await;
+        let attach = ["ATTACH".to_string()];
+        let fake_daemon = async {
+            let mut buffer = [0; 64];
+            let (_, client) = daemon.recv_from(&mut buffer).await.unwrap();
+            let client = client.as_pathname().unwrap();
+            // the PONG comes back only once the keepalive has given up on it,
+            // and is not read as the reply to the DETACH or ATTACH
+            for command in ["DETACH", "ATTACH"] {
+                let (n, _) = daemon.recv_from(&mut buffer).await.unwrap();
+                assert_eq!(&buffer[..n], command.as_bytes());
+                if command == "DETACH" {
+                    daemon.send_to(b"PONG\n", client).await.unwrap();
+                }
+                daemon.send_to(b"OK\n", client).await.unwrap();
+            }
+        };
+        let (reattached, ()) = tokio::join!(handle.check_attached(&attach), fake_daemon);
+        assert!(reattached.unwrap());
+
+        // nor is a stray OK read as the reply to the next keepalive
+        let fake_daemon = async {
+            let mut buffer = [0; 64];
+            let (n, client) = daemon.recv_from(&mut buffer).await.unwrap();
+            assert_eq!(&buffer[..n], b"PING");
+            let client = client.as_pathname().unwrap();
+            daemon.send_to(b"OK\n", client).await.unwrap();
+            daemon.send_to(b"PONG\n", client).await.unwrap();
+        };
+        let (reattached, ()) = tokio::join!(handle.check_attached(&attach), fake_daemon);
+        assert!(!reattached.unwrap());
+    }
+
+    #[tokio::test]
+    async fn attach_gives_up_after_retries() {
+        let (_dir, daemon, mut handle) = connect(Duration::from_secs(1)).await;
+        let command = attach_command(&["probe_rx_events=1".to_string()]);
+        let (attached, received) = tokio::join!(
+            retry_command(&mut handle, command.as_bytes(), 2, Duration::ZERO),
+            serve(&daemon, &[Some("FAIL\n"), Some("FAIL\n")])
+        );
+        assert!(matches!(
+            attached,
+            Err(error::SocketError::AttachFailed(command)) if command == "ATTACH probe_rx_events=1"
+        ));
+        assert_eq!(received.len(), 2);
+    }
+
+    struct Shutdown;
+
+    impl ShutdownSignal for Shutdown {
+        fn is_shutdown(&self) -> bool {
+            true
+        }
     }
 }
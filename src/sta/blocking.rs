@@ -0,0 +1,612 @@
+use super::*;
+use crate::blocking::BlockingSocket;
+use crate::error::BlockingError;
+use crate::sta::{DEFAULT_COMMAND_TIMEOUT, DEFAULT_SELECT_TIMEOUT};
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Default time [`Client::get_scan`] waits for a scan to complete.
+const DEFAULT_SCAN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Synchronous wpa_supplicant client for programs without a tokio runtime.
+/// Requests go straight to the control socket from the calling thread, with
+/// the same commands, parsing and timeouts as [`RequestClient`]. Its methods
+/// are those of [`RequestClient`] except `cancel_select` and `shutdown`,
+/// which only make sense with a runner: a select here blocks until it
+/// resolves, and there is no runner to stop.
+///
+/// ```no_run
+/// use wifi_ctrl::sta::blocking::Client;
+///
+/// let mut client = Client::open("/var/run/wpa_supplicant/wlan0")?;
+/// for network in client.get_scan()?.iter() {
+///     println!("{} {}", network.signal, network.name);
+/// }
+/// for event in client.events()? {
+///     println!("{:?}", event?);
+/// }
+/// # Ok::<(), wifi_ctrl::error::BlockingError>(())
+/// ```
+pub struct Client {
+    socket_path: PathBuf,
+    socket: BlockingSocket,
+    command_timeout: Duration,
+    select_timeout: Duration,
+    scan_timeout: Duration,
+    /// Names for the BSSID ignore lists, found from the daemon's version
+    /// on first use
+    ignore_naming: Option<IgnoreNaming>,
+}
+
+impl Client {
+    /// Connect to the control socket of one interface, e.g.
+    /// `/var/run/wpa_supplicant/wlan0`.
+    pub fn open<P: AsRef<Path>>(path: P) -> BlockingResult<Self> {
+        let socket_path = path.as_ref().to_path_buf();
+        let socket = BlockingSocket::open(
+            &socket_path,
+            "wpa_ctrl_blocking.sock",
+            DEFAULT_COMMAND_TIMEOUT,
+        )?;
+        Ok(Self {
+            socket_path,
+            socket,
+            command_timeout: DEFAULT_COMMAND_TIMEOUT,
+            select_timeout: DEFAULT_SELECT_TIMEOUT,
+            scan_timeout: DEFAULT_SCAN_TIMEOUT,
+            ignore_naming: None,
+        })
+    }
+
+    /// Set how long to wait for a reply to a control command/request before
+    /// giving up with [`ClientError::Timeout`].
+    pub fn set_command_timeout(&mut self, timeout: Duration) {
+        self.command_timeout = timeout;
+        self.socket.set_command_timeout(timeout);
+    }
+
+    pub fn set_select_timeout(&mut self, timeout: Duration) {
+        self.select_timeout = timeout;
+    }
+
+    pub fn set_scan_timeout(&mut self, timeout: Duration) {
+        self.scan_timeout = timeout;
+    }
+
+    /// Open a second socket attached for events. Events are only delivered
+    /// from the moment it's attached.
+    pub fn events(&self) -> BlockingResult<Events> {
+        let mut socket = BlockingSocket::open(
+            &self.socket_path,
+            "wpa_ctrl_blocking_events.sock",
+            self.command_timeout,
+        )?;
+        socket.command("ATTACH")?;
+        Ok(Events { socket })
+    }
+
+    pub fn send_custom(&mut self, custom: &str) -> BlockingResult<String> {
+        self.socket.request(custom, TryInto::try_into)
+    }
+
+    /// Trigger a scan and return its results once complete.
+    pub fn get_scan(&mut self) -> BlockingResult<ScanResults> {
+        // attach first so the completion event can't slip past
+        let mut events = self.events()?;
+        // FAIL-BUSY: a scan is already in progress and will complete for us
+        self.socket
+            .command_matching("SCAN", |data| data == "OK" || data == "FAIL-BUSY")?;
+        let deadline = Instant::now() + self.scan_timeout;
+        loop {
+            match events.next_event(Some(deadline))? {
+                Event::ScanComplete => break,
+                Event::ScanFailed => return Err(ClientError::Failed.into()),
+                _ => (),
+            }
+        }
+        self.socket
+            .request("SCAN_RESULTS", ScanResult::vec_from_str)
+    }
+
+    pub fn get_networks(&mut self) -> BlockingResult<Vec<NetworkResult>> {
+        let response: String = self.socket.request("LIST_NETWORKS", TryInto::try_into)?;
+        let mut results = Vec::new();
+        for (network_id, flags) in NetworkResult::parse_list(&response) {
+            let ssid = self
+                .socket
+                .request(&format!("GET_NETWORK {network_id} ssid"), parse_get_network)?;
+            results.push(NetworkResult {
+                flags,
+                ssid,
+                network_id,
+            });
+        }
+        Ok(results)
+    }
+
+    pub fn get_status(&mut self) -> BlockingResult<Status> {
+        self.socket.request("STATUS", parse_status)
+    }
+
+    /// See [`RequestClient::get_capabilities`].
+    pub fn get_capabilities(&mut self) -> BlockingResult<Capabilities> {
+        let mut replies = HashMap::new();
+        for (field, cmd) in Capabilities::queries() {
+            let reply: String = self.socket.request(&cmd, TryInto::try_into)?;
+            Capabilities::keep_reply(&mut replies, field, &cmd, reply);
+        }
+        Ok(Capabilities::from_replies(&replies))
+    }
+
+    pub fn add_network(&mut self) -> BlockingResult<usize> {
+        self.socket.request("ADD_NETWORK", usize::from_str)
+    }
+
+    pub fn set_network_psk(&mut self, network_id: usize, psk: Psk) -> BlockingResult {
+        self.set_network(network_id, SetNetwork::Psk(psk))
+    }
+
+    pub fn set_network_ssid(&mut self, network_id: usize, ssid: String) -> BlockingResult {
+        self.set_network(network_id, SetNetwork::Ssid(ssid))
+    }
+
+    pub fn set_network_bssid(&mut self, network_id: usize, bssid: Bssid) -> BlockingResult {
+        self.set_network(network_id, SetNetwork::Bssid(bssid))
+    }
+
+    pub fn set_network_keymgmt(&mut self, network_id: usize, mgmt: KeyMgmt) -> BlockingResult {
+        self.set_network(network_id, SetNetwork::KeyMgmt(mgmt))
+    }
+
+    pub fn set_network_bgscan(&mut self, network_id: usize, bgscan: Bgscan) -> BlockingResult {
+        self.set_network(network_id, SetNetwork::Bgscan(bgscan))
+    }
+
+    pub fn set_network_mode(&mut self, network_id: usize, mode: NetworkMode) -> BlockingResult {
+        self.set_network(network_id, SetNetwork::Mode(mode))
+    }
+
+    pub fn set_network_frequency(&mut self, network_id: usize, freq: u32) -> BlockingResult {
+        self.set_network(network_id, SetNetwork::Frequency(freq))
+    }
+
+    pub fn set_network_freq_list(&mut self, network_id: usize, freqs: &[u32]) -> BlockingResult {
+        self.set_network(network_id, SetNetwork::FreqList(freqs.to_vec()))
+    }
+
+    pub fn set_network_priority(&mut self, network_id: usize, priority: i32) -> BlockingResult {
+        self.set_network(network_id, SetNetwork::Priority(priority))
+    }
+
+    pub fn set_network_disabled(&mut self, network_id: usize, disabled: bool) -> BlockingResult {
+        self.set_network(network_id, SetNetwork::Disabled(disabled))
+    }
+
+    pub fn set_network_scan_ssid(&mut self, network_id: usize, scan_ssid: bool) -> BlockingResult {
+        self.set_network(network_id, SetNetwork::ScanSsid(scan_ssid))
+    }
+
+    pub fn set_network_id_str(&mut self, network_id: usize, id_str: String) -> BlockingResult {
+        self.set_network(network_id, SetNetwork::IdStr(id_str))
+    }
+
+    pub fn set_network_mac_randomization(
+        &mut self,
+        network_id: usize,
+        policy: MacRandomization,
+    ) -> BlockingResult {
+        self.set_network(network_id, SetNetwork::MacAddr(policy))
+    }
+
+    /// See [`RequestClient::set_network_bssid_accept`].
+    pub fn set_network_bssid_accept(
+        &mut self,
+        network_id: usize,
+        bssids: &[Bssid],
+    ) -> BlockingResult {
+        self.set_network_bssids(network_id, BssidList::Accept, bssids)
+    }
+
+    /// See [`RequestClient::set_network_bssid_ignore`].
+    pub fn set_network_bssid_ignore(
+        &mut self,
+        network_id: usize,
+        bssids: &[Bssid],
+    ) -> BlockingResult {
+        self.set_network_bssids(network_id, BssidList::Ignore, bssids)
+    }
+
+    fn set_network_bssids(
+        &mut self,
+        network_id: usize,
+        list: BssidList,
+        bssids: &[Bssid],
+    ) -> BlockingResult {
+        let naming = self.ignore_naming()?;
+        self.socket
+            .command(&list.to_command(naming, network_id, bssids))
+    }
+
+    pub fn set_mac_randomization(&mut self, policy: MacRandomization) -> BlockingResult {
+        self.set(Set::MacAddr(policy))
+    }
+
+    pub fn set_preassoc_mac_randomization(&mut self, policy: MacRandomization) -> BlockingResult {
+        self.set(Set::PreassocMacAddr(policy))
+    }
+
+    pub fn set_rand_addr_lifetime(&mut self, lifetime: Duration) -> BlockingResult {
+        self.set(Set::RandAddrLifetime(lifetime))
+    }
+
+    pub fn set_country(&mut self, country: CountryCode) -> BlockingResult {
+        self.set(Set::Country(country))
+    }
+
+    pub fn get_country(&mut self) -> BlockingResult<Option<CountryCode>> {
+        match self.socket.request("GET country", parse_country) {
+            Err(BlockingError::Client(ClientError::Failed)) => Ok(None),
+            country => country.map(Some),
+        }
+    }
+
+    pub fn set_auto_connect(&mut self, enabled: bool) -> BlockingResult {
+        self.socket
+            .command(&format!("STA_AUTOCONNECT {}", u8::from(enabled)))
+    }
+
+    pub fn set_autoscan(&mut self, autoscan: Option<Autoscan>) -> BlockingResult {
+        match autoscan {
+            Some(autoscan) => self.socket.command(&format!("AUTOSCAN {autoscan}")),
+            None => self.socket.command("AUTOSCAN "),
+        }
+    }
+
+    fn set_network(&mut self, network_id: usize, param: SetNetwork) -> BlockingResult {
+        self.socket.command(&param.to_command(network_id))
+    }
+
+    fn set(&mut self, set: Set) -> BlockingResult {
+        self.socket.command(&set.to_command()?)
+    }
+
+    /// See [`RequestClient::bssid_ignore_add`].
+    pub fn bssid_ignore_add(&mut self, bssid: Bssid) -> BlockingResult {
+        let cmd = self.ignore_naming()?.command();
+        self.socket.command(&format!("{cmd} {bssid}"))
+    }
+
+    /// See [`RequestClient::bssid_ignore_remove`].
+    pub fn bssid_ignore_remove(&mut self, bssid: Bssid) -> BlockingResult {
+        let ignored = self.bssid_ignore_list()?;
+        if !ignored.contains(&bssid) {
+            return Ok(());
+        }
+        // there's no command to remove a single entry, so clear the list and
+        // add the others back
+        self.bssid_ignore_clear()?;
+        for other in ignored.into_iter().filter(|other| *other != bssid) {
+            self.bssid_ignore_add(other)?;
+        }
+        Ok(())
+    }
+
+    pub fn bssid_ignore_list(&mut self) -> BlockingResult<Vec<Bssid>> {
+        let cmd = self.ignore_naming()?.command();
+        self.socket.request(cmd, parse_bssid_list)
+    }
+
+    pub fn bssid_ignore_clear(&mut self) -> BlockingResult {
+        let cmd = self.ignore_naming()?.command();
+        self.socket.command(&format!("{cmd} clear"))
+    }
+
+    /// See [`RequestClient::add_credential`].
+    pub fn add_credential(&mut self, credential: Credential) -> BlockingResult<usize> {
+        let fields = credential.to_fields()?;
+        let id = self.socket.request("ADD_CRED", usize::from_str)?;
+        if let Err(e) = self.set_credential_fields(id, &fields) {
+            if let Err(e) = self.remove_credential(id) {
+                warn!("Failed to remove partially set credential {id}: {e}");
+            }
+            return Err(e);
+        }
+        Ok(id)
+    }
+
+    pub fn set_credential(&mut self, id: usize, credential: Credential) -> BlockingResult {
+        let fields = credential.to_fields()?;
+        self.set_credential_fields(id, &fields)
+    }
+
+    fn set_credential_fields(&mut self, id: usize, fields: &[(&str, String)]) -> BlockingResult {
+        for (field, value) in fields {
+            self.socket
+                .command(&format!("SET_CRED {id} {field} {value}"))?;
+        }
+        Ok(())
+    }
+
+    pub fn list_credentials(&mut self) -> BlockingResult<Vec<CredentialEntry>> {
+        self.socket
+            .request("LIST_CREDS", CredentialEntry::vec_from_str)
+    }
+
+    pub fn remove_credential(&mut self, id: usize) -> BlockingResult {
+        self.socket.command(&format!("REMOVE_CRED {id}"))
+    }
+
+    pub fn remove_all_credentials(&mut self) -> BlockingResult {
+        self.socket.command("REMOVE_CRED all")
+    }
+
+    /// See [`RequestClient::interworking_select`]; matches arrive through
+    /// [`Self::events`].
+    pub fn interworking_select(&mut self, auto: bool) -> BlockingResult {
+        self.interworking(Interworking::Select { auto })
+    }
+
+    pub fn interworking_connect(&mut self, bssid: Bssid) -> BlockingResult {
+        self.interworking(Interworking::Connect(bssid))
+    }
+
+    pub fn anqp_get(&mut self, bssid: Bssid, info_ids: &[u16]) -> BlockingResult {
+        self.interworking(Interworking::AnqpGet(bssid, info_ids.to_vec()))
+    }
+
+    pub fn hs20_anqp_get(&mut self, bssid: Bssid, subtypes: &[u8]) -> BlockingResult {
+        self.interworking(Interworking::Hs20AnqpGet(bssid, subtypes.to_vec()))
+    }
+
+    fn interworking(&mut self, interworking: Interworking) -> BlockingResult {
+        self.socket.command(&interworking.to_command()?)
+    }
+
+    /// See [`RequestClient::mesh_interface_add`]. Open a second client on
+    /// the new interface's socket to drive the mesh.
+    pub fn mesh_interface_add(&mut self, ifname: Option<&str>) -> BlockingResult<String> {
+        let cmd = match ifname {
+            Some(ifname) => format!("MESH_INTERFACE_ADD ifname={}", check_ifname(ifname)?),
+            None => "MESH_INTERFACE_ADD".to_string(),
+        };
+        self.socket.request(&cmd, parse_mesh_interface)
+    }
+
+    pub fn mesh_group_add(&mut self, network_id: usize) -> BlockingResult {
+        self.mesh(Mesh::GroupAdd(network_id))
+    }
+
+    pub fn mesh_group_remove(&mut self, ifname: &str) -> BlockingResult {
+        self.mesh(Mesh::GroupRemove(ifname.into()))
+    }
+
+    pub fn mesh_peer_add(&mut self, peer: MacAddr) -> BlockingResult {
+        self.mesh(Mesh::PeerAdd(peer))
+    }
+
+    pub fn mesh_peer_remove(&mut self, peer: MacAddr) -> BlockingResult {
+        self.mesh(Mesh::PeerRemove(peer))
+    }
+
+    fn mesh(&mut self, mesh: Mesh) -> BlockingResult {
+        self.socket.command(&mesh.to_command()?)
+    }
+
+    pub fn save_config(&mut self) -> BlockingResult {
+        self.socket.command("SAVE_CONFIG")
+    }
+
+    pub fn reload_config(&mut self) -> BlockingResult {
+        self.socket.command("RECONFIGURE")
+    }
+
+    pub fn remove_network(&mut self, id: usize) -> BlockingResult {
+        self.socket.command(&format!("REMOVE_NETWORK {id}"))
+    }
+
+    pub fn remove_all_networks(&mut self) -> BlockingResult {
+        self.socket.command("REMOVE_NETWORK all")
+    }
+
+    /// Select a network and wait up to the select timeout for the outcome,
+    /// failing with [`ClientError::Timeout`] if none arrives. As with
+    /// [`RequestClient::select_network`](super::RequestClient::select_network),
+    /// a `scan_ssid` network is probed for by SSID before it's not found.
+    pub fn select_network(&mut self, network_id: usize) -> BlockingResult<SelectResult> {
+        let mut events = self.events()?;
+        self.socket
+            .command(&format!("SELECT_NETWORK {network_id}"))?;
+        if self.get_status()?.id == Some(network_id) {
+            return Ok(SelectResult::AlreadyConnected);
+        }
+        let deadline = Instant::now() + self.select_timeout;
+        let mut probed = false;
+        loop {
+            match events.next_event(Some(deadline))? {
+                Event::Connected => return Ok(SelectResult::Success),
+                // a hidden network may only answer a probe for its SSID, so
+                // look for it by name once before giving up
+                Event::NetworkNotFound if !probed => match self.directed_probe(network_id)? {
+                    DirectedProbe::Started => probed = true,
+                    // the scan already under way ended; probe after the next
+                    DirectedProbe::Busy => (),
+                    DirectedProbe::NotHidden => return Ok(SelectResult::NotFound),
+                },
+                Event::NetworkNotFound => return Ok(SelectResult::NotFound),
+                Event::WrongPsk => return Ok(SelectResult::WrongPsk),
+                _ => (),
+            }
+        }
+    }
+}
+
+impl Client {
+    /// The daemon's names for the BSSID ignore lists. One that can't answer
+    /// `GET version` predates the rename.
+    fn ignore_naming(&mut self) -> BlockingResult<IgnoreNaming> {
+        if let Some(naming) = self.ignore_naming {
+            return Ok(naming);
+        }
+        let naming = match self
+            .socket
+            .request("GET version", TryInto::<String>::try_into)
+        {
+            Ok(version) => IgnoreNaming::from_version(version.trim()),
+            Err(BlockingError::Client(ClientError::Failed | ClientError::Daemon { .. })) => {
+                IgnoreNaming::Legacy
+            }
+            Err(e) => return Err(e),
+        };
+        self.ignore_naming = Some(naming);
+        Ok(naming)
+    }
+
+    /// Start a scan probing for the network by SSID if it's hidden
+    /// (`scan_ssid=1`).
+    fn directed_probe(&mut self, network_id: usize) -> BlockingResult<DirectedProbe> {
+        let cmd = format!("GET_NETWORK {network_id} scan_ssid");
+        if !self.socket.request(&cmd, parse_scan_ssid)? {
+            return Ok(DirectedProbe::NotHidden);
+        }
+        let cmd = format!("GET_NETWORK {network_id} ssid");
+        let ssid_hex = self.socket.request(&cmd, parse_ssid_hex)?;
+        let scan = match self.socket.command(&directed_scan_command(&ssid_hex)) {
+            Ok(()) => Ok(()),
+            Err(BlockingError::Client(e)) => Err(e),
+            Err(e) => return Err(e),
+        };
+        Ok(DirectedProbe::from_scan(scan)?)
+    }
+}
+
+/// Blocking iterator over the events of an attached socket, from
+/// [`Client::events`]. It waits for the next event indefinitely.
+pub struct Events {
+    socket: BlockingSocket,
+}
+
+impl Events {
+    fn next_event(&mut self, deadline: Option<Instant>) -> BlockingResult<Event> {
+        let data_str = self.socket.recv_event(deadline)?;
+        debug!("wpa_ctrl event: {data_str}");
+        Ok(EventSocket::parse(&data_str).1)
+    }
+}
+
+impl Iterator for Events {
+    type Item = BlockingResult<Broadcast>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let broadcast = match self.next_event(None) {
+                Ok(Event::Connected) => Broadcast::Connected,
+                Ok(Event::Disconnected) => Broadcast::Disconnected,
+                Ok(Event::NetworkNotFound) => Broadcast::NetworkNotFound,
+                Ok(Event::WrongPsk) => Broadcast::WrongPsk,
+                Ok(Event::InterworkingAp(ap)) => Broadcast::InterworkingAp(ap),
+                Ok(Event::InterworkingNoMatch) => Broadcast::InterworkingNoMatch,
+                Ok(Event::Anqp(info)) => Broadcast::AnqpReceived(info),
+                Ok(Event::Hs20Anqp(info)) => Broadcast::Hs20AnqpReceived(info),
+                Ok(Event::MeshGroupStarted(group)) => Broadcast::MeshGroupStarted(group),
+                Ok(Event::MeshGroupRemoved(ifname)) => Broadcast::MeshGroupRemoved(ifname),
+                Ok(Event::MeshPeerConnected(peer)) => Broadcast::MeshPeerConnected(peer),
+                Ok(Event::MeshPeerDisconnected(peer)) => Broadcast::MeshPeerDisconnected(peer),
+                Ok(Event::RegdomChanged(change)) => Broadcast::RegdomChanged(change),
+                Ok(Event::Unknown(msg)) => Broadcast::Unknown(msg),
+                // the async runtime fetches the results to broadcast them,
+                // which takes a request of its own; use Client::get_scan
+                Ok(Event::ScanComplete | Event::ScanFailed) => continue,
+                Err(e) => return Some(Err(e)),
+            };
+            return Some(Ok(broadcast));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixDatagram;
+
+    #[test]
+    fn select_network_waits_for_the_outcome() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wlan0");
+        let daemon = UnixDatagram::bind(&path).unwrap();
+        let fake_daemon = std::thread::spawn(move || {
+            let mut buffer = [0; 64];
+            let mut commands = Vec::new();
+            let mut monitor = None;
+            while commands.len() < 3 {
+                let (n, from) = daemon.recv_from(&mut buffer).unwrap();
+                let from = from.as_pathname().unwrap().to_path_buf();
+                let command = String::from_utf8_lossy(&buffer[..n]).into_owned();
+                let reply = match command.as_str() {
+                    "ATTACH" => {
+                        monitor = Some(from.clone());
+                        "OK\n"
+                    }
+                    "SELECT_NETWORK 1" => "OK\n",
+                    "STATUS" => "wpa_state=SCANNING\n",
+                    _ => "FAIL\n",
+                };
+                daemon.send_to(reply.as_bytes(), &from).unwrap();
+                commands.push(command);
+            }
+            let event = b"<3>CTRL-EVENT-SSID-TEMP-DISABLED id=1 ssid=\"home\" reason=WRONG_KEY";
+            daemon.send_to(event, monitor.unwrap()).unwrap();
+            commands
+        });
+        let mut client = Client::open(&path).unwrap();
+        assert!(matches!(
+            client.select_network(1).unwrap(),
+            SelectResult::WrongPsk
+        ));
+        assert_eq!(
+            fake_daemon.join().unwrap(),
+            ["ATTACH", "SELECT_NETWORK 1", "STATUS"]
+        );
+    }
+
+    #[test]
+    fn bssid_ignore_uses_the_daemons_naming() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wlan0");
+        let daemon = UnixDatagram::bind(&path).unwrap();
+        let fake_daemon = std::thread::spawn(move || {
+            let mut buffer = [0; 64];
+            let mut commands = Vec::new();
+            while commands.len() < 5 {
+                let (n, from) = daemon.recv_from(&mut buffer).unwrap();
+                let command = String::from_utf8_lossy(&buffer[..n]).into_owned();
+                // a daemon older than 2.10
+                let reply = match command.as_str() {
+                    "GET version" => "UNKNOWN COMMAND\n",
+                    "BLACKLIST" => "02:00:00:00:01:00\n02:00:00:00:02:00\n",
+                    _ => "OK\n",
+                };
+                daemon
+                    .send_to(reply.as_bytes(), from.as_pathname().unwrap())
+                    .unwrap();
+                commands.push(command);
+            }
+            commands
+        });
+        let mut client = Client::open(&path).unwrap();
+        let flaky: Bssid = "02:00:00:00:01:00".parse().unwrap();
+        client.bssid_ignore_remove(flaky).unwrap();
+        client.set_network_bssid_accept(0, &[flaky]).unwrap();
+        assert_eq!(
+            fake_daemon.join().unwrap(),
+            [
+                "GET version",
+                "BLACKLIST",
+                "BLACKLIST clear",
+                "BLACKLIST 02:00:00:00:02:00",
+                "SET_NETWORK 0 bssid_whitelist 02:00:00:00:01:00",
+            ]
+        );
+    }
+}
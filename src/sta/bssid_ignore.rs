@@ -0,0 +1,191 @@
+use super::*;
+use crate::error::ParseError;
+
+/// Which names the daemon uses for BSSID ignore lists: wpa_supplicant 2.10
+/// renamed the `BLACKLIST` command to `BSSID_IGNORE`, and the
+/// `bssid_blacklist` and `bssid_whitelist` network fields to `bssid_ignore`
+/// and `bssid_accept`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum IgnoreNaming {
+    Current,
+    Legacy,
+}
+
+impl IgnoreNaming {
+    /// Pick the naming for a `GET version` reply such as `2.10` or
+    /// `2.9-devel`.
+    pub(crate) fn from_version(version: &str) -> Self {
+        let mut numbers = version
+            .split(|c: char| !c.is_ascii_digit())
+            .map(|n| n.parse::<u32>().ok());
+        match (numbers.next().flatten(), numbers.next().flatten()) {
+            (Some(major), Some(minor)) if (major, minor) >= (2, 10) => IgnoreNaming::Current,
+            _ => IgnoreNaming::Legacy,
+        }
+    }
+
+    /// Ask the daemon for its version. One that can't answer `GET version`
+    /// predates the rename.
+    pub(crate) async fn request<const N: usize>(
+        socket_handle: &mut SocketHandle<N>,
+    ) -> SocketResult<Result<Self>> {
+        let version: Result<String> = socket_handle
+            .request("GET version", TryInto::try_into)
+            .await?;
+        Ok(match version {
+            Ok(version) => Ok(Self::from_version(version.trim())),
+            Err(ClientError::Failed | ClientError::Daemon { .. }) => Ok(IgnoreNaming::Legacy),
+            Err(e) => Err(e),
+        })
+    }
+
+    pub(crate) fn command(self) -> &'static str {
+        match self {
+            IgnoreNaming::Current => "BSSID_IGNORE",
+            IgnoreNaming::Legacy => "BLACKLIST",
+        }
+    }
+}
+
+/// A change to the BSSID ignore list, which wpa_supplicant skips when
+/// picking an access point to connect to.
+#[derive(Debug)]
+pub(crate) enum BssidIgnore {
+    Add(Bssid),
+    Remove(Bssid),
+    Clear,
+}
+
+impl BssidIgnore {
+    pub(crate) async fn apply<const N: usize>(
+        &self,
+        socket_handle: &mut SocketHandle<N>,
+        naming: IgnoreNaming,
+    ) -> SocketResult<Result> {
+        let cmd = naming.command();
+        let bssid = match self {
+            BssidIgnore::Add(bssid) => {
+                return socket_handle
+                    .command(format!("{cmd} {bssid}").as_bytes())
+                    .await;
+            }
+            BssidIgnore::Clear => {
+                return socket_handle
+                    .command(format!("{cmd} clear").as_bytes())
+                    .await;
+            }
+            BssidIgnore::Remove(bssid) => bssid,
+        };
+        // there's no command to remove a single entry, so clear the list and
+        // add the others back
+        let ignored = match list_bssid_ignore(socket_handle, naming).await? {
+            Ok(ignored) => ignored,
+            Err(e) => return Ok(Err(e)),
+        };
+        if !ignored.contains(bssid) {
+            return Ok(Ok(()));
+        }
+        if let Err(e) = socket_handle
+            .command(format!("{cmd} clear").as_bytes())
+            .await?
+        {
+            return Ok(Err(e));
+        }
+        for other in ignored.into_iter().filter(|other| other != bssid) {
+            if let Err(e) = socket_handle
+                .command(format!("{cmd} {other}").as_bytes())
+                .await?
+            {
+                warn!("Failed to restore ignored BSSID {other}: {e}");
+                return Ok(Err(e));
+            }
+        }
+        Ok(Ok(()))
+    }
+}
+
+pub(crate) async fn list_bssid_ignore<const N: usize>(
+    socket_handle: &mut SocketHandle<N>,
+    naming: IgnoreNaming,
+) -> SocketResult<Result<Vec<Bssid>>> {
+    socket_handle
+        .request(naming.command(), parse_bssid_list)
+        .await
+}
+
+/// The reply to a bare `BSSID_IGNORE`: one BSSID per line.
+pub(crate) fn parse_bssid_list(reply: &str) -> ParseResult<Vec<Bssid>> {
+    // a line that isn't a BSSID, e.g. `FAIL`, is classified by the caller
+    reply
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.trim().parse().map_err(|_| ParseError::NotOK))
+        .collect()
+}
+
+/// A per-network list of access points: the only ones to connect to, or ones
+/// to avoid.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum BssidList {
+    Accept,
+    Ignore,
+}
+
+impl BssidList {
+    pub(crate) fn to_command(self, naming: IgnoreNaming, id: usize, bssids: &[Bssid]) -> String {
+        let field = match (self, naming) {
+            (BssidList::Accept, IgnoreNaming::Current) => "bssid_accept",
+            (BssidList::Accept, IgnoreNaming::Legacy) => "bssid_whitelist",
+            (BssidList::Ignore, IgnoreNaming::Current) => "bssid_ignore",
+            (BssidList::Ignore, IgnoreNaming::Legacy) => "bssid_blacklist",
+        };
+        let bssids = bssids
+            .iter()
+            .map(Bssid::to_string)
+            .collect::<Vec<_>>()
+            .join(" ");
+        // an empty list clears the field
+        format!("SET_NETWORK {id} {field} {bssids}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn naming_follows_the_version() {
+        assert_eq!(IgnoreNaming::from_version("2.10"), IgnoreNaming::Current);
+        assert_eq!(
+            IgnoreNaming::from_version("2.11-devel"),
+            IgnoreNaming::Current
+        );
+        assert_eq!(IgnoreNaming::from_version("3.0"), IgnoreNaming::Current);
+        assert_eq!(
+            IgnoreNaming::from_version("2.9-devel"),
+            IgnoreNaming::Legacy
+        );
+    }
+
+    #[test]
+    fn bssid_lists_are_built_and_parsed() {
+        let bssids: Vec<Bssid> = vec![
+            "02:00:00:00:01:00".parse().unwrap(),
+            "02:00:00:00:02:00".parse().unwrap(),
+        ];
+        assert_eq!(
+            BssidList::Accept.to_command(IgnoreNaming::Current, 1, &bssids),
+            "SET_NETWORK 1 bssid_accept 02:00:00:00:01:00 02:00:00:00:02:00"
+        );
+        assert_eq!(
+            BssidList::Ignore.to_command(IgnoreNaming::Legacy, 1, &[]),
+            "SET_NETWORK 1 bssid_blacklist "
+        );
+        assert_eq!(
+            parse_bssid_list("02:00:00:00:01:00\n02:00:00:00:02:00\n").unwrap(),
+            bssids
+        );
+        assert!(parse_bssid_list("").unwrap().is_empty());
+        assert!(parse_bssid_list("FAIL\n").is_err());
+    }
+}
@@ -0,0 +1,277 @@
+use super::*;
+use crate::error::DaemonError;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// The `GET_CAPABILITY` fields queried by [`RequestClient::get_capabilities`].
+/// `channels` is covered by `freq`, which adds each channel's frequency.
+const CAPABILITY_FIELDS: [&str; 14] = [
+    "eap", "pairwise", "group", "key_mgmt", "proto", "auth_alg", "modes", "freq", "tdls", "erp",
+    "fips", "acs", "sae", "dpp",
+];
+
+/// Frequency band of a [`Channel`].
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Band {
+    Band2GHz,
+    Band5GHz,
+    Band6GHz,
+    Band60GHz,
+}
+
+impl Band {
+    pub fn from_freq(freq: u32) -> Option<Self> {
+        match freq {
+            2400..=2500 => Some(Band::Band2GHz),
+            4900..=5925 => Some(Band::Band5GHz),
+            5926..=7125 => Some(Band::Band6GHz),
+            57000..=71000 => Some(Band::Band60GHz),
+            _ => None,
+        }
+    }
+}
+
+/// A channel the driver can use, from `GET_CAPABILITY freq`. Disabled
+/// channels aren't reported.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Channel {
+    /// The hardware mode listing it: `B`, `G`, `A` or `AD`
+    pub mode: String,
+    pub channel: u16,
+    /// Centre frequency in MHz
+    pub freq: u32,
+    /// Initiating radiation (active scanning, beaconing) isn't allowed
+    pub no_ir: bool,
+    /// Radar detection is required before use
+    pub dfs: bool,
+}
+
+impl Channel {
+    pub fn band(&self) -> Option<Band> {
+        Band::from_freq(self.freq)
+    }
+}
+
+/// What wpa_supplicant and the driver support, from `GET_CAPABILITY`,
+/// `DRIVER_FLAGS` and `DRIVER_FLAGS2`.
+///
+/// Lists hold the names wpa_supplicant reports, e.g. `SAE` or `OWE` in
+/// [`Capabilities::key_mgmt`]. A field wpa_supplicant doesn't know, because
+/// it's an older version or built without the feature, is left empty.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct Capabilities {
+    pub eap: Vec<String>,
+    pub pairwise: Vec<String>,
+    pub group: Vec<String>,
+    pub key_mgmt: Vec<String>,
+    pub proto: Vec<String>,
+    pub auth_alg: Vec<String>,
+    /// Interface modes besides station, e.g. `AP` or `MESH`
+    pub modes: Vec<String>,
+    pub channels: Vec<Channel>,
+    /// TDLS is supported, whether set up by the driver or wpa_supplicant
+    pub tdls: bool,
+    /// EAP Re-authentication Protocol
+    pub erp: bool,
+    pub fips: bool,
+    /// Automatic channel selection
+    pub acs: bool,
+    /// SAE extensions, e.g. `H2E` (hash-to-element) and `PK` (public key)
+    pub sae: Vec<String>,
+    /// Highest DPP version supported, if any
+    pub dpp: Option<u8>,
+    /// Names of the driver capability flags that are set
+    pub driver_flags: Vec<String>,
+    pub driver_flags2: Vec<String>,
+}
+
+impl Capabilities {
+    /// WPA3-Personal: SAE key management is supported by both wpa_supplicant
+    /// and the driver.
+    pub fn supports_sae(&self) -> bool {
+        self.key_mgmt.iter().any(|k| k == "SAE")
+    }
+
+    /// SAE's hash-to-element, required for WPA3 on 6 GHz.
+    pub fn supports_sae_h2e(&self) -> bool {
+        self.supports_sae() && self.sae.iter().any(|s| s == "H2E")
+    }
+
+    /// Opportunistic Wireless Encryption (Enhanced Open).
+    pub fn supports_owe(&self) -> bool {
+        self.key_mgmt.iter().any(|k| k == "OWE")
+    }
+
+    pub fn supports_band(&self, band: Band) -> bool {
+        self.channels.iter().any(|c| c.band() == Some(band))
+    }
+
+    /// Channel numbers usable in `band`, in the order reported.
+    pub fn supported_channels(&self, band: Band) -> Vec<u16> {
+        self.channels
+            .iter()
+            .filter(|c| c.band() == Some(band))
+            .map(|c| c.channel)
+            .collect()
+    }
+
+    pub fn has_driver_flag(&self, flag: &str) -> bool {
+        self.driver_flags
+            .iter()
+            .chain(&self.driver_flags2)
+            .any(|f| f == flag)
+    }
+
+    pub(crate) async fn request<const N: usize>(
+        socket_handle: &mut SocketHandle<N>,
+    ) -> SocketResult<Result<Capabilities>> {
+        let mut replies = HashMap::new();
+        for (field, cmd) in Self::queries() {
+            let reply: String = match socket_handle.request(&cmd, TryInto::try_into).await? {
+                Ok(reply) => reply,
+                Err(e) => return Ok(Err(e)),
+            };
+            Self::keep_reply(&mut replies, field, &cmd, reply);
+        }
+        Ok(Ok(Self::from_replies(&replies)))
+    }
+
+    /// Each field with the command querying it.
+    pub(crate) fn queries() -> impl Iterator<Item = (&'static str, String)> {
+        CAPABILITY_FIELDS
+            .iter()
+            .map(|field| (*field, format!("GET_CAPABILITY {field}")))
+            .chain([
+                ("driver_flags", "DRIVER_FLAGS".to_string()),
+                ("driver_flags2", "DRIVER_FLAGS2".to_string()),
+            ])
+    }
+
+    /// Keep the reply to `cmd` for `field`, unless it says the daemon or
+    /// driver doesn't support the query.
+    pub(crate) fn keep_reply(
+        replies: &mut HashMap<&'static str, String>,
+        field: &'static str,
+        cmd: &str,
+        reply: String,
+    ) {
+        if reply != "FAIL" && DaemonError::from_reply(&reply).is_none() {
+            replies.insert(field, reply);
+        } else {
+            debug!("wpa_ctrl {cmd} unsupported: {reply}");
+        }
+    }
+
+    /// Build from the replies to each query, keyed by field name, leaving out
+    /// those that failed.
+    pub(crate) fn from_replies(replies: &HashMap<&str, String>) -> Self {
+        let reply = |field: &str| replies.get(field).map(String::as_str).unwrap_or_default();
+        let words = |field: &str| reply(field).split_whitespace().map(String::from).collect();
+        Self {
+            eap: words("eap"),
+            pairwise: words("pairwise"),
+            group: words("group"),
+            key_mgmt: words("key_mgmt"),
+            proto: words("proto"),
+            auth_alg: words("auth_alg"),
+            modes: words("modes"),
+            channels: parse_channels(reply("freq")),
+            tdls: matches!(reply("tdls"), "EXTERNAL" | "INTERNAL"),
+            erp: reply("erp") == "ERP",
+            fips: reply("fips") == "FIPS",
+            acs: reply("acs") == "ACS",
+            sae: words("sae"),
+            dpp: reply("dpp")
+                .strip_prefix("DPP=")
+                .and_then(|v| v.parse().ok()),
+            driver_flags: parse_driver_flags(reply("driver_flags")),
+            driver_flags2: parse_driver_flags(reply("driver_flags2")),
+        }
+    }
+}
+
+/// Parse `GET_CAPABILITY freq`: a `Mode[<mode>] Channels:` header per
+/// hardware mode, then ` <channel> = <freq> MHz` lines, each optionally
+/// followed by ` (NO_IR)` and ` (DFS)`.
+fn parse_channels(response: &str) -> Vec<Channel> {
+    let mut channels = Vec::new();
+    let mut mode = None;
+    for line in response.lines() {
+        if let Some(header) = line.strip_prefix("Mode[") {
+            mode = header.split_once(']').map(|(mode, _)| mode.to_string());
+            continue;
+        }
+        let Some(mode) = &mode else { continue };
+        let Some((channel, rest)) = line.split_once(" = ") else {
+            continue;
+        };
+        let mut rest = rest.split_whitespace();
+        let (Ok(channel), Some(Ok(freq))) = (channel.trim().parse(), rest.next().map(str::parse))
+        else {
+            warn!("Invalid channel in capabilities: {line}");
+            continue;
+        };
+        let flags: Vec<_> = rest.collect();
+        channels.push(Channel {
+            mode: mode.clone(),
+            channel,
+            freq,
+            no_ir: flags.contains(&"(NO_IR)"),
+            dfs: flags.contains(&"(DFS)"),
+        });
+    }
+    channels
+}
+
+/// Parse `DRIVER_FLAGS`/`DRIVER_FLAGS2`: the flags as hex on the first line,
+/// then the name of each flag set, one per line.
+fn parse_driver_flags(response: &str) -> Vec<String> {
+    response
+        .lines()
+        .skip(1)
+        .map(str::trim)
+        .filter(|flag| !flag.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capabilities_are_parsed() {
+        let replies = HashMap::from([
+            (
+                "key_mgmt",
+                "NONE IEEE8021X WPA-EAP WPA-PSK SAE OWE".to_string(),
+            ),
+            (
+                "freq",
+                "Mode[G] Channels:\n 1 = 2412 MHz\n 13 = 2472 MHz (NO_IR)\n\
+                 Mode[A] Channels:\n 36 = 5180 MHz\n 52 = 5260 MHz (NO_IR) (DFS)\n 37 = 6135 MHz\n"
+                    .to_string(),
+            ),
+            ("sae", "H2E PK".to_string()),
+            ("dpp", "DPP=3".to_string()),
+            ("tdls", "UNSUPPORTED".to_string()),
+            ("driver_flags", "0000000000004000:\nAP\nSAE\n".to_string()),
+        ]);
+        let capabilities = Capabilities::from_replies(&replies);
+        assert!(capabilities.supports_sae_h2e());
+        assert!(capabilities.supports_owe());
+        assert!(!capabilities.tdls);
+        assert_eq!(capabilities.dpp, Some(3));
+        assert!(capabilities.has_driver_flag("AP"));
+        assert_eq!(capabilities.supported_channels(Band::Band2GHz), [1, 13]);
+        assert_eq!(capabilities.supported_channels(Band::Band5GHz), [36, 52]);
+        assert_eq!(capabilities.supported_channels(Band::Band6GHz), [37]);
+        assert!(!capabilities.supports_band(Band::Band60GHz));
+        let dfs = &capabilities.channels[3];
+        assert_eq!(dfs.mode, "A");
+        assert!(dfs.no_ir && dfs.dfs);
+        // fields the daemon didn't answer are left empty
+        assert!(capabilities.eap.is_empty());
+        assert!(!capabilities.fips);
+    }
+}
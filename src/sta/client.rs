@@ -13,6 +13,23 @@ pub enum SelectResult {
     WrongPsk,
     NotFound,
     AlreadyConnected,
+    /// A later select took this one's place, under [`SelectPolicy::Supersede`]
+    Superseded,
+}
+
+/// What the runner does with a [`RequestClient::select_network`] made while
+/// another select is still pending, set with [`WifiSetup::set_select_policy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SelectPolicy {
+    /// Fail the new select with
+    /// [`ClientError::PendingSelect`](crate::error::ClientError::PendingSelect)
+    #[default]
+    Reject,
+    /// Start the new select once those ahead of it have resolved
+    Queue,
+    /// Resolve the pending select with [`SelectResult::Superseded`] and start
+    /// the new one straight away
+    Supersede,
 }
 
 use std::fmt;
@@ -24,6 +41,7 @@ impl fmt::Display for SelectResult {
             SelectResult::WrongPsk => "wrong_psk",
             SelectResult::NotFound => "network_not_found",
             SelectResult::AlreadyConnected => "already_connected",
+            SelectResult::Superseded => "superseded",
         };
         write!(f, "{s}")
     }
@@ -39,6 +57,7 @@ pub(crate) enum RemoveNetwork {
 pub(crate) enum Request {
     Custom(String, oneshot::Sender<Result<String>>),
     Status(oneshot::Sender<Result<Status>>),
+    Capabilities(oneshot::Sender<Result<Capabilities>>),
     Networks(oneshot::Sender<Result<Vec<NetworkResult>>>),
     Scan(oneshot::Sender<Result<ScanResults>>),
     AddNetwork(oneshot::Sender<Result<usize>>),
@@ -47,9 +66,70 @@ pub(crate) enum Request {
     ReloadConfig(oneshot::Sender<Result>),
     RemoveNetwork(RemoveNetwork, oneshot::Sender<Result>),
     SelectNetwork(usize, oneshot::Sender<Result<SelectResult>>),
+    Autoscan(Option<Autoscan>, oneshot::Sender<Result>),
+    AutoConnect(bool, oneshot::Sender<Result>),
+    AddCred(Box<Credential>, oneshot::Sender<Result<usize>>),
+    SetCred(usize, Box<Credential>, oneshot::Sender<Result>),
+    ListCreds(oneshot::Sender<Result<Vec<CredentialEntry>>>),
+    RemoveCred(RemoveNetwork, oneshot::Sender<Result>),
+    Interworking(Interworking, oneshot::Sender<Result>),
+    MeshInterfaceAdd(Option<String>, oneshot::Sender<Result<String>>),
+    Mesh(Mesh, oneshot::Sender<Result>),
+    Set(Set, oneshot::Sender<Result>),
+    GetCountry(oneshot::Sender<Result<Option<CountryCode>>>),
+    BssidIgnore(BssidIgnore, oneshot::Sender<Result>),
+    BssidIgnoreList(oneshot::Sender<Result<Vec<Bssid>>>),
+    NetworkBssids(usize, BssidList, Vec<Bssid>, oneshot::Sender<Result>),
+    CancelSelect(oneshot::Sender<Result<bool>>),
+    /// A request from a client made with [`RequestClient::with_timeout`]
+    WithDeadline(Instant, Box<Request>),
     Shutdown,
 }
 
+impl Request {
+    /// Whether the client stopped waiting for the response, e.g. because its
+    /// future was dropped, so the request needn't be sent at all.
+    pub(crate) fn is_abandoned(&self) -> bool {
+        match self {
+            Request::Custom(_, response) => response.is_closed(),
+            Request::Status(response) => response.is_closed(),
+            Request::Capabilities(response) => response.is_closed(),
+            Request::Networks(response) => response.is_closed(),
+            Request::Scan(response) => response.is_closed(),
+            Request::AddNetwork(response) => response.is_closed(),
+            Request::SetNetwork(_, _, response) => response.is_closed(),
+            Request::SaveConfig(response) => response.is_closed(),
+            Request::ReloadConfig(response) => response.is_closed(),
+            Request::RemoveNetwork(_, response) => response.is_closed(),
+            Request::SelectNetwork(_, response) => response.is_closed(),
+            Request::Autoscan(_, response) => response.is_closed(),
+            Request::AutoConnect(_, response) => response.is_closed(),
+            Request::AddCred(_, response) => response.is_closed(),
+            Request::SetCred(_, _, response) => response.is_closed(),
+            Request::ListCreds(response) => response.is_closed(),
+            Request::RemoveCred(_, response) => response.is_closed(),
+            Request::Interworking(_, response) => response.is_closed(),
+            Request::MeshInterfaceAdd(_, response) => response.is_closed(),
+            Request::Mesh(_, response) => response.is_closed(),
+            Request::Set(_, response) => response.is_closed(),
+            Request::GetCountry(response) => response.is_closed(),
+            Request::BssidIgnore(_, response) => response.is_closed(),
+            Request::BssidIgnoreList(response) => response.is_closed(),
+            Request::NetworkBssids(_, _, _, response) => response.is_closed(),
+            Request::CancelSelect(response) => response.is_closed(),
+            Request::WithDeadline(_, request) => request.is_abandoned(),
+            Request::Shutdown => false,
+        }
+    }
+
+    pub(crate) fn split_deadline(self) -> (Option<Instant>, Request) {
+        match self {
+            Request::WithDeadline(deadline, request) => (Some(deadline), *request),
+            request => (None, request),
+        }
+    }
+}
+
 impl ShutdownSignal for Request {
     fn is_shutdown(&self) -> bool {
         matches!(self, Request::Shutdown)
@@ -62,17 +142,131 @@ pub(crate) enum SetNetwork {
     Bssid(Bssid),
     Psk(Psk),
     KeyMgmt(KeyMgmt),
+    Bgscan(Bgscan),
+    Mode(NetworkMode),
+    Frequency(u32),
+    FreqList(Vec<u32>),
+    Priority(i32),
+    Disabled(bool),
+    ScanSsid(bool),
+    IdStr(String),
+    MacAddr(MacRandomization),
+}
+
+impl SetNetwork {
+    pub(crate) fn to_command(&self, id: usize) -> String {
+        // Psk and Bssid are validated at construction, so every variant
+        // formats infallibly; Psk's Debug impl redacts the key wherever the
+        // request is logged.
+        let field = match self {
+            SetNetwork::Ssid(ssid) => format!("ssid {}", conf_escape(ssid)),
+            SetNetwork::Bssid(bssid) => format!("bssid {bssid}"),
+            SetNetwork::Psk(psk) => format!("psk {}", psk.to_field()),
+            SetNetwork::KeyMgmt(mgmt) => format!("key_mgmt {mgmt}"),
+            SetNetwork::Bgscan(bgscan) => format!("bgscan {}", conf_escape(&bgscan.to_string())),
+            SetNetwork::Mode(mode) => format!("mode {mode}"),
+            SetNetwork::Frequency(freq) => format!("frequency {freq}"),
+            SetNetwork::FreqList(freqs) => {
+                let freqs: Vec<_> = freqs.iter().map(u32::to_string).collect();
+                format!("freq_list {}", freqs.join(" "))
+            }
+            SetNetwork::Priority(priority) => format!("priority {priority}"),
+            SetNetwork::Disabled(disabled) => format!("disabled {}", u8::from(*disabled)),
+            SetNetwork::ScanSsid(scan_ssid) => format!("scan_ssid {}", u8::from(*scan_ssid)),
+            SetNetwork::IdStr(id_str) => format!("id_str {}", conf_escape(id_str)),
+            SetNetwork::MacAddr(policy) => format!("mac_addr {policy}"),
+        };
+        format!("SET_NETWORK {id} {field}")
+    }
+}
+
+/// A global setting, changed with `SET`.
+#[derive(Debug)]
+pub(crate) enum Set {
+    MacAddr(MacRandomization),
+    PreassocMacAddr(MacRandomization),
+    RandAddrLifetime(Duration),
+    Country(CountryCode),
+}
+
+impl Set {
+    pub(crate) fn to_command(&self) -> Result<String> {
+        let (name, value) = match self {
+            Set::MacAddr(policy) => ("mac_addr", policy.to_string()),
+            Set::PreassocMacAddr(policy) => ("preassoc_mac_addr", policy.to_string()),
+            Set::RandAddrLifetime(lifetime) => {
+                ("rand_addr_lifetime", lifetime.as_secs().to_string())
+            }
+            Set::Country(country) => ("country", country.to_string()),
+        };
+        match self {
+            Set::MacAddr(MacRandomization::Dedicated)
+            | Set::PreassocMacAddr(MacRandomization::Dedicated) => {
+                Err(ClientError::InvalidParameter(name))
+            }
+            _ => Ok(format!("SET {name} {value}")),
+        }
+    }
 }
 
 #[derive(Clone)]
 /// Request client wraps the request events, awaiting oneshot channels when appropriate
 pub struct RequestClient {
-    sender: mpsc::Sender<Request>,
+    sender: RequestSender,
+    /// Deadline for each request, counted from when it's made
+    timeout: Option<Duration>,
+}
+
+#[derive(Clone)]
+enum RequestSender {
+    Station(mpsc::Sender<Request>),
+    /// An interface behind the global control socket; requests are tagged
+    /// with its name
+    Global(mpsc::Sender<GlobalRequest>, Arc<str>),
+}
+
+impl RequestSender {
+    async fn send(&self, request: Request) -> Result {
+        match self {
+            RequestSender::Station(sender) => sender.send(request).await?,
+            RequestSender::Global(sender, ifname) => {
+                sender
+                    .send(GlobalRequest::Interface(ifname.to_string(), request))
+                    .await?
+            }
+        }
+        Ok(())
+    }
 }
 
 impl RequestClient {
     pub(crate) fn new(sender: mpsc::Sender<Request>) -> RequestClient {
-        RequestClient { sender }
+        RequestClient {
+            sender: RequestSender::Station(sender),
+            timeout: None,
+        }
+    }
+
+    pub(crate) fn for_interface(
+        sender: mpsc::Sender<GlobalRequest>,
+        ifname: &str,
+    ) -> RequestClient {
+        RequestClient {
+            sender: RequestSender::Global(sender, ifname.into()),
+            timeout: None,
+        }
+    }
+
+    /// A client whose requests each fail with [`ClientError::Timeout`] if not
+    /// answered within `timeout`. This replaces the runner's command timeout,
+    /// and for [`Self::select_network`] its select timeout, so it can be
+    /// longer as well as shorter. A request still queued when its time runs
+    /// out is skipped by the runner rather than sent late.
+    pub fn with_timeout(&self, timeout: Duration) -> RequestClient {
+        RequestClient {
+            sender: self.sender.clone(),
+            timeout: Some(timeout),
+        }
     }
 
     async fn request<T>(
@@ -80,8 +274,18 @@ impl RequestClient {
         build_request: impl FnOnce(oneshot::Sender<Result<T>>) -> Request,
     ) -> Result<T> {
         let (response, request) = oneshot::channel();
-        self.sender.send(build_request(response)).await?;
-        request.await?
+        let Some(timeout) = self.timeout else {
+            self.sender.send(build_request(response)).await?;
+            return request.await?;
+        };
+        let deadline = Instant::now() + timeout;
+        let request_with_deadline =
+            Request::WithDeadline(deadline, Box::new(build_request(response)));
+        tokio::time::timeout_at(deadline, async {
+            self.sender.send(request_with_deadline).await?;
+            request.await?
+        })
+        .await?
     }
 
     pub async fn send_custom(&self, custom: String) -> Result<String> {
@@ -101,6 +305,12 @@ impl RequestClient {
         self.request(Request::Status).await
     }
 
+    /// Query what wpa_supplicant and the driver support, e.g. before
+    /// offering WPA3 or 6 GHz. See [`Capabilities`].
+    pub async fn get_capabilities(&self) -> Result<Capabilities> {
+        self.request(Request::Capabilities).await
+    }
+
     pub async fn add_network(&self) -> Result<usize> {
         self.request(Request::AddNetwork).await
     }
@@ -130,6 +340,295 @@ impl RequestClient {
         .await
     }
 
+    /// Set the network's background scan module; see [`Bgscan`].
+    pub async fn set_network_bgscan(&self, network_id: usize, bgscan: Bgscan) -> Result {
+        self.request(|response| {
+            Request::SetNetwork(network_id, SetNetwork::Bgscan(bgscan), response)
+        })
+        .await
+    }
+
+    /// Set the network's operating mode; see [`NetworkMode`].
+    pub async fn set_network_mode(&self, network_id: usize, mode: NetworkMode) -> Result {
+        self.request(|response| Request::SetNetwork(network_id, SetNetwork::Mode(mode), response))
+            .await
+    }
+
+    /// Set the frequency in MHz to use for a network we start, e.g. a
+    /// [mesh](NetworkMode::Mesh); ignored for stations.
+    pub async fn set_network_frequency(&self, network_id: usize, freq: u32) -> Result {
+        self.request(|response| {
+            Request::SetNetwork(network_id, SetNetwork::Frequency(freq), response)
+        })
+        .await
+    }
+
+    /// Only scan these frequencies in MHz when looking for the network, or
+    /// all of them again with an empty list.
+    pub async fn set_network_freq_list(&self, network_id: usize, freqs: &[u32]) -> Result {
+        let freqs = freqs.to_vec();
+        self.request(|response| {
+            Request::SetNetwork(network_id, SetNetwork::FreqList(freqs), response)
+        })
+        .await
+    }
+
+    /// Set the network's priority: among the networks in range,
+    /// wpa_supplicant connects to the one with the highest. Networks
+    /// default to 0.
+    pub async fn set_network_priority(&self, network_id: usize, priority: i32) -> Result {
+        self.request(|response| {
+            Request::SetNetwork(network_id, SetNetwork::Priority(priority), response)
+        })
+        .await
+    }
+
+    /// Disable the network, so wpa_supplicant won't connect to it until it's
+    /// enabled again or picked with [`Self::select_network`], or enable it.
+    pub async fn set_network_disabled(&self, network_id: usize, disabled: bool) -> Result {
+        self.request(|response| {
+            Request::SetNetwork(network_id, SetNetwork::Disabled(disabled), response)
+        })
+        .await
+    }
+
+    /// Probe for the network's SSID by name when scanning, which is needed
+    /// to find a hidden network.
+    pub async fn set_network_scan_ssid(&self, network_id: usize, scan_ssid: bool) -> Result {
+        self.request(|response| {
+            Request::SetNetwork(network_id, SetNetwork::ScanSsid(scan_ssid), response)
+        })
+        .await
+    }
+
+    /// Tag the network with a string of our own, passed to action scripts
+    /// as `WPA_ID_STR` and found under `id_str` in [`Self::get_status`].
+    pub async fn set_network_id_str(&self, network_id: usize, id_str: String) -> Result {
+        self.request(|response| {
+            Request::SetNetwork(network_id, SetNetwork::IdStr(id_str), response)
+        })
+        .await
+    }
+
+    /// Only connect to the network through these access points, or through
+    /// any again with an empty list.
+    pub async fn set_network_bssid_accept(&self, network_id: usize, bssids: &[Bssid]) -> Result {
+        let bssids = bssids.to_vec();
+        self.request(|response| {
+            Request::NetworkBssids(network_id, BssidList::Accept, bssids, response)
+        })
+        .await
+    }
+
+    /// Never connect to the network through these access points; an empty
+    /// list clears it.
+    pub async fn set_network_bssid_ignore(&self, network_id: usize, bssids: &[Bssid]) -> Result {
+        let bssids = bssids.to_vec();
+        self.request(|response| {
+            Request::NetworkBssids(network_id, BssidList::Ignore, bssids, response)
+        })
+        .await
+    }
+
+    /// Set which MAC address to use when connecting to the network,
+    /// overriding the default set with [`Self::set_mac_randomization`].
+    /// Takes effect from the next connection.
+    pub async fn set_network_mac_randomization(
+        &self,
+        network_id: usize,
+        policy: MacRandomization,
+    ) -> Result {
+        self.request(|response| {
+            Request::SetNetwork(network_id, SetNetwork::MacAddr(policy), response)
+        })
+        .await
+    }
+
+    /// Set which MAC address to use for networks without a policy of their
+    /// own. [`MacRandomization::Dedicated`] is rejected with
+    /// [`ClientError::InvalidParameter`] as it's per network only.
+    pub async fn set_mac_randomization(&self, policy: MacRandomization) -> Result {
+        self.request(|response| Request::Set(Set::MacAddr(policy), response))
+            .await
+    }
+
+    /// Set which MAC address to scan with while not connected.
+    /// [`MacRandomization::Dedicated`] is rejected with
+    /// [`ClientError::InvalidParameter`].
+    pub async fn set_preassoc_mac_randomization(&self, policy: MacRandomization) -> Result {
+        self.request(|response| Request::Set(Set::PreassocMacAddr(policy), response))
+            .await
+    }
+
+    /// Set how long a random address is kept before a new one is picked,
+    /// at whole-second granularity; wpa_supplicant's default is a minute.
+    pub async fn set_rand_addr_lifetime(&self, lifetime: Duration) -> Result {
+        self.request(|response| Request::Set(Set::RandAddrLifetime(lifetime), response))
+            .await
+    }
+
+    /// Set the regulatory domain, which decides the channels and transmit
+    /// power allowed. The kernel's new domain is broadcast as
+    /// [`Broadcast::RegdomChanged`]; access points advertising a country may
+    /// change it again.
+    pub async fn set_country(&self, country: CountryCode) -> Result {
+        self.request(|response| Request::Set(Set::Country(country), response))
+            .await
+    }
+
+    /// The country set with [`Self::set_country`] or in the config file, if
+    /// any.
+    pub async fn get_country(&self) -> Result<Option<CountryCode>> {
+        self.request(Request::GetCountry).await
+    }
+
+    /// Turn wpa_supplicant's automatic reconnection (`STA_AUTOCONNECT`) on,
+    /// the default, or off. While it's off wpa_supplicant only connects when
+    /// told to, e.g. by [`Self::select_network`].
+    pub async fn set_auto_connect(&self, enabled: bool) -> Result {
+        self.request(|response| Request::AutoConnect(enabled, response))
+            .await
+    }
+
+    /// Set how wpa_supplicant rescans while disconnected, or go back to its
+    /// default with `None`. Scan results it finds are broadcast as
+    /// [`Broadcast::ScanResults`]. Not saved by [`Self::save_config`]; use
+    /// the `autoscan` global in the config file for that.
+    pub async fn set_autoscan(&self, autoscan: Option<Autoscan>) -> Result {
+        self.request(|response| Request::Autoscan(autoscan, response))
+            .await
+    }
+
+    /// Skip an access point when choosing one to connect to, whatever the
+    /// network. wpa_supplicant also adds access points it fails to connect
+    /// to, for a while. Uses `BSSID_IGNORE`, or `BLACKLIST` on daemons older
+    /// than 2.10.
+    pub async fn bssid_ignore_add(&self, bssid: Bssid) -> Result {
+        self.request(|response| Request::BssidIgnore(BssidIgnore::Add(bssid), response))
+            .await
+    }
+
+    /// Stop skipping an access point. wpa_supplicant can only clear the
+    /// whole list, so the other entries are added back.
+    pub async fn bssid_ignore_remove(&self, bssid: Bssid) -> Result {
+        self.request(|response| Request::BssidIgnore(BssidIgnore::Remove(bssid), response))
+            .await
+    }
+
+    pub async fn bssid_ignore_list(&self) -> Result<Vec<Bssid>> {
+        self.request(Request::BssidIgnoreList).await
+    }
+
+    pub async fn bssid_ignore_clear(&self) -> Result {
+        self.request(|response| Request::BssidIgnore(BssidIgnore::Clear, response))
+            .await
+    }
+
+    /// Add a Passpoint credential, returning its id. Nothing is left behind
+    /// if setting any of its fields fails.
+    pub async fn add_credential(&self, credential: Credential) -> Result<usize> {
+        self.request(|response| Request::AddCred(Box::new(credential), response))
+            .await
+    }
+
+    /// Set the fields given in `credential` on an existing credential.
+    pub async fn set_credential(&self, id: usize, credential: Credential) -> Result {
+        self.request(|response| Request::SetCred(id, Box::new(credential), response))
+            .await
+    }
+
+    pub async fn list_credentials(&self) -> Result<Vec<CredentialEntry>> {
+        self.request(Request::ListCreds).await
+    }
+
+    pub async fn remove_credential(&self, id: usize) -> Result {
+        self.request(|response| Request::RemoveCred(RemoveNetwork::Id(id), response))
+            .await
+    }
+
+    pub async fn remove_all_credentials(&self) -> Result {
+        self.request(|response| Request::RemoveCred(RemoveNetwork::All, response))
+            .await
+    }
+
+    /// Look for access points matching the credentials, fetching ANQP
+    /// information as needed. Matches are broadcast as
+    /// [`Broadcast::InterworkingAp`], or [`Broadcast::InterworkingNoMatch`]
+    /// if there are none. With `auto`, wpa_supplicant also connects to the
+    /// best match.
+    pub async fn interworking_select(&self, auto: bool) -> Result {
+        self.request(|response| Request::Interworking(Interworking::Select { auto }, response))
+            .await
+    }
+
+    /// Connect to an access point using a matching credential.
+    pub async fn interworking_connect(&self, bssid: Bssid) -> Result {
+        self.request(|response| Request::Interworking(Interworking::Connect(bssid), response))
+            .await
+    }
+
+    /// Query ANQP elements by info id (e.g. 258 for Venue Name) from an
+    /// access point. Each received element is broadcast as
+    /// [`Broadcast::AnqpReceived`].
+    pub async fn anqp_get(&self, bssid: Bssid, info_ids: &[u16]) -> Result {
+        let query = Interworking::AnqpGet(bssid, info_ids.to_vec());
+        self.request(|response| Request::Interworking(query, response))
+            .await
+    }
+
+    /// Query Hotspot 2.0 ANQP elements by subtype (e.g. 3 for Operator
+    /// Friendly Name). Each received element is broadcast as
+    /// [`Broadcast::Hs20AnqpReceived`].
+    pub async fn hs20_anqp_get(&self, bssid: Bssid, subtypes: &[u8]) -> Result {
+        let query = Interworking::Hs20AnqpGet(bssid, subtypes.to_vec());
+        self.request(|response| Request::Interworking(query, response))
+            .await
+    }
+
+    /// Create a virtual interface for a mesh to run on, alongside the
+    /// station, returning its name (`mesh-<ifname>-<n>` unless `ifname` is
+    /// given). Pass the name to [`Self::mesh_group_remove`] when done.
+    ///
+    /// The new interface has its own control socket and networks, so the
+    /// mesh network is added and [`Self::mesh_group_add`] called on a client
+    /// for it, whose events carry the `MESH-*` broadcasts. Under the global
+    /// runner [`GlobalClient::mesh_interface_add`] returns one; otherwise
+    /// run a separate [`WifiSetup`] on the interface's socket in the same
+    /// control directory.
+    pub async fn mesh_interface_add(&self, ifname: Option<&str>) -> Result<String> {
+        self.request(|response| Request::MeshInterfaceAdd(ifname.map(String::from), response))
+            .await
+    }
+
+    /// Start or join the mesh configured in a [`NetworkMode::Mesh`] network,
+    /// e.g. one from [`NetworkProfile::mesh`], on the interface from
+    /// [`Self::mesh_interface_add`]. Broadcasts
+    /// [`Broadcast::MeshGroupStarted`], then [`Broadcast::MeshPeerConnected`]
+    /// for each peer that joins.
+    pub async fn mesh_group_add(&self, network_id: usize) -> Result {
+        self.request(|response| Request::Mesh(Mesh::GroupAdd(network_id), response))
+            .await
+    }
+
+    /// Leave the mesh running on `ifname`.
+    pub async fn mesh_group_remove(&self, ifname: &str) -> Result {
+        self.request(|response| Request::Mesh(Mesh::GroupRemove(ifname.into()), response))
+            .await
+    }
+
+    /// Open a peering with a mesh neighbour, e.g. one wpa_supplicant
+    /// doesn't peer with automatically because `no_auto_peer` is set.
+    pub async fn mesh_peer_add(&self, peer: MacAddr) -> Result {
+        self.request(|response| Request::Mesh(Mesh::PeerAdd(peer), response))
+            .await
+    }
+
+    /// Close the peering with a mesh neighbour.
+    pub async fn mesh_peer_remove(&self, peer: MacAddr) -> Result {
+        self.request(|response| Request::Mesh(Mesh::PeerRemove(peer), response))
+            .await
+    }
+
     pub async fn save_config(&self) -> Result {
         self.request(Request::SaveConfig).await
     }
@@ -148,14 +647,31 @@ impl RequestClient {
             .await
     }
 
+    /// Connect to the network, resolving once it connects or isn't found.
+    /// A network with `scan_ssid` set, e.g. from [`NetworkProfile::hidden`],
+    /// is only reported [`SelectResult::NotFound`] after a scan probing for
+    /// its SSID also fails to find it.
     pub async fn select_network(&self, network_id: usize) -> Result<SelectResult> {
         self.request(|response| Request::SelectNetwork(network_id, response))
             .await
     }
 
+    /// Resolve the pending [`Self::select_network`] request, if any, with
+    /// [`ClientError::Cancelled`] so another network can be selected at once;
+    /// under [`SelectPolicy::Queue`] the next queued select starts. Returns
+    /// whether there was one. wpa_supplicant isn't told and carries on with
+    /// the selected network.
+    pub async fn cancel_select(&self) -> Result<bool> {
+        self.request(Request::CancelSelect).await
+    }
+
+    /// Stop the runner. For a client returned by [`GlobalClient::interface`]
+    /// this only drops the global runner's state for the interface, failing
+    /// its pending scan and select requests with
+    /// [`ClientError::RunnerNotRunning`]; stop the global runner with
+    /// [`GlobalClient::shutdown`].
     pub async fn shutdown(&self) -> Result {
-        self.sender.send(Request::Shutdown).await?;
-        Ok(())
+        self.sender.send(Request::Shutdown).await
     }
 }
 
@@ -167,9 +683,135 @@ pub enum Broadcast {
     NetworkNotFound,
     WrongPsk,
     Ready,
+    /// A scan completed, whoever started it: a [`RequestClient::get_scan`],
+    /// background scanning or autoscan
+    ScanResults(ScanResults),
+    InterworkingAp(InterworkingAp),
+    /// Interworking selection found no access point matching a credential
+    InterworkingNoMatch,
+    AnqpReceived(AnqpInfo),
+    Hs20AnqpReceived(AnqpInfo),
+    MeshGroupStarted(MeshGroup),
+    /// We left the mesh on this interface
+    MeshGroupRemoved(String),
+    /// A mesh peer with this MAC address has joined
+    MeshPeerConnected(MacAddr),
+    MeshPeerDisconnected(MacAddr),
+    RegdomChanged(RegdomChange),
+    /// The keepalive found the event socket no longer answering and
+    /// re-attached it; events in between may have been missed.
+    Reattached,
     Unknown(String),
+    /// Only yielded by [`EventStream`]: this many broadcasts were missed
+    /// because the stream fell behind, so any state tracked from them should
+    /// be refreshed, e.g. with [`RequestClient::get_status`].
+    Lagged(u64),
+}
+
+/// The variants of [`Broadcast`] without their data, for
+/// [`EventStream::kinds`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BroadcastKind {
+    Connected,
+    Disconnected,
+    NetworkNotFound,
+    WrongPsk,
+    Ready,
+    ScanResults,
+    InterworkingAp,
+    InterworkingNoMatch,
+    AnqpReceived,
+    Hs20AnqpReceived,
+    MeshGroupStarted,
+    MeshGroupRemoved,
+    MeshPeerConnected,
+    MeshPeerDisconnected,
+    RegdomChanged,
+    Reattached,
+    Unknown,
+    Lagged,
+}
+
+impl Broadcast {
+    pub fn kind(&self) -> BroadcastKind {
+        match self {
+            Broadcast::Connected => BroadcastKind::Connected,
+            Broadcast::Disconnected => BroadcastKind::Disconnected,
+            Broadcast::NetworkNotFound => BroadcastKind::NetworkNotFound,
+            Broadcast::WrongPsk => BroadcastKind::WrongPsk,
+            Broadcast::Ready => BroadcastKind::Ready,
+            Broadcast::ScanResults(_) => BroadcastKind::ScanResults,
+            Broadcast::InterworkingAp(_) => BroadcastKind::InterworkingAp,
+            Broadcast::InterworkingNoMatch => BroadcastKind::InterworkingNoMatch,
+            Broadcast::AnqpReceived(_) => BroadcastKind::AnqpReceived,
+            Broadcast::Hs20AnqpReceived(_) => BroadcastKind::Hs20AnqpReceived,
+            Broadcast::MeshGroupStarted(_) => BroadcastKind::MeshGroupStarted,
+            Broadcast::MeshGroupRemoved(_) => BroadcastKind::MeshGroupRemoved,
+            Broadcast::MeshPeerConnected(_) => BroadcastKind::MeshPeerConnected,
+            Broadcast::MeshPeerDisconnected(_) => BroadcastKind::MeshPeerDisconnected,
+            Broadcast::RegdomChanged(_) => BroadcastKind::RegdomChanged,
+            Broadcast::Reattached => BroadcastKind::Reattached,
+            Broadcast::Unknown(_) => BroadcastKind::Unknown,
+            Broadcast::Lagged(_) => BroadcastKind::Lagged,
+        }
+    }
 }
 
 /// Channel for broadcasting events. Subscribing to this channel is equivalent to
 /// "wpa_ctrl_attach". Can be temporarily silenced using broadcast::Receiver's unsubscribe
 pub type BroadcastReceiver = broadcast::Receiver<Broadcast>;
+
+/// Broadcasts as a [`Stream`](tokio_stream::Stream), from
+/// [`WifiSetup::event_stream`]. Unlike a bare [`BroadcastReceiver`], falling
+/// behind isn't silent: it's reported as [`Broadcast::Lagged`], which passes
+/// every filter.
+///
+/// ```no_run
+/// use futures::StreamExt;
+/// use wifi_ctrl::sta::{BroadcastKind, WifiSetup};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let setup = WifiSetup::new();
+///     let mut events = setup
+///         .event_stream()
+///         .kinds(&[BroadcastKind::Connected, BroadcastKind::Disconnected]);
+///     while let Some(event) = events.next().await {
+///         println!("{event:?}");
+///     }
+/// }
+/// ```
+pub struct EventStream {
+    inner: FilteredStream<Broadcast>,
+}
+
+impl EventStream {
+    pub(crate) fn new(receiver: BroadcastReceiver) -> Self {
+        Self {
+            inner: FilteredStream::new(receiver, Broadcast::Lagged),
+        }
+    }
+
+    /// Only yield broadcasts of the given kinds.
+    pub fn kinds(self, kinds: &[BroadcastKind]) -> Self {
+        let kinds = kinds.to_vec();
+        self.filter(move |broadcast| kinds.contains(&broadcast.kind()))
+    }
+
+    /// Only yield broadcasts for which `filter` returns `true`.
+    pub fn filter(mut self, filter: impl Fn(&Broadcast) -> bool + Send + Sync + 'static) -> Self {
+        self.inner.push_filter(filter);
+        self
+    }
+}
+
+impl tokio_stream::Stream for EventStream {
+    type Item = Broadcast;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Broadcast>> {
+        self.get_mut().inner.poll_next(cx)
+    }
+}
@@ -2,6 +2,8 @@ use super::*;
 
 pub(crate) struct EventSocket {
     socket_handle: SocketHandle<1024>,
+    /// Commands registering the socket for events, replayed to re-attach
+    attach_commands: Vec<String>,
 }
 
 #[derive(Debug)]
@@ -12,52 +14,159 @@ pub(crate) enum Event {
     Disconnected,
     NetworkNotFound,
     WrongPsk,
+    InterworkingAp(InterworkingAp),
+    InterworkingNoMatch,
+    Anqp(AnqpInfo),
+    Hs20Anqp(AnqpInfo),
+    MeshGroupStarted(MeshGroup),
+    MeshGroupRemoved(String),
+    MeshPeerConnected(MacAddr),
+    MeshPeerDisconnected(MacAddr),
+    RegdomChanged(RegdomChange),
     Unknown(String),
 }
 
 impl EventSocket {
-    pub(crate) async fn new<P>(
-        socket: P,
-        request_receiver: &mut mpsc::Receiver<Request>,
+    /// Open the event socket and register it with `ATTACH`, followed by
+    /// `LEVEL <level>` if given, retrying each until wpa_supplicant answers
+    /// `OK`. The same commands are replayed if the keepalive finds the
+    /// socket dropped.
+    pub(crate) async fn new<S>(
+        control_interface: &ControlInterface,
+        request_receiver: &mut mpsc::Receiver<S>,
+        attach_options: &[String],
+        level: Option<u8>,
         command_timeout: Duration,
-    ) -> SocketResult<(Vec<Request>, Self)>
+        attach_retries: usize,
+        attach_retry_delay: Duration,
+    ) -> SocketResult<(Vec<S>, Self)>
     where
-        P: AsRef<std::path::Path> + std::fmt::Debug,
+        S: ShutdownSignal,
     {
-        let (socket_handle, deferred_requests) = SocketHandle::open(
-            socket,
+        let (mut socket_handle, deferred_requests) = SocketHandle::open(
+            control_interface,
             "wpa_ctrl_async.sock",
             request_receiver,
             command_timeout,
         )
         .await?;
         info!("wpa_ctrl attempting attach");
-        socket_handle.socket.send(b"ATTACH").await?;
-        Ok((deferred_requests, Self { socket_handle }))
+        let mut attach_commands = vec![attach_command(attach_options)];
+        if let Some(level) = level {
+            attach_commands.push(format!("LEVEL {level}"));
+        }
+        for command in &attach_commands {
+            retry_command(
+                &mut socket_handle,
+                command.as_bytes(),
+                attach_retries,
+                attach_retry_delay,
+            )
+            .await?;
+        }
+        info!("wpa_ctrl event stream registered");
+        Ok((
+            deferred_requests,
+            Self {
+                socket_handle,
+                attach_commands,
+            },
+        ))
+    }
+
+    /// Returns whether the socket had to be re-attached, see
+    /// [`SocketHandle::check_attached`].
+    pub(crate) async fn keepalive(&mut self) -> SocketResult<bool> {
+        self.socket_handle
+            .check_attached(&self.attach_commands)
+            .await
     }
 
     pub(crate) async fn recv(&mut self) -> SocketResult<Event> {
-        let bytes = self.socket_handle.recv().await?;
-        let data_str = String::from_utf8_lossy(bytes);
+        Ok(self.recv_tagged().await?.1)
+    }
+
+    /// Like [`Self::recv`], but also returns the interface named by the
+    /// `IFNAME=` prefix the global control socket puts on interface events.
+    pub(crate) async fn recv_tagged(&mut self) -> SocketResult<(Option<String>, Event)> {
+        // events that turned up on this socket while waiting for a handshake
+        // reply come first
+        let data_str = match self.socket_handle.take_unsolicited() {
+            Some(data_str) => data_str,
+            None => String::from_utf8_lossy(self.socket_handle.recv().await?).into_owned(),
+        };
         debug!("wpa_ctrl event: {data_str}");
-        Ok(
-            if data_str.trim_end().ends_with("CTRL-EVENT-SCAN-RESULTS") {
-                Event::ScanComplete
-            } else if data_str.contains("CTRL-EVENT-SCAN-FAILED") {
-                Event::ScanFailed
-            } else if data_str.contains("CTRL-EVENT-CONNECTED") {
-                Event::Connected
-            } else if data_str.contains("CTRL-EVENT-DISCONNECTED") {
-                Event::Disconnected
-            } else if data_str.contains("CTRL-EVENT-NETWORK-NOT-FOUND") {
-                Event::NetworkNotFound
-            } else if data_str.contains("CTRL-EVENT-SSID-TEMP-DISABLED")
-                && data_str.contains("reason=WRONG_KEY")
-            {
-                Event::WrongPsk
-            } else {
-                Event::Unknown(data_str.trim_end().into())
-            },
-        )
+        Ok(Self::parse(&data_str))
+    }
+
+    /// Parse an event datagram, which may have come in on the request socket
+    /// (see [`SocketHandle::take_unsolicited`]).
+    pub(crate) fn parse(data_str: &str) -> (Option<String>, Event) {
+        let (ifname, data_str) = match data_str
+            .strip_prefix("IFNAME=")
+            .and_then(|rest| rest.split_once(' '))
+        {
+            Some((ifname, rest)) => (Some(ifname.to_string()), rest),
+            None => (None, data_str),
+        };
+        // the event without its `<level>` prefix
+        let body = match data_str
+            .strip_prefix('<')
+            .and_then(|rest| rest.split_once('>'))
+        {
+            Some((_, body)) => body,
+            None => data_str,
+        };
+        let event = if data_str.trim_end().ends_with("CTRL-EVENT-SCAN-RESULTS") {
+            Event::ScanComplete
+        } else if data_str.contains("CTRL-EVENT-SCAN-FAILED") {
+            Event::ScanFailed
+        } else if data_str.contains("CTRL-EVENT-CONNECTED") {
+            Event::Connected
+        } else if data_str.contains("CTRL-EVENT-DISCONNECTED") {
+            Event::Disconnected
+        } else if data_str.contains("CTRL-EVENT-NETWORK-NOT-FOUND") {
+            Event::NetworkNotFound
+        } else if data_str.contains("CTRL-EVENT-SSID-TEMP-DISABLED")
+            && data_str.contains("reason=WRONG_KEY")
+        {
+            Event::WrongPsk
+        } else if let Some(ap) = body
+            .strip_prefix("INTERWORKING-AP ")
+            .and_then(InterworkingAp::parse)
+        {
+            Event::InterworkingAp(ap)
+        } else if body.starts_with("INTERWORKING-NO-MATCH") {
+            Event::InterworkingNoMatch
+        } else if let Some(info) = body.strip_prefix("RX-ANQP ").and_then(AnqpInfo::parse) {
+            Event::Anqp(info)
+        } else if let Some(info) = body.strip_prefix("RX-HS20-ANQP ").and_then(AnqpInfo::parse) {
+            Event::Hs20Anqp(info)
+        } else if let Some(group) = body
+            .strip_prefix("MESH-GROUP-STARTED ")
+            .and_then(MeshGroup::parse)
+        {
+            Event::MeshGroupStarted(group)
+        } else if let Some(ifname) = body.strip_prefix("MESH-GROUP-REMOVED ") {
+            Event::MeshGroupRemoved(ifname.trim().to_string())
+        } else if let Some(Ok(peer)) = body
+            .strip_prefix("MESH-PEER-CONNECTED ")
+            .map(|peer| peer.trim().parse())
+        {
+            Event::MeshPeerConnected(peer)
+        } else if let Some(Ok(peer)) = body
+            .strip_prefix("MESH-PEER-DISCONNECTED ")
+            .map(|peer| peer.trim().parse())
+        {
+            Event::MeshPeerDisconnected(peer)
+        } else if let Some(change) = body
+            .strip_prefix("CTRL-EVENT-REGDOM-CHANGE ")
+            .and_then(RegdomChange::parse)
+        {
+            Event::RegdomChanged(change)
+        } else {
+            Event::Unknown(data_str.trim_end().into())
+        };
+        (ifname, event)
     }
 }
@@ -0,0 +1,647 @@
+use super::*;
+use crate::sta::{
+    DEFAULT_ATTACH_RETRIES, DEFAULT_ATTACH_RETRY_DELAY, DEFAULT_CHANNEL_SIZE,
+    DEFAULT_COMMAND_TIMEOUT,
+};
+
+use std::collections::HashMap;
+
+const PATH_DEFAULT_GLOBAL: &str = "/var/run/wpa_supplicant-global";
+
+/// Parameters of an `INTERFACE_ADD` request, telling wpa_supplicant to start
+/// managing another interface.
+#[derive(Debug, Clone, Default)]
+pub struct InterfaceAdd {
+    pub ifname: String,
+    /// Configuration file for the interface
+    pub config_file: Option<String>,
+    /// Driver name(s), e.g. `nl80211`
+    pub driver: Option<String>,
+    /// Control interface directory for the interface's own socket
+    pub ctrl_interface: Option<String>,
+    pub driver_param: Option<String>,
+    pub bridge_name: Option<String>,
+}
+
+impl InterfaceAdd {
+    pub fn new(ifname: impl Into<String>) -> Self {
+        Self {
+            ifname: ifname.into(),
+            ..Default::default()
+        }
+    }
+
+    /// `INTERFACE_ADD` takes tab-separated positional fields, so a value
+    /// containing a tab or newline would shift the ones after it.
+    fn to_command(&self) -> Result<String> {
+        let fields = [
+            ("ifname", Some(&self.ifname)),
+            ("config_file", self.config_file.as_ref()),
+            ("driver", self.driver.as_ref()),
+            ("ctrl_interface", self.ctrl_interface.as_ref()),
+            ("driver_param", self.driver_param.as_ref()),
+            ("bridge_name", self.bridge_name.as_ref()),
+        ];
+        let mut values = Vec::with_capacity(fields.len());
+        for (name, value) in fields {
+            let value = value.map(String::as_str).unwrap_or_default();
+            if value.chars().any(char::is_control) {
+                return Err(ClientError::InvalidParameter(name));
+            }
+            values.push(value);
+        }
+        if self.ifname.is_empty() || self.ifname.contains(' ') {
+            return Err(ClientError::InvalidParameter("ifname"));
+        }
+        Ok(format!("INTERFACE_ADD {}", values.join("\t")))
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum GlobalRequest {
+    Interfaces(oneshot::Sender<Result<Vec<String>>>),
+    InterfaceAdd(InterfaceAdd, oneshot::Sender<Result>),
+    InterfaceRemove(String, oneshot::Sender<Result>),
+    /// A station request for one interface, sent with an `IFNAME=` prefix
+    Interface(String, Request),
+    Shutdown,
+}
+
+impl ShutdownSignal for GlobalRequest {
+    fn is_shutdown(&self) -> bool {
+        // a shutdown from an interface's client only ends that interface
+        matches!(self, GlobalRequest::Shutdown)
+    }
+}
+
+fn parse_interfaces(response: &str) -> ParseResult<Vec<String>> {
+    Ok(response
+        .lines()
+        .map(str::trim)
+        .filter(|ifname| !ifname.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+#[derive(Clone)]
+/// Client for the wpa_supplicant global control interface (`wpa_supplicant
+/// -g <socket>`), managing several interfaces through one socket.
+pub struct GlobalClient {
+    sender: mpsc::Sender<GlobalRequest>,
+}
+
+impl GlobalClient {
+    async fn request<T>(
+        &self,
+        build_request: impl FnOnce(oneshot::Sender<Result<T>>) -> GlobalRequest,
+    ) -> Result<T> {
+        let (response, request) = oneshot::channel();
+        self.sender.send(build_request(response)).await?;
+        request.await?
+    }
+
+    /// Names of the interfaces wpa_supplicant is managing.
+    pub async fn interfaces(&self) -> Result<Vec<String>> {
+        self.request(GlobalRequest::Interfaces).await
+    }
+
+    pub async fn interface_add(&self, interface: InterfaceAdd) -> Result {
+        self.request(|response| GlobalRequest::InterfaceAdd(interface, response))
+            .await
+    }
+
+    /// Stop managing an interface. Scan and select requests still pending on
+    /// it fail with [`ClientError::RunnerNotRunning`].
+    pub async fn interface_remove(&self, ifname: &str) -> Result {
+        let ifname = check_ifname(ifname)?;
+        self.request(|response| GlobalRequest::InterfaceRemove(ifname.into(), response))
+            .await
+    }
+
+    /// A [`RequestClient`] for one interface. Its requests share the global
+    /// socket, and its events arrive on the global broadcast channel tagged
+    /// with `ifname`.
+    pub fn interface(&self, ifname: &str) -> Result<RequestClient> {
+        let ifname = check_ifname(ifname)?;
+        Ok(RequestClient::for_interface(self.sender.clone(), ifname))
+    }
+
+    /// Create a mesh interface alongside `parent`, as
+    /// [`RequestClient::mesh_interface_add`], returning a client for it to
+    /// add the mesh network and call
+    /// [`mesh_group_add`](RequestClient::mesh_group_add) on.
+    pub async fn mesh_interface_add(
+        &self,
+        parent: &str,
+        ifname: Option<&str>,
+    ) -> Result<RequestClient> {
+        let ifname = self.interface(parent)?.mesh_interface_add(ifname).await?;
+        self.interface(&ifname)
+    }
+
+    pub async fn shutdown(&self) -> Result {
+        Ok(self.sender.send(GlobalRequest::Shutdown).await?)
+    }
+}
+
+/// A station [`Broadcast`] from the global runner, tagged with the interface it
+/// came from. `ifname` is `None` for events that aren't about one interface,
+/// such as [`Broadcast::Ready`].
+#[derive(Debug, Clone)]
+pub struct InterfaceBroadcast {
+    pub ifname: Option<String>,
+    pub broadcast: Broadcast,
+}
+
+/// Channel for broadcasting events from every interface of the global runner.
+pub type GlobalBroadcastReceiver = broadcast::Receiver<InterfaceBroadcast>;
+
+/// Setup struct for the global control interface process.
+pub struct GlobalSetup {
+    /// Struct for handling runtime process
+    wifi: GlobalStation,
+    /// Client for making requests
+    global_client: GlobalClient,
+}
+
+impl GlobalSetup {
+    pub fn new() -> Self {
+        Self::with_capacities(DEFAULT_CHANNEL_SIZE, DEFAULT_CHANNEL_SIZE)
+    }
+
+    /// Like [`Self::new`] but with explicit request and broadcast channel
+    /// capacities (both default to 32).
+    pub fn with_capacities(request_channel_size: usize, broadcast_channel_size: usize) -> Self {
+        let (self_sender, request_receiver) = mpsc::channel(request_channel_size);
+        let global_client = GlobalClient {
+            sender: self_sender.clone(),
+        };
+        let broadcast_sender = broadcast::Sender::new(broadcast_channel_size);
+
+        Self {
+            wifi: GlobalStation {
+                control_interface: ControlInterface::Unix(PATH_DEFAULT_GLOBAL.into()),
+                request_receiver,
+                broadcast_sender,
+                self_sender,
+                select_timeout: DEFAULT_SELECT_TIMEOUT,
+                select_policy: SelectPolicy::default(),
+                command_timeout: DEFAULT_COMMAND_TIMEOUT,
+                keepalive_interval: None,
+                attach_options: vec![],
+                attach_level: None,
+                attach_retries: DEFAULT_ATTACH_RETRIES,
+                attach_retry_delay: DEFAULT_ATTACH_RETRY_DELAY,
+            },
+            global_client,
+        }
+    }
+
+    pub fn set_socket_path<S: Into<std::path::PathBuf>>(&mut self, path: S) {
+        self.wifi.control_interface = ControlInterface::Unix(path.into());
+    }
+
+    /// Connect through `control_interface` instead of the default Unix socket.
+    pub fn set_control_interface(&mut self, control_interface: ControlInterface) {
+        self.wifi.control_interface = control_interface;
+    }
+
+    pub fn set_select_timeout(&mut self, timeout: Duration) {
+        self.wifi.select_timeout = timeout;
+    }
+
+    /// Choose what happens to a select made while another is pending on the
+    /// same interface; see [`SelectPolicy`]. Defaults to
+    /// [`SelectPolicy::Reject`].
+    pub fn set_select_policy(&mut self, policy: SelectPolicy) {
+        self.wifi.select_policy = policy;
+    }
+
+    /// Set how long to wait for a reply to a control command/request before
+    /// giving up with [`ClientError::Timeout`].
+    pub fn set_command_timeout(&mut self, timeout: Duration) {
+        self.wifi.command_timeout = timeout;
+    }
+
+    /// Options to pass to `ATTACH`, such as `probe_rx_events=1`.
+    pub fn add_attach_options(&mut self, options: &[&str]) {
+        for o in options {
+            self.wifi.attach_options.push(o.to_string());
+        }
+    }
+
+    /// Only deliver events at or above `level` (wpa_supplicant's debug
+    /// levels: 2 for `MSG_DEBUG`, 3 for `MSG_INFO`, the default, 4 for
+    /// `MSG_WARNING`), set with `LEVEL <level>` after attaching.
+    pub fn set_attach_level(&mut self, level: u8) {
+        self.wifi.attach_level = Some(level);
+    }
+
+    /// Set how many times to retry each wpa_supplicant attach handshake
+    /// command before giving up with
+    /// [`SocketError::AttachFailed`](crate::error::SocketError::AttachFailed).
+    pub fn set_attach_retries(&mut self, retries: usize) {
+        self.wifi.attach_retries = retries;
+    }
+
+    /// Set how long to wait between attach handshake attempts.
+    pub fn set_attach_retry_delay(&mut self, delay: Duration) {
+        self.wifi.attach_retry_delay = delay;
+    }
+
+    /// Periodically `PING` the event socket and `DETACH` and re-attach it if
+    /// the daemon stops answering, broadcasting [`Broadcast::Reattached`]
+    /// with no `ifname`. As with [`WifiSetup::set_keepalive_interval`], a
+    /// `PONG` doesn't prove the socket is still attached. Off by default.
+    pub fn set_keepalive_interval(&mut self, interval: Duration) {
+        self.wifi.keepalive_interval = Some(interval);
+    }
+
+    pub fn get_broadcast_receiver(&self) -> GlobalBroadcastReceiver {
+        self.wifi.broadcast_sender.subscribe()
+    }
+
+    pub fn get_global_client(&self) -> GlobalClient {
+        self.global_client.clone()
+    }
+
+    pub fn complete(self) -> GlobalStation {
+        self.wifi
+    }
+}
+
+impl Default for GlobalSetup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Instance that runs the wpa_supplicant global control interface process
+pub struct GlobalStation {
+    /// Path to the global socket
+    control_interface: ControlInterface,
+    /// Channel for receiving requests
+    request_receiver: mpsc::Receiver<GlobalRequest>,
+    /// Channel for broadcasting alerts
+    broadcast_sender: broadcast::Sender<InterfaceBroadcast>,
+    /// Channel for sending requests to itself
+    self_sender: mpsc::Sender<GlobalRequest>,
+    /// Timeout duration in case no valid select response is received
+    select_timeout: Duration,
+    /// What to do with a select made while another is pending
+    select_policy: SelectPolicy,
+    /// How long to wait for a reply to a control command/request
+    command_timeout: Duration,
+    /// How often to check the event socket is still attached, if at all
+    keepalive_interval: Option<Duration>,
+    /// Options to pass to the wpa_supplicant attach command
+    attach_options: Vec<String>,
+    /// Monitor level to set with `LEVEL` after attaching
+    attach_level: Option<u8>,
+    /// How many times to retry each attach handshake command before giving up
+    attach_retries: usize,
+    /// How long to wait between attach handshake attempts
+    attach_retry_delay: Duration,
+}
+
+impl GlobalStation {
+    pub async fn run(&mut self) -> SocketResult {
+        info!("Starting Wifi global control process");
+        let (socket_handle, mut deferred_requests) = SocketHandle::open(
+            &self.control_interface,
+            "mapper_wpa_global_sync.sock",
+            &mut self.request_receiver,
+            self.command_timeout,
+        )
+        .await?;
+        // Attached to the global socket, wpa_supplicant forwards the events of
+        // every interface with an IFNAME= prefix
+        let (next_deferred_requests, event_socket) = EventSocket::new(
+            &self.control_interface,
+            &mut self.request_receiver,
+            &self.attach_options,
+            self.attach_level,
+            self.command_timeout,
+            self.attach_retries,
+            self.attach_retry_delay,
+        )
+        .await?;
+        deferred_requests.extend(next_deferred_requests);
+        for request in deferred_requests {
+            self.self_sender
+                .send(request)
+                .await
+                .expect("self_sender should never close as same struct owns both ends");
+        }
+        self.broadcast(None, Broadcast::Ready);
+        self.run_internal(event_socket, socket_handle).await
+    }
+
+    fn broadcast(&self, ifname: Option<&str>, broadcast: Broadcast) {
+        let event = InterfaceBroadcast {
+            ifname: ifname.map(str::to_string),
+            broadcast,
+        };
+        if self.broadcast_sender.send(event).is_err() {
+            debug!("broadcast listener closed")
+        }
+    }
+
+    async fn run_internal(
+        &mut self,
+        mut event_socket: EventSocket,
+        mut socket_handle: SocketHandle<10240>,
+    ) -> SocketResult {
+        let mut interfaces: HashMap<String, Interface> = HashMap::new();
+        let mut keepalive = Keepalive::new(self.keepalive_interval);
+        loop {
+            enum EventOrRequest {
+                Event(Option<String>, Event),
+                Request(Option<GlobalRequest>),
+                SelectTimeout(String),
+                Keepalive,
+            }
+
+            // events that arrived on the request socket in place of a reply
+            let event_or_request = if let Some(msg) = socket_handle.take_unsolicited() {
+                let (ifname, event) = EventSocket::parse(&msg);
+                EventOrRequest::Event(ifname, event)
+            } else {
+                tokio::select!(
+                    event = event_socket.recv_tagged() => {
+                        let (ifname, event) = event?;
+                        EventOrRequest::Event(ifname, event)
+                    },
+                    request = self.request_receiver.recv() => EventOrRequest::Request(request),
+                    ifname = next_select_timeout(&mut interfaces) => EventOrRequest::SelectTimeout(ifname),
+                    _ = keepalive.due() => EventOrRequest::Keepalive,
+                )
+            };
+
+            match event_or_request {
+                EventOrRequest::Event(Some(ifname), event) => {
+                    debug!("Unsolicited event on {ifname}: {event:?}");
+                    let interface = interfaces
+                        .entry(ifname.clone())
+                        .or_insert_with(|| Interface::new(self.select_timeout, self.select_policy));
+                    socket_handle.set_interface(Some(&ifname));
+                    let handled = interface
+                        .handle_event(&mut socket_handle, event, |event| {
+                            self.broadcast(Some(&ifname), event)
+                        })
+                        .await;
+                    socket_handle.set_interface(None);
+                    handled?
+                }
+                EventOrRequest::Event(None, Event::Unknown(msg)) => {
+                    self.broadcast(None, Broadcast::Unknown(msg))
+                }
+                EventOrRequest::Event(None, event) => {
+                    debug!("Ignoring event without an interface: {event:?}")
+                }
+                EventOrRequest::Request(request) => match request {
+                    Some(request) if request.is_shutdown() => return Ok(()),
+                    Some(request) => {
+                        self.handle_request(&mut socket_handle, &mut interfaces, request)
+                            .await?
+                    }
+                    None => return Err(error::SocketError::ClientChannelClosed),
+                },
+                EventOrRequest::SelectTimeout(ifname) => {
+                    if let Some(interface) = interfaces.get_mut(&ifname) {
+                        socket_handle.set_interface(Some(&ifname));
+                        let expired = interface.expire_select(&mut socket_handle).await;
+                        socket_handle.set_interface(None);
+                        expired?
+                    }
+                }
+                EventOrRequest::Keepalive => {
+                    if event_socket.keepalive().await? {
+                        self.broadcast(None, Broadcast::Reattached);
+                    }
+                }
+            }
+        }
+    }
+
+    async fn handle_request<const N: usize>(
+        &self,
+        socket_handle: &mut SocketHandle<N>,
+        interfaces: &mut HashMap<String, Interface>,
+        request: GlobalRequest,
+    ) -> SocketResult {
+        debug!("Handling global request: {request:?}");
+        match request {
+            GlobalRequest::Interfaces(response) => {
+                let _ = response.send(
+                    socket_handle
+                        .request("INTERFACES", parse_interfaces)
+                        .await?,
+                );
+            }
+            GlobalRequest::InterfaceAdd(interface, response) => match interface.to_command() {
+                Ok(cmd) => {
+                    let _ = response.send(socket_handle.command(cmd.as_bytes()).await?);
+                }
+                Err(e) => {
+                    let _ = response.send(Err(e));
+                }
+            },
+            GlobalRequest::InterfaceRemove(ifname, response) => {
+                let cmd = format!("INTERFACE_REMOVE {ifname}");
+                let result = socket_handle.command(cmd.as_bytes()).await?;
+                if result.is_ok() {
+                    interfaces.remove(&ifname);
+                }
+                let _ = response.send(result);
+            }
+            GlobalRequest::Interface(ifname, Request::Shutdown) => {
+                debug!("Dropping the state of {ifname}");
+                interfaces.remove(&ifname);
+            }
+            GlobalRequest::Interface(ifname, request) => {
+                let interface = interfaces
+                    .entry(ifname.clone())
+                    .or_insert_with(|| Interface::new(self.select_timeout, self.select_policy));
+                socket_handle.set_interface(Some(&ifname));
+                let handled = interface.handle_request(socket_handle, request).await;
+                socket_handle.set_interface(None);
+                handled?
+            }
+            GlobalRequest::Shutdown => (), //shutdown is handled at the scope above
+        }
+        Ok(())
+    }
+}
+
+/// Resolves with the name of the interface whose pending select request times
+/// out first.
+async fn next_select_timeout(interfaces: &mut HashMap<String, Interface>) -> String {
+    let next = interfaces
+        .iter_mut()
+        .filter_map(|(ifname, interface)| {
+            let deadline = interface.select_request.as_ref()?.timeout.deadline();
+            Some((deadline, ifname, interface))
+        })
+        .min_by_key(|(deadline, _, _)| *deadline);
+    match next {
+        Some((_, ifname, interface)) => {
+            interface.select_timeout().await;
+            ifname.clone()
+        }
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::socket_handle::tests::FakeDaemon;
+
+    #[test]
+    fn interface_add_fields_are_tab_separated() {
+        let mut add = InterfaceAdd::new("wlan1");
+        add.config_file = Some("/etc/wpa_supplicant/wlan1.conf".into());
+        add.driver = Some("nl80211".into());
+        assert_eq!(
+            add.to_command().unwrap(),
+            "INTERFACE_ADD wlan1\t/etc/wpa_supplicant/wlan1.conf\tnl80211\t\t\t"
+        );
+    }
+
+    #[test]
+    fn interface_add_rejects_field_separators() {
+        let mut add = InterfaceAdd::new("wlan1");
+        add.driver_param = Some("a\tb".into());
+        assert!(matches!(
+            add.to_command(),
+            Err(ClientError::InvalidParameter("driver_param"))
+        ));
+        assert!(matches!(
+            InterfaceAdd::new("wlan 1").to_command(),
+            Err(ClientError::InvalidParameter("ifname"))
+        ));
+    }
+
+    #[test]
+    fn interfaces_are_listed_one_per_line() {
+        assert_eq!(
+            parse_interfaces("wlan0\nwlan1\n").unwrap(),
+            ["wlan0", "wlan1"]
+        );
+        assert!(parse_interfaces("").unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn tagged_events_reach_their_interface() {
+        let mut daemon = FakeDaemon::start(|command| match command {
+            "INTERFACES" => Some("wlan0\nwlan1\n".into()),
+            _ => Some("OK\n".into()),
+        });
+        let mut setup = GlobalSetup::new();
+        setup.set_socket_path(&daemon.path);
+        let mut broadcasts = setup.get_broadcast_receiver();
+        let global_client = setup.get_global_client();
+        let mut runner = setup.complete();
+        let runner = tokio::spawn(async move { runner.run().await });
+        assert!(matches!(
+            broadcasts.recv().await.unwrap(),
+            InterfaceBroadcast {
+                ifname: None,
+                broadcast: Broadcast::Ready
+            }
+        ));
+
+        daemon
+            .send_event("IFNAME=wlan0 <3>CTRL-EVENT-CONNECTED - Connection to 02:00:00:00:01:00")
+            .await;
+        daemon
+            .send_event("IFNAME=wlan1 <3>CTRL-EVENT-DISCONNECTED bssid=02:00:00:00:02:00")
+            .await;
+        let event = broadcasts.recv().await.unwrap();
+        assert_eq!(event.ifname.as_deref(), Some("wlan0"));
+        assert!(matches!(event.broadcast, Broadcast::Connected));
+        let event = broadcasts.recv().await.unwrap();
+        assert_eq!(event.ifname.as_deref(), Some("wlan1"));
+        assert!(matches!(event.broadcast, Broadcast::Disconnected));
+
+        // shutting down one interface's client leaves the runner serving the
+        // others
+        let wlan1 = global_client.interface("wlan1").unwrap();
+        wlan1.shutdown().await.unwrap();
+        assert_eq!(
+            global_client.interfaces().await.unwrap(),
+            ["wlan0", "wlan1"]
+        );
+        assert_eq!(daemon.next_command().await, "INTERFACES");
+        global_client
+            .interface("wlan0")
+            .unwrap()
+            .set_auto_connect(true)
+            .await
+            .unwrap();
+        assert_eq!(
+            daemon.next_command().await,
+            "IFNAME=wlan0 STA_AUTOCONNECT 1"
+        );
+
+        assert!(matches!(
+            global_client.interface("wlan0 all"),
+            Err(ClientError::InvalidParameter("ifname"))
+        ));
+        assert!(matches!(
+            global_client.interface_remove("").await,
+            Err(ClientError::InvalidParameter("ifname"))
+        ));
+        global_client.shutdown().await.unwrap();
+        assert!(runner.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn mesh_is_driven_through_its_own_interface() {
+        let mut daemon = FakeDaemon::start(|command| match command {
+            "IFNAME=wlan0 MESH_INTERFACE_ADD" => Some("mesh-wlan0-0\n".into()),
+            "IFNAME=mesh-wlan0-0 ADD_NETWORK" => Some("0\n".into()),
+            _ => Some("OK\n".into()),
+        });
+        let mut setup = GlobalSetup::new();
+        setup.set_socket_path(&daemon.path);
+        let mut broadcasts = setup.get_broadcast_receiver();
+        let global_client = setup.get_global_client();
+        let mut runner = setup.complete();
+        let runner = tokio::spawn(async move { runner.run().await });
+        broadcasts.recv().await.unwrap();
+
+        let mesh = global_client
+            .mesh_interface_add("wlan0", None)
+            .await
+            .unwrap();
+        assert_eq!(
+            daemon.next_command().await,
+            "IFNAME=wlan0 MESH_INTERFACE_ADD"
+        );
+        let network_id = mesh.add_network().await.unwrap();
+        assert_eq!(
+            daemon.next_command().await,
+            "IFNAME=mesh-wlan0-0 ADD_NETWORK"
+        );
+        mesh.mesh_group_add(network_id).await.unwrap();
+        assert_eq!(
+            daemon.next_command().await,
+            "IFNAME=mesh-wlan0-0 MESH_GROUP_ADD 0"
+        );
+
+        daemon
+            .send_event("IFNAME=mesh-wlan0-0 <3>MESH-GROUP-STARTED ssid=\"backhaul\" id=0")
+            .await;
+        let event = broadcasts.recv().await.unwrap();
+        assert_eq!(event.ifname.as_deref(), Some("mesh-wlan0-0"));
+        assert!(matches!(
+            event.broadcast,
+            Broadcast::MeshGroupStarted(group) if group.ssid == "backhaul"
+        ));
+
+        global_client.shutdown().await.unwrap();
+        assert!(runner.await.unwrap().is_ok());
+    }
+}
@@ -0,0 +1,85 @@
+use super::*;
+use crate::config::unprintf;
+use crate::error::ParseError;
+
+#[derive(Debug)]
+pub(crate) enum Mesh {
+    GroupAdd(usize),
+    GroupRemove(String),
+    PeerAdd(MacAddr),
+    PeerRemove(MacAddr),
+}
+
+impl Mesh {
+    pub(crate) fn to_command(&self) -> Result<String> {
+        Ok(match self {
+            Mesh::GroupAdd(network_id) => format!("MESH_GROUP_ADD {network_id}"),
+            Mesh::GroupRemove(ifname) => format!("MESH_GROUP_REMOVE {}", check_ifname(ifname)?),
+            Mesh::PeerAdd(peer) => format!("MESH_PEER_ADD {peer}"),
+            Mesh::PeerRemove(peer) => format!("MESH_PEER_REMOVE {peer}"),
+        })
+    }
+}
+
+/// The reply to `MESH_INTERFACE_ADD`: the name of the interface created.
+pub(crate) fn parse_mesh_interface(reply: &str) -> ParseResult<String> {
+    // failures such as `FAIL` or `UNKNOWN COMMAND` are classified by the caller
+    if reply.is_empty() || reply.starts_with("FAIL") || reply.contains(' ') {
+        Err(ParseError::NotOK)
+    } else {
+        Ok(reply.to_string())
+    }
+}
+
+/// A `MESH-GROUP-STARTED` event: we've started or joined the mesh of a
+/// [`NetworkMode::Mesh`] network.
+#[derive(Debug, Clone)]
+pub struct MeshGroup {
+    pub ssid: String,
+    pub network_id: usize,
+}
+
+impl MeshGroup {
+    /// Parse the body of a `MESH-GROUP-STARTED ssid="<ssid>" id=<id>` event.
+    /// The SSID may hold spaces, so it's everything up to the last ` id=`.
+    pub(crate) fn parse(body: &str) -> Option<Self> {
+        let (ssid, id) = body.trim_end().rsplit_once(" id=")?;
+        let ssid = ssid.strip_prefix("ssid=\"")?.strip_suffix('"')?;
+        Some(MeshGroup {
+            ssid: unprintf(ssid).unwrap_or_else(|_| ssid.to_string()),
+            network_id: id.parse().ok()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mesh_events_are_parsed() {
+        let (_, event) = EventSocket::parse("<3>MESH-GROUP-STARTED ssid=\"mesh id=1\" id=2");
+        assert!(
+            matches!(event, Event::MeshGroupStarted(group) if group.ssid == "mesh id=1" && group.network_id == 2)
+        );
+        let (_, event) = EventSocket::parse("<3>MESH-PEER-CONNECTED 02:00:00:00:01:00");
+        assert!(
+            matches!(event, Event::MeshPeerConnected(peer) if peer.to_string() == "02:00:00:00:01:00")
+        );
+        let (_, event) = EventSocket::parse("<3>MESH-PEER-DISCONNECTED 02:00:00:00:01:00");
+        assert!(matches!(event, Event::MeshPeerDisconnected(_)));
+        let (_, event) = EventSocket::parse("<3>MESH-GROUP-REMOVED mesh-wlan0-0");
+        assert!(matches!(event, Event::MeshGroupRemoved(ifname) if ifname == "mesh-wlan0-0"));
+    }
+
+    #[test]
+    fn mesh_commands_are_built() {
+        assert_eq!(Mesh::GroupAdd(3).to_command().unwrap(), "MESH_GROUP_ADD 3");
+        assert!(Mesh::GroupRemove("mesh0 all".into()).to_command().is_err());
+        assert_eq!(
+            parse_mesh_interface("mesh-wlan0-0").unwrap(),
+            "mesh-wlan0-0"
+        );
+        assert!(parse_mesh_interface("FAIL").is_err());
+    }
+}
@@ -1,8 +1,10 @@
+use crate::config::conf_escape;
 use crate::error::ClientError;
 
 use super::*;
 
-use tokio::time::Duration;
+use std::collections::VecDeque;
+use tokio::time::{Duration, Instant};
 
 mod types;
 pub use types::*;
@@ -10,18 +12,40 @@ pub use types::*;
 mod client;
 pub use client::*;
 
+mod profile;
+pub use profile::*;
+
+mod capability;
+pub use capability::*;
+
+mod passpoint;
+pub use passpoint::*;
+
+mod mesh;
+pub use mesh::*;
+
+mod bssid_ignore;
+use bssid_ignore::*;
+
+mod global;
+pub use global::*;
+
 mod setup;
 pub use setup::*;
 
 mod event_socket;
 use event_socket::*;
 
+/// Synchronous client for programs without a tokio runtime
+#[cfg(feature = "blocking")]
+pub mod blocking;
+
 const PATH_DEFAULT_SERVER: &str = "/var/run/wpa_supplicant/wlan2";
 
 /// Instance that runs the Wifi process
 pub struct WifiStation {
     /// Path to the socket
-    socket_path: std::path::PathBuf,
+    control_interface: ControlInterface,
     /// Channel for receiving requests
     request_receiver: mpsc::Receiver<Request>,
     /// Channel for broadcasting alerts
@@ -30,15 +54,27 @@ pub struct WifiStation {
     self_sender: mpsc::Sender<Request>,
     /// Timeout duration in case no valid select response is received
     select_timeout: Duration,
+    /// What to do with a select made while another is pending
+    select_policy: SelectPolicy,
     /// How long to wait for a reply to a control command/request
     command_timeout: Duration,
+    /// How often to check the event socket is still attached, if at all
+    keepalive_interval: Option<Duration>,
+    /// Options to pass to the wpa_supplicant attach command
+    attach_options: Vec<String>,
+    /// Monitor level to set with `LEVEL` after attaching
+    attach_level: Option<u8>,
+    /// How many times to retry each attach handshake command before giving up
+    attach_retries: usize,
+    /// How long to wait between attach handshake attempts
+    attach_retry_delay: Duration,
 }
 
 impl WifiStation {
     pub async fn run(&mut self) -> SocketResult {
         info!("Starting Wifi Station process");
         let (socket_handle, mut deferred_requests) = SocketHandle::open(
-            &self.socket_path,
+            &self.control_interface,
             "mapper_wpa_ctrl_sync.sock",
             &mut self.request_receiver,
             self.command_timeout,
@@ -47,9 +83,13 @@ impl WifiStation {
         // We start up a separate socket for receiving the "unexpected" events that
         // gets forwarded to us via the unsolicited_receiver
         let (next_deferred_requests, unsolicited) = EventSocket::new(
-            &self.socket_path,
+            &self.control_interface,
             &mut self.request_receiver,
+            &self.attach_options,
+            self.attach_level,
             self.command_timeout,
+            self.attach_retries,
+            self.attach_retry_delay,
         )
         .await?;
         deferred_requests.extend(next_deferred_requests);
@@ -74,111 +114,259 @@ impl WifiStation {
         mut unsolicited: EventSocket,
         mut socket_handle: SocketHandle<10240>,
     ) -> SocketResult {
-        // We will collect scan requests and batch respond to them when results are ready
-        let mut scan_requests = Vec::new();
-        let mut select_request: Option<SelectRequest> = None;
+        let mut interface = Interface::new(self.select_timeout, self.select_policy);
+        let mut keepalive = Keepalive::new(self.keepalive_interval);
         loop {
             enum EventOrRequest {
                 Event(Event),
                 Request(Option<Request>),
                 SelectTimeout,
+                Keepalive,
             }
 
-            let event_or_request = tokio::select!(
-                unsolicited_msg = unsolicited.recv() => {
-                    EventOrRequest::Event(unsolicited_msg?)
-                },
-                request = self.request_receiver.recv() => {
-                    EventOrRequest::Request(request)
-                },
-                _ = async {
-                    match select_request.as_mut() {
-                        Some(select_request) => select_request.timeout.as_mut().await,
-                        None => std::future::pending().await,
-                    }
-                } => EventOrRequest::SelectTimeout,
-            );
+            // events that arrived on the request socket in place of a reply
+            let event_or_request = if let Some(msg) = socket_handle.take_unsolicited() {
+                EventOrRequest::Event(EventSocket::parse(&msg).1)
+            } else {
+                tokio::select!(
+                    unsolicited_msg = unsolicited.recv() => {
+                        EventOrRequest::Event(unsolicited_msg?)
+                    },
+                    request = self.request_receiver.recv() => {
+                        EventOrRequest::Request(request)
+                    },
+                    _ = interface.select_timeout() => EventOrRequest::SelectTimeout,
+                    _ = keepalive.due() => EventOrRequest::Keepalive,
+                )
+            };
 
             match event_or_request {
                 EventOrRequest::Event(unsolicited_msg) => {
                     debug!("Unsolicited event: {unsolicited_msg:?}");
-                    self.handle_event(
-                        &mut socket_handle,
-                        unsolicited_msg,
-                        &mut scan_requests,
-                        &mut select_request,
-                    )
-                    .await?
+                    interface
+                        .handle_event(&mut socket_handle, unsolicited_msg, |event| {
+                            self.broadcast(event)
+                        })
+                        .await?
                 }
                 EventOrRequest::Request(request) => match request {
                     Some(Request::Shutdown) => return Ok(()),
                     Some(request) => {
-                        self.handle_request(
-                            &mut socket_handle,
-                            request,
-                            &mut scan_requests,
-                            &mut select_request,
-                        )
-                        .await?;
+                        interface
+                            .handle_request(&mut socket_handle, request)
+                            .await?;
                     }
                     None => return Err(error::SocketError::ClientChannelClosed),
                 },
                 EventOrRequest::SelectTimeout => {
-                    if let Some(sender) = select_request.take() {
-                        sender.send(Err(ClientError::Timeout));
-                    };
+                    interface.expire_select(&mut socket_handle).await?
+                }
+                EventOrRequest::Keepalive => {
+                    if unsolicited.keepalive().await? {
+                        self.broadcast(Broadcast::Reattached);
+                    }
                 }
             }
         }
     }
+}
+
+/// Request state for one wpa_supplicant interface. The single-interface
+/// runner owns one; the [global runner](GlobalStation) keeps one per interface.
+struct Interface {
+    /// Scan requests, batch-answered when results are ready
+    scan_requests: Vec<oneshot::Sender<Result<ScanResults>>>,
+    select_request: Option<SelectRequest>,
+    /// Selects waiting for the pending one, under [`SelectPolicy::Queue`]
+    select_queue: VecDeque<QueuedSelect>,
+    /// Timeout duration in case no valid select response is received
+    select_timeout: Duration,
+    select_policy: SelectPolicy,
+    /// Learnt from the daemon's version on first use
+    ignore_naming: Option<IgnoreNaming>,
+}
+
+impl Interface {
+    fn new(select_timeout: Duration, select_policy: SelectPolicy) -> Self {
+        Self {
+            scan_requests: Vec::new(),
+            select_request: None,
+            select_queue: VecDeque::new(),
+            select_timeout,
+            select_policy,
+            ignore_naming: None,
+        }
+    }
+
+    async fn ignore_naming<const N: usize>(
+        &mut self,
+        socket_handle: &mut SocketHandle<N>,
+    ) -> SocketResult<Result<IgnoreNaming>> {
+        if let Some(naming) = self.ignore_naming {
+            return Ok(Ok(naming));
+        }
+        let naming = IgnoreNaming::request(socket_handle).await?;
+        if let Ok(naming) = naming {
+            debug!("wpa_ctrl BSSID ignore naming: {naming:?}");
+            self.ignore_naming = Some(naming);
+        }
+        Ok(naming)
+    }
+
+    /// Resolves when the pending select request, if any, times out or is
+    /// abandoned by its client, so it stops holding up the next select.
+    async fn select_timeout(&mut self) {
+        match self.select_request.as_mut() {
+            Some(SelectRequest {
+                response, timeout, ..
+            }) => tokio::select!(
+                _ = timeout.as_mut() => (),
+                _ = response.closed() => (),
+            ),
+            None => std::future::pending().await,
+        }
+    }
+
+    async fn expire_select<const N: usize>(
+        &mut self,
+        socket_handle: &mut SocketHandle<N>,
+    ) -> SocketResult {
+        if let Some(sender) = self.select_request.take() {
+            sender.send(Err(ClientError::Timeout));
+        };
+        self.next_select(socket_handle).await
+    }
+
+    /// Start queued selects, oldest first, until one is left pending.
+    async fn next_select<const N: usize>(
+        &mut self,
+        socket_handle: &mut SocketHandle<N>,
+    ) -> SocketResult {
+        while self.select_request.is_none() {
+            let Some(QueuedSelect {
+                id,
+                response,
+                deadline,
+            }) = self.select_queue.pop_front()
+            else {
+                break;
+            };
+            if response.is_closed() {
+                debug!("Skipping queued select of network {id} abandoned by its client");
+                continue;
+            }
+            self.start_select(socket_handle, id, response, deadline)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn start_select<const N: usize>(
+        &mut self,
+        socket_handle: &mut SocketHandle<N>,
+        id: usize,
+        response_sender: oneshot::Sender<Result<SelectResult>>,
+        deadline: Option<Instant>,
+    ) -> SocketResult {
+        let cmd = format!("SELECT_NETWORK {id}");
+        if let Err(e) = socket_handle.command(cmd.as_bytes()).await? {
+            warn!("Error while selecting network {id}: {e}");
+            let _ = response_sender.send(Err(e));
+            return Ok(());
+        }
+        debug!("wpa_ctrl selected network {id}");
+        match Self::get_status(socket_handle).await? {
+            Err(e) => {
+                let _ = response_sender.send(Err(e));
+            }
+            Ok(status) if status.id == Some(id) => {
+                let _ = response_sender.send(Ok(SelectResult::AlreadyConnected));
+            }
+            Ok(_) => {
+                let deadline = deadline.unwrap_or_else(|| Instant::now() + self.select_timeout);
+                self.select_request = Some(SelectRequest::new(response_sender, id, deadline));
+            }
+        }
+        Ok(())
+    }
 
     async fn handle_event<const N: usize>(
         &mut self,
         socket_handle: &mut SocketHandle<N>,
         event: Event,
-        scan_requests: &mut Vec<oneshot::Sender<Result<Arc<Vec<ScanResult>>>>>,
-        select_request: &mut Option<SelectRequest>,
+        broadcast: impl Fn(Broadcast),
     ) -> SocketResult {
         match event {
             Event::ScanComplete => {
                 let scan_results = socket_handle
                     .request("SCAN_RESULTS", ScanResult::vec_from_str)
                     .await?;
-                while let Some(scan_request) = scan_requests.pop() {
+                if let Ok(scan_results) = &scan_results {
+                    broadcast(Broadcast::ScanResults(scan_results.clone()));
+                }
+                while let Some(scan_request) = self.scan_requests.pop() {
                     let _ = scan_request.send(scan_results.clone());
                 }
             }
             Event::ScanFailed => {
-                while let Some(scan_request) = scan_requests.pop() {
+                while let Some(scan_request) = self.scan_requests.pop() {
                     let _ = scan_request.send(Err(ClientError::Failed));
                 }
             }
             Event::Connected => {
-                self.broadcast(Broadcast::Connected);
-                if let Some(sender) = select_request.take() {
+                broadcast(Broadcast::Connected);
+                if let Some(sender) = self.select_request.take() {
                     sender.send(Ok(SelectResult::Success));
                 }
             }
             Event::Disconnected => {
-                self.broadcast(Broadcast::Disconnected);
+                broadcast(Broadcast::Disconnected);
             }
             Event::NetworkNotFound => {
-                self.broadcast(Broadcast::NetworkNotFound);
-                if let Some(sender) = select_request.take() {
-                    sender.send(Ok(SelectResult::NotFound));
+                broadcast(Broadcast::NetworkNotFound);
+                if let Some(mut select_request) = self.select_request.take() {
+                    // a hidden network may only answer a probe for its SSID,
+                    // so look for it by name once before giving up
+                    let probing = if select_request.probed {
+                        Ok(DirectedProbe::NotHidden)
+                    } else {
+                        Self::directed_probe(socket_handle, select_request.network_id).await?
+                    };
+                    match probing {
+                        Ok(DirectedProbe::Started) => {
+                            select_request.probed = true;
+                            self.select_request = Some(select_request);
+                        }
+                        // this NETWORK-NOT-FOUND came from the scan already
+                        // under way; probe when the next one ends
+                        Ok(DirectedProbe::Busy) => self.select_request = Some(select_request),
+                        Ok(DirectedProbe::NotHidden) => {
+                            select_request.send(Ok(SelectResult::NotFound))
+                        }
+                        Err(e) => select_request.send(Err(e)),
+                    }
                 }
             }
             Event::WrongPsk => {
-                self.broadcast(Broadcast::WrongPsk);
-                if let Some(sender) = select_request.take() {
+                broadcast(Broadcast::WrongPsk);
+                if let Some(sender) = self.select_request.take() {
                     sender.send(Ok(SelectResult::WrongPsk));
                 }
             }
+            Event::InterworkingAp(ap) => broadcast(Broadcast::InterworkingAp(ap)),
+            Event::InterworkingNoMatch => broadcast(Broadcast::InterworkingNoMatch),
+            Event::Anqp(info) => broadcast(Broadcast::AnqpReceived(info)),
+            Event::Hs20Anqp(info) => broadcast(Broadcast::Hs20AnqpReceived(info)),
+            Event::MeshGroupStarted(group) => broadcast(Broadcast::MeshGroupStarted(group)),
+            Event::MeshGroupRemoved(ifname) => broadcast(Broadcast::MeshGroupRemoved(ifname)),
+            Event::MeshPeerConnected(peer) => broadcast(Broadcast::MeshPeerConnected(peer)),
+            Event::MeshPeerDisconnected(peer) => broadcast(Broadcast::MeshPeerDisconnected(peer)),
+            Event::RegdomChanged(change) => broadcast(Broadcast::RegdomChanged(change)),
             Event::Unknown(msg) => {
-                self.broadcast(Broadcast::Unknown(msg));
+                broadcast(Broadcast::Unknown(msg));
             }
         }
-        Ok(())
+        self.next_select(socket_handle).await
     }
 
     async fn get_status<const N: usize>(
@@ -187,12 +375,52 @@ impl WifiStation {
         socket_handle.request("STATUS", parse_status).await
     }
 
+    /// Start a scan probing for the network by SSID if it's hidden
+    /// (`scan_ssid=1`).
+    async fn directed_probe<const N: usize>(
+        socket_handle: &mut SocketHandle<N>,
+        network_id: usize,
+    ) -> SocketResult<Result<DirectedProbe>> {
+        let cmd = format!("GET_NETWORK {network_id} scan_ssid");
+        match socket_handle.request(&cmd, parse_scan_ssid).await? {
+            Ok(true) => (),
+            Ok(false) => return Ok(Ok(DirectedProbe::NotHidden)),
+            Err(e) => return Ok(Err(e)),
+        }
+        let cmd = format!("GET_NETWORK {network_id} ssid");
+        let ssid_hex = match socket_handle.request(&cmd, parse_ssid_hex).await? {
+            Ok(ssid_hex) => ssid_hex,
+            Err(e) => return Ok(Err(e)),
+        };
+        debug!("wpa_ctrl probing for hidden network {network_id}");
+        let scan = socket_handle
+            .command(directed_scan_command(&ssid_hex).as_bytes())
+            .await?;
+        Ok(DirectedProbe::from_scan(scan))
+    }
+
     async fn handle_request<const N: usize>(
-        &self,
+        &mut self,
         socket_handle: &mut SocketHandle<N>,
         request: Request,
-        scan_requests: &mut Vec<oneshot::Sender<Result<Arc<Vec<ScanResult>>>>>,
-        select_request: &mut Option<SelectRequest>,
+    ) -> SocketResult {
+        let (deadline, request) = request.split_deadline();
+        if request.is_abandoned() {
+            debug!("Skipping request abandoned by its client: {request:?}");
+            return Ok(());
+        }
+        socket_handle.set_deadline(deadline);
+        let handled = self.send_request(socket_handle, request, deadline).await;
+        socket_handle.set_deadline(None);
+        handled?;
+        self.next_select(socket_handle).await
+    }
+
+    async fn send_request<const N: usize>(
+        &mut self,
+        socket_handle: &mut SocketHandle<N>,
+        request: Request,
+        deadline: Option<Instant>,
     ) -> SocketResult {
         debug!("Handling request: {request:?}");
         match request {
@@ -210,7 +438,7 @@ impl WifiStation {
                     .await?
                 {
                     Ok(_) => {
-                        scan_requests.push(response_channel);
+                        self.scan_requests.push(response_channel);
                     }
                     Err(e) => {
                         let _ = response_channel.send(Err(e));
@@ -225,6 +453,10 @@ impl WifiStation {
                 let status = Self::get_status(socket_handle).await?;
                 let _ = response_channel.send(status);
             }
+            Request::Capabilities(response_channel) => {
+                let capabilities = Capabilities::request(socket_handle).await?;
+                let _ = response_channel.send(capabilities);
+            }
             Request::AddNetwork(response_channel) => {
                 let network_id = socket_handle
                     .request("ADD_NETWORK", usize::from_str)
@@ -233,16 +465,7 @@ impl WifiStation {
                 let _ = response_channel.send(network_id);
             }
             Request::SetNetwork(id, param, response) => {
-                // Psk and Bssid are validated at construction, so every
-                // variant formats infallibly; Psk's Debug impl redacts the
-                // key wherever the request is logged.
-                let field = match &param {
-                    SetNetwork::Ssid(ssid) => format!("ssid {}", conf_escape(ssid)),
-                    SetNetwork::Bssid(bssid) => format!("bssid {bssid}"),
-                    SetNetwork::Psk(psk) => format!("psk {}", psk.to_field()),
-                    SetNetwork::KeyMgmt(mgmt) => format!("key_mgmt {mgmt}"),
-                };
-                let cmd = format!("SET_NETWORK {id} {field}");
+                let cmd = param.to_command(id);
                 match &param {
                     SetNetwork::Psk(_) => debug!("wpa_ctrl SET_NETWORK {id} psk <redacted>"),
                     _ => debug!("wpa_ctrl {cmd:?}"),
@@ -268,69 +491,188 @@ impl WifiStation {
                 let _ = response.send(socket_handle.command(&bytes).await?);
             }
             Request::SelectNetwork(id, response_sender) => {
-                match select_request {
-                    None => {
-                        let cmd = format!("SELECT_NETWORK {id}");
-                        let bytes = cmd.into_bytes();
-                        if let Err(e) = socket_handle.command(&bytes).await? {
-                            warn!("Error while selecting network {id}: {e}");
-                            let _ = response_sender.send(Err(e));
-                        } else {
-                            debug!("wpa_ctrl selected network {id}");
-                            match Self::get_status(socket_handle).await? {
-                                Err(e) => {
-                                    let _ = response_sender.send(Err(e));
-                                }
-                                Ok(status) if status.id == Some(id) => {
-                                    let _ =
-                                        response_sender.send(Ok(SelectResult::AlreadyConnected));
-                                }
-                                Ok(_) => {
-                                    *select_request = Some(SelectRequest::new(
-                                        response_sender,
-                                        self.select_timeout,
-                                    ));
-                                }
-                            }
-                        }
+                match (&self.select_request, self.select_policy) {
+                    (None, _) => {
+                        self.start_select(socket_handle, id, response_sender, deadline)
+                            .await?
                     }
-                    Some(_) => {
+                    (Some(_), SelectPolicy::Reject) => {
                         warn!("Select request already pending! Dropping this one.");
                         let _ = response_sender.send(Err(ClientError::PendingSelect));
                         debug!(
                             "wpa_ctrl rejected select of network {id}: a select is already pending"
                         );
                     }
+                    (Some(_), SelectPolicy::Queue) => {
+                        debug!("wpa_ctrl queued select of network {id}");
+                        self.select_queue.push_back(QueuedSelect {
+                            id,
+                            response: response_sender,
+                            deadline,
+                        });
+                    }
+                    (Some(_), SelectPolicy::Supersede) => {
+                        if let Some(pending) = self.select_request.take() {
+                            pending.send(Ok(SelectResult::Superseded));
+                        }
+                        debug!("wpa_ctrl superseded pending select with network {id}");
+                        self.start_select(socket_handle, id, response_sender, deadline)
+                            .await?
+                    }
+                };
+            }
+            Request::AddCred(cred, response) => {
+                let _ = response.send(cred.add(socket_handle).await?);
+            }
+            Request::SetCred(id, cred, response) => {
+                let _ = response.send(cred.set(socket_handle, id).await?);
+            }
+            Request::ListCreds(response) => {
+                let creds = socket_handle
+                    .request("LIST_CREDS", CredentialEntry::vec_from_str)
+                    .await?;
+                let _ = response.send(creds);
+            }
+            Request::RemoveCred(remove_cred, response) => {
+                let cmd = match remove_cred {
+                    RemoveNetwork::All => "REMOVE_CRED all".to_string(),
+                    RemoveNetwork::Id(id) => format!("REMOVE_CRED {id}"),
+                };
+                debug!("wpa_ctrl {cmd}");
+                let _ = response.send(socket_handle.command(cmd.as_bytes()).await?);
+            }
+            Request::Interworking(interworking, response) => match interworking.to_command() {
+                Ok(cmd) => {
+                    debug!("wpa_ctrl {cmd}");
+                    let _ = response.send(socket_handle.command(cmd.as_bytes()).await?);
+                }
+                Err(e) => {
+                    let _ = response.send(Err(e));
+                }
+            },
+            Request::MeshInterfaceAdd(ifname, response) => {
+                let cmd = match ifname.as_deref().map(check_ifname) {
+                    Some(Ok(ifname)) => format!("MESH_INTERFACE_ADD ifname={ifname}"),
+                    Some(Err(e)) => {
+                        let _ = response.send(Err(e));
+                        return Ok(());
+                    }
+                    None => "MESH_INTERFACE_ADD".to_string(),
                 };
+                let ifname = socket_handle.request(&cmd, parse_mesh_interface).await?;
+                debug!("wpa_ctrl created mesh interface {ifname:?}");
+                let _ = response.send(ifname);
             }
+            Request::Mesh(mesh, response) => match mesh.to_command() {
+                Ok(cmd) => {
+                    debug!("wpa_ctrl {cmd}");
+                    let _ = response.send(socket_handle.command(cmd.as_bytes()).await?);
+                }
+                Err(e) => {
+                    let _ = response.send(Err(e));
+                }
+            },
+            Request::BssidIgnore(change, response) => {
+                let result = match self.ignore_naming(socket_handle).await? {
+                    Ok(naming) => change.apply(socket_handle, naming).await?,
+                    Err(e) => Err(e),
+                };
+                let _ = response.send(result);
+            }
+            Request::BssidIgnoreList(response) => {
+                let ignored = match self.ignore_naming(socket_handle).await? {
+                    Ok(naming) => list_bssid_ignore(socket_handle, naming).await?,
+                    Err(e) => Err(e),
+                };
+                let _ = response.send(ignored);
+            }
+            Request::NetworkBssids(id, list, bssids, response) => {
+                let result = match self.ignore_naming(socket_handle).await? {
+                    Ok(naming) => {
+                        let cmd = list.to_command(naming, id, &bssids);
+                        debug!("wpa_ctrl {cmd}");
+                        socket_handle.command(cmd.as_bytes()).await?
+                    }
+                    Err(e) => Err(e),
+                };
+                let _ = response.send(result);
+            }
+            Request::GetCountry(response) => {
+                let country = match socket_handle.request("GET country", parse_country).await? {
+                    // wpa_supplicant fails the request if no country is set
+                    Err(ClientError::Failed) => Ok(None),
+                    country => country.map(Some),
+                };
+                let _ = response.send(country);
+            }
+            Request::Set(set, response) => match set.to_command() {
+                Ok(cmd) => {
+                    debug!("wpa_ctrl {cmd}");
+                    let _ = response.send(socket_handle.command(cmd.as_bytes()).await?);
+                }
+                Err(e) => {
+                    let _ = response.send(Err(e));
+                }
+            },
+            Request::Autoscan(autoscan, response) => {
+                let cmd = match autoscan {
+                    Some(autoscan) => format!("AUTOSCAN {autoscan}"),
+                    None => "AUTOSCAN ".to_string(),
+                };
+                debug!("wpa_ctrl {cmd:?}");
+                let _ = response.send(socket_handle.command(cmd.as_bytes()).await?);
+            }
+            Request::AutoConnect(enabled, response) => {
+                let cmd = format!("STA_AUTOCONNECT {}", u8::from(enabled));
+                debug!("wpa_ctrl {cmd}");
+                let _ = response.send(socket_handle.command(cmd.as_bytes()).await?);
+            }
+            Request::CancelSelect(response) => {
+                let pending = self.select_request.take();
+                let cancelled = pending.is_some();
+                if let Some(select_request) = pending {
+                    debug!("wpa_ctrl select cancelled");
+                    select_request.send(Err(ClientError::Cancelled));
+                }
+                let _ = response.send(Ok(cancelled));
+            }
+            // unwrapped by handle_request
+            Request::WithDeadline(..) => (),
             Request::Shutdown => (), //shutdown is handled at the scope above
         }
         Ok(())
     }
 }
 
-/// convert to wpa config format, ideally a "quoted string"
-/// in case of new-lines, quotes or emoji fall back to hex encoding the whole thing
-fn conf_escape(raw: &str) -> String {
-    if raw.bytes().all(|b| b.is_ascii_graphic() && b != b'"') {
-        format!("\"{raw}\"")
-    } else {
-        hex::encode(raw)
-    }
+/// A select waiting its turn under [`SelectPolicy::Queue`]
+struct QueuedSelect {
+    id: usize,
+    response: oneshot::Sender<Result<SelectResult>>,
+    deadline: Option<Instant>,
 }
 
 struct SelectRequest {
     response: oneshot::Sender<Result<SelectResult>>,
+    network_id: usize,
+    /// A directed scan for the network's SSID has been started, so the next
+    /// NETWORK-NOT-FOUND is final
+    probed: bool,
     /// Polled as a branch of the main event loop; expiry resolves the request
     /// with a timeout error
     timeout: std::pin::Pin<Box<tokio::time::Sleep>>,
 }
 
 impl SelectRequest {
-    fn new(response: oneshot::Sender<Result<SelectResult>>, timeout: Duration) -> Self {
+    fn new(
+        response: oneshot::Sender<Result<SelectResult>>,
+        network_id: usize,
+        deadline: Instant,
+    ) -> Self {
         Self {
             response,
-            timeout: Box::pin(tokio::time::sleep(timeout)),
+            network_id,
+            probed: false,
+            timeout: Box::pin(tokio::time::sleep_until(deadline)),
         }
     }
 
@@ -342,6 +684,237 @@ impl SelectRequest {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::socket_handle::tests::{connect, serve};
+
+    #[tokio::test]
+    async fn abandoned_requests_are_skipped_and_select_can_be_cancelled() {
+        let (_dir, daemon, mut handle) = connect(Duration::from_secs(1)).await;
+        let mut interface = Interface::new(Duration::from_secs(10), SelectPolicy::Reject);
+        let (abandoned, dropped) = oneshot::channel();
+        drop(dropped);
+        let (select, selected) = oneshot::channel();
+        let (cancel, cancelled) = oneshot::channel();
+        let requests = async {
+            for request in [
+                Request::Status(abandoned),
+                Request::SelectNetwork(1, select),
+                Request::CancelSelect(cancel),
+            ] {
+                interface
+                    .handle_request(&mut handle, request)
+                    .await
+                    .unwrap();
+            }
+        };
+        let ((), received) = tokio::join!(
+            requests,
+            serve(&daemon, &[Some("OK\n"), Some("wpa_state=SCANNING\n")])
+        );
+        assert_eq!(received, ["SELECT_NETWORK 1", "STATUS"]);
+        assert!(matches!(cancelled.await.unwrap(), Ok(true)));
+        assert!(matches!(
+            selected.await.unwrap(),
+            Err(ClientError::Cancelled)
+        ));
+    }
+
+    #[tokio::test]
+    async fn bssid_ignore_uses_the_legacy_names_on_older_daemons() {
+        let (_dir, daemon, mut handle) = connect(Duration::from_secs(1)).await;
+        let mut interface = Interface::new(Duration::from_secs(10), SelectPolicy::Reject);
+        let flaky: Bssid = "02:00:00:00:01:00".parse().unwrap();
+        let (remove, removed) = oneshot::channel();
+        let (accept, accepted) = oneshot::channel();
+        let requests = async {
+            for request in [
+                Request::BssidIgnore(BssidIgnore::Remove(flaky), remove),
+                Request::NetworkBssids(0, BssidList::Accept, vec![flaky], accept),
+            ] {
+                interface
+                    .handle_request(&mut handle, request)
+                    .await
+                    .unwrap();
+            }
+        };
+        let replies = [
+            Some("2.9\n"),
+            Some("02:00:00:00:01:00\n02:00:00:00:02:00\n"),
+            Some("OK\n"),
+            Some("OK\n"),
+            Some("OK\n"),
+        ];
+        let ((), received) = tokio::join!(requests, serve(&daemon, &replies));
+        // the version is only asked for once
+        assert_eq!(
+            received,
+            [
+                "GET version",
+                "BLACKLIST",
+                "BLACKLIST clear",
+                "BLACKLIST 02:00:00:00:02:00",
+                "SET_NETWORK 0 bssid_whitelist 02:00:00:00:01:00",
+            ]
+        );
+        assert!(removed.await.unwrap().is_ok());
+        assert!(accepted.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn queued_select_starts_when_the_pending_one_resolves() {
+        let (_dir, daemon, mut handle) = connect(Duration::from_secs(1)).await;
+        let mut interface = Interface::new(Duration::from_secs(10), SelectPolicy::Queue);
+        let (first, first_result) = oneshot::channel();
+        let (second, mut second_result) = oneshot::channel();
+        let requests = async {
+            for request in [
+                Request::SelectNetwork(1, first),
+                Request::SelectNetwork(2, second),
+            ] {
+                interface
+                    .handle_request(&mut handle, request)
+                    .await
+                    .unwrap();
+            }
+            interface
+                .handle_event(&mut handle, Event::Connected, |_| ())
+                .await
+                .unwrap();
+        };
+        let scanning = Some("wpa_state=SCANNING\n");
+        let replies = [Some("OK\n"), scanning, Some("OK\n"), scanning];
+        let ((), received) = tokio::join!(requests, serve(&daemon, &replies));
+        assert_eq!(
+            received,
+            ["SELECT_NETWORK 1", "STATUS", "SELECT_NETWORK 2", "STATUS"]
+        );
+        assert!(matches!(
+            first_result.await.unwrap(),
+            Ok(SelectResult::Success)
+        ));
+        // the second is now the pending select, awaiting its own outcome
+        assert!(second_result.try_recv().is_err());
+        assert!(interface.select_request.is_some());
+    }
+
+    #[tokio::test]
+    async fn superseding_select_resolves_the_pending_one() {
+        let (_dir, daemon, mut handle) = connect(Duration::from_secs(1)).await;
+        let mut interface = Interface::new(Duration::from_secs(10), SelectPolicy::Supersede);
+        let (first, first_result) = oneshot::channel();
+        let (second, mut second_result) = oneshot::channel();
+        let requests = async {
+            for request in [
+                Request::SelectNetwork(1, first),
+                Request::SelectNetwork(2, second),
+            ] {
+                interface
+                    .handle_request(&mut handle, request)
+                    .await
+                    .unwrap();
+            }
+        };
+        let scanning = Some("wpa_state=SCANNING\n");
+        let replies = [Some("OK\n"), scanning, Some("OK\n"), scanning];
+        let ((), received) = tokio::join!(requests, serve(&daemon, &replies));
+        // the second select is sent straight away rather than queued
+        assert_eq!(
+            received,
+            ["SELECT_NETWORK 1", "STATUS", "SELECT_NETWORK 2", "STATUS"]
+        );
+        assert!(matches!(
+            first_result.await.unwrap(),
+            Ok(SelectResult::Superseded)
+        ));
+        assert!(second_result.try_recv().is_err());
+        assert!(interface.select_request.is_some());
+        assert!(interface.select_queue.is_empty());
+    }
+
+    #[tokio::test]
+    async fn hidden_network_is_probed_before_not_found() {
+        let (_dir, daemon, mut handle) = connect(Duration::from_secs(1)).await;
+        let mut interface = Interface::new(Duration::from_secs(10), SelectPolicy::Reject);
+        let (select, mut selected) = oneshot::channel();
+        let requests = async {
+            interface
+                .handle_request(&mut handle, Request::SelectNetwork(1, select))
+                .await
+                .unwrap();
+            interface
+                .handle_event(&mut handle, Event::NetworkNotFound, |_| ())
+                .await
+                .unwrap();
+        };
+        let replies = [
+            Some("OK\n"),
+            Some("wpa_state=SCANNING\n"),
+            Some("1\n"),
+            Some("\"lab\"\n"),
+            Some("OK\n"),
+        ];
+        let ((), received) = tokio::join!(requests, serve(&daemon, &replies));
+        assert_eq!(
+            received,
+            [
+                "SELECT_NETWORK 1",
+                "STATUS",
+                "GET_NETWORK 1 scan_ssid",
+                "GET_NETWORK 1 ssid",
+                "SCAN ssid 6c6162",
+            ]
+        );
+        // the first NETWORK-NOT-FOUND only starts the directed scan
+        assert!(selected.try_recv().is_err());
+        interface
+            .handle_event(&mut handle, Event::NetworkNotFound, |_| ())
+            .await
+            .unwrap();
+        assert!(matches!(
+            selected.await.unwrap(),
+            Ok(SelectResult::NotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn busy_probe_is_retried_on_the_next_not_found() {
+        let (_dir, daemon, mut handle) = connect(Duration::from_secs(1)).await;
+        let mut interface = Interface::new(Duration::from_secs(10), SelectPolicy::Reject);
+        let (select, mut selected) = oneshot::channel();
+        interface.select_request = Some(SelectRequest::new(
+            select,
+            1,
+            Instant::now() + Duration::from_secs(10),
+        ));
+        // a UTF-8 SSID comes back as bare hex and is passed on as-is
+        let probe = [Some("1\n"), Some("636166c3a9\n")];
+        for scan_reply in ["FAIL-BUSY\n", "OK\n"] {
+            let replies = [probe[0], probe[1], Some(scan_reply)];
+            let events = async {
+                interface
+                    .handle_event(&mut handle, Event::NetworkNotFound, |_| ())
+                    .await
+                    .unwrap();
+            };
+            let ((), received) = tokio::join!(events, serve(&daemon, &replies));
+            assert_eq!(
+                received,
+                [
+                    "GET_NETWORK 1 scan_ssid",
+                    "GET_NETWORK 1 ssid",
+                    "SCAN ssid 636166c3a9",
+                ]
+            );
+            assert!(selected.try_recv().is_err());
+        }
+        interface
+            .handle_event(&mut handle, Event::NetworkNotFound, |_| ())
+            .await
+            .unwrap();
+        assert!(matches!(
+            selected.await.unwrap(),
+            Ok(SelectResult::NotFound)
+        ));
+    }
 
     #[test]
     fn psk_passphrase_is_quoted() {
@@ -447,7 +1020,7 @@ mod tests {
             "",
         ] {
             assert!(
-                matches!(bad.parse::<Bssid>(), Err(ClientError::InvalidBssid)),
+                matches!(bad.parse::<Bssid>(), Err(ClientError::InvalidMacAddr)),
                 "expected {bad:?} to be rejected"
             );
         }
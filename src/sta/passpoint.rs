@@ -0,0 +1,366 @@
+use super::*;
+use serde::Serialize;
+use std::fmt::Display;
+
+/// A Hotspot 2.0 (Passpoint) credential, as stored in a `cred={ ... }` block
+/// and added at runtime with [`RequestClient::add_credential`]. Interworking
+/// selection matches it against access points advertising the realm, domain
+/// or roaming consortium.
+///
+/// ```
+/// use wifi_ctrl::sta::Credential;
+///
+/// let mut cred = Credential::default();
+/// cred.realm = Some("example.com".into());
+/// cred.domain = vec!["example.com".into()];
+/// cred.username = Some("user@example.com".into());
+/// cred.password = Some("secret".into());
+/// cred.eap = Some("TTLS".into());
+/// cred.phase2 = Some("auth=MSCHAPV2".into());
+/// // Debug never reveals the password
+/// assert!(!format!("{cred:?}").contains("secret"));
+/// ```
+#[derive(Clone, Default)]
+pub struct Credential {
+    pub realm: Option<String>,
+    /// Home service provider FQDNs
+    pub domain: Vec<String>,
+    /// Roaming consortium OI, as 3-15 octets of hex
+    pub roaming_consortium: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub ca_cert: Option<String>,
+    /// EAP method, e.g. `TTLS`, `TLS` or `AKA`
+    pub eap: Option<String>,
+    /// Inner authentication, e.g. `auth=MSCHAPV2`
+    pub phase2: Option<String>,
+    pub sim: Option<SimCredential>,
+    pub priority: Option<u32>,
+}
+
+/// SIM-based authentication for a [`Credential`] (EAP-SIM/AKA).
+#[derive(Clone)]
+pub struct SimCredential {
+    /// IMSI as `<MCC><MNC>-<MSIN>`, e.g. `310026-000000000`
+    pub imsi: String,
+    /// Milenage parameters `<Ki>:<OPc>:<SQN>` for a simulated SIM, or `None`
+    /// to use a real one through PC/SC
+    pub milenage: Option<String>,
+}
+
+/// Never print secrets: `Request` is logged at debug level with `{:?}`.
+impl std::fmt::Debug for Credential {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credential")
+            .field("realm", &self.realm)
+            .field("domain", &self.domain)
+            .field("roaming_consortium", &self.roaming_consortium)
+            .field("username", &self.username)
+            .field("password", &self.password.as_ref().map(|_| "<redacted>"))
+            .field("eap", &self.eap)
+            .field("sim", &self.sim.as_ref().map(|sim| &sim.imsi))
+            .finish_non_exhaustive()
+    }
+}
+
+impl Credential {
+    /// The `SET_CRED <id> <field> <value>` pairs setting this credential,
+    /// validating every value that goes into the command unquoted.
+    pub(crate) fn to_fields(&self) -> Result<Vec<(&'static str, String)>> {
+        let invalid = || ClientError::InvalidParameter("credential");
+        let mut fields = Vec::new();
+        let mut quoted = |field, value: &Option<String>| {
+            if let Some(value) = value {
+                fields.push((field, conf_escape(value)));
+            }
+        };
+        quoted("realm", &self.realm);
+        quoted("username", &self.username);
+        quoted("password", &self.password);
+        quoted("ca_cert", &self.ca_cert);
+        quoted("phase2", &self.phase2);
+        for domain in &self.domain {
+            // every SET_CRED of domain adds one to the list
+            fields.push(("domain", conf_escape(domain)));
+        }
+        if let Some(oi) = &self.roaming_consortium {
+            let hex = oi.len() % 2 == 0 && oi.bytes().all(|b| b.is_ascii_hexdigit());
+            if !hex || !(6..=30).contains(&oi.len()) {
+                return Err(invalid());
+            }
+            fields.push(("roaming_consortium", oi.clone()));
+        }
+        if let Some(eap) = &self.eap {
+            if eap.is_empty() || !eap.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-') {
+                return Err(invalid());
+            }
+            fields.push(("eap", eap.clone()));
+        }
+        if let Some(sim) = &self.sim {
+            if !sim.imsi.bytes().all(|b| b.is_ascii_digit() || b == b'-') {
+                return Err(invalid());
+            }
+            fields.push(("imsi", format!("\"{}\"", sim.imsi)));
+            if let Some(milenage) = &sim.milenage {
+                if !milenage.bytes().all(|b| b.is_ascii_hexdigit() || b == b':') {
+                    return Err(invalid());
+                }
+                fields.push(("milenage", format!("\"{milenage}\"")));
+            }
+        }
+        if let Some(priority) = self.priority {
+            fields.push(("priority", priority.to_string()));
+        }
+        Ok(fields)
+    }
+
+    /// `ADD_CRED` then set every field, removing the credential again if any
+    /// of them fails so a half-configured one isn't left matching networks.
+    pub(crate) async fn add<const N: usize>(
+        &self,
+        socket_handle: &mut SocketHandle<N>,
+    ) -> SocketResult<Result<usize>> {
+        let fields = match self.to_fields() {
+            Ok(fields) => fields,
+            Err(e) => return Ok(Err(e)),
+        };
+        let id = match socket_handle.request("ADD_CRED", usize::from_str).await? {
+            Ok(id) => id,
+            Err(e) => return Ok(Err(e)),
+        };
+        if let Err(e) = set_fields(socket_handle, id, &fields).await? {
+            let cmd = format!("REMOVE_CRED {id}");
+            if let Err(e) = socket_handle.command(cmd.as_bytes()).await? {
+                warn!("Failed to remove partially set credential {id}: {e}");
+            }
+            return Ok(Err(e));
+        }
+        debug!("wpa_ctrl created credential {id}");
+        Ok(Ok(id))
+    }
+
+    /// Set the fields given in this credential on an existing one.
+    pub(crate) async fn set<const N: usize>(
+        &self,
+        socket_handle: &mut SocketHandle<N>,
+        id: usize,
+    ) -> SocketResult<Result> {
+        match self.to_fields() {
+            Ok(fields) => set_fields(socket_handle, id, &fields).await,
+            Err(e) => Ok(Err(e)),
+        }
+    }
+}
+
+async fn set_fields<const N: usize>(
+    socket_handle: &mut SocketHandle<N>,
+    id: usize,
+    fields: &[(&str, String)],
+) -> SocketResult<Result> {
+    for (field, value) in fields {
+        // not logged: the value may be a password
+        let cmd = format!("SET_CRED {id} {field} {value}");
+        if let Err(e) = socket_handle.command(cmd.as_bytes()).await? {
+            warn!("Error setting credential {id} {field}: {e}");
+            return Ok(Err(e));
+        }
+    }
+    Ok(Ok(()))
+}
+
+/// A credential from [`RequestClient::list_credentials`]. Fields that aren't
+/// set are empty.
+#[derive(Serialize, Debug, Clone)]
+pub struct CredentialEntry {
+    pub id: usize,
+    pub realm: String,
+    pub username: String,
+    /// The first home domain
+    pub domain: String,
+    pub imsi: String,
+}
+
+impl CredentialEntry {
+    /// Parse a `LIST_CREDS` response: a header line, then tab-separated
+    /// id, realm, username, domain and IMSI.
+    pub(crate) fn vec_from_str(response: &str) -> ParseResult<Vec<CredentialEntry>> {
+        let mut creds = Vec::new();
+        for line in response.lines().skip(1) {
+            let mut fields = line.split('\t');
+            let Some(Ok(id)) = fields.next().map(usize::from_str) else {
+                warn!("Invalid credential: {line}");
+                continue;
+            };
+            let mut next = || fields.next().unwrap_or_default().to_string();
+            creds.push(CredentialEntry {
+                id,
+                realm: next(),
+                username: next(),
+                domain: next(),
+                imsi: next(),
+            });
+        }
+        Ok(creds)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum Interworking {
+    Select { auto: bool },
+    Connect(Bssid),
+    AnqpGet(Bssid, Vec<u16>),
+    Hs20AnqpGet(Bssid, Vec<u8>),
+}
+
+impl Interworking {
+    pub(crate) fn to_command(&self) -> Result<String> {
+        fn list<T: Display>(ids: &[T]) -> Result<String> {
+            if ids.is_empty() {
+                return Err(ClientError::InvalidParameter("ANQP info ids"));
+            }
+            Ok(ids.iter().map(T::to_string).collect::<Vec<_>>().join(","))
+        }
+        Ok(match self {
+            Interworking::Select { auto: false } => "INTERWORKING_SELECT".to_string(),
+            Interworking::Select { auto: true } => "INTERWORKING_SELECT auto".to_string(),
+            Interworking::Connect(bssid) => format!("INTERWORKING_CONNECT {bssid}"),
+            Interworking::AnqpGet(bssid, ids) => format!("ANQP_GET {bssid} {}", list(ids)?),
+            Interworking::Hs20AnqpGet(bssid, subtypes) => {
+                format!("HS20_ANQP_GET {bssid} {}", list(subtypes)?)
+            }
+        })
+    }
+}
+
+/// How an access point found by interworking selection matches a
+/// [`Credential`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterworkingMatch {
+    /// Operated by the credential's home service provider
+    Home,
+    /// Reachable through a roaming partner
+    Roaming,
+    Unknown,
+}
+
+/// An `INTERWORKING-AP` event: an access point matching one of the
+/// credentials, found by [`RequestClient::interworking_select`].
+#[derive(Debug, Clone)]
+pub struct InterworkingAp {
+    pub bssid: Bssid,
+    pub matched: InterworkingMatch,
+    /// The matching credential
+    pub cred_id: Option<usize>,
+}
+
+impl InterworkingAp {
+    /// Parse the body of an `INTERWORKING-AP <bssid> type=<type> id=<id> ...`
+    /// event.
+    pub(crate) fn parse(body: &str) -> Option<Self> {
+        let mut words = body.split_whitespace();
+        let bssid = words.next()?.parse().ok()?;
+        let mut ap = InterworkingAp {
+            bssid,
+            matched: InterworkingMatch::Unknown,
+            cred_id: None,
+        };
+        for (key, value) in words.filter_map(|word| word.split_once('=')) {
+            match key {
+                "type" => {
+                    ap.matched = match value {
+                        "home" => InterworkingMatch::Home,
+                        "roaming" => InterworkingMatch::Roaming,
+                        _ => InterworkingMatch::Unknown,
+                    }
+                }
+                "id" => ap.cred_id = value.parse().ok(),
+                _ => (),
+            }
+        }
+        Some(ap)
+    }
+}
+
+/// An ANQP element received from an access point, from `RX-ANQP` or
+/// `RX-HS20-ANQP`. The element itself is read from the BSS table, e.g. with a
+/// custom `BSS <bssid>` request.
+#[derive(Debug, Clone)]
+pub struct AnqpInfo {
+    pub bssid: Bssid,
+    /// The element's name, e.g. `Venue Name` or `Operator Friendly Name`.
+    /// wpa_supplicant names a few elements with an `ANQP ` prefix, such as
+    /// `ANQP Capability list`; it is left off here, giving `Capability list`.
+    pub info: String,
+}
+
+impl AnqpInfo {
+    /// Parse the body of an `RX-ANQP <bssid> <info>` event.
+    pub(crate) fn parse(body: &str) -> Option<Self> {
+        let (bssid, info) = body.split_once(' ')?;
+        let info = info.trim_end();
+        Some(AnqpInfo {
+            bssid: bssid.parse().ok()?,
+            info: info.strip_prefix("ANQP ").unwrap_or(info).to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn credential_fields_are_encoded_and_validated() {
+        let cred = Credential {
+            realm: Some("example.com".into()),
+            domain: vec!["example.com".into(), "example.net".into()],
+            roaming_consortium: Some("223344".into()),
+            password: Some("pass word".into()),
+            eap: Some("TTLS".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            cred.to_fields().unwrap(),
+            [
+                ("realm", "\"example.com\"".to_string()),
+                ("password", hex::encode("pass word")),
+                ("domain", "\"example.com\"".to_string()),
+                ("domain", "\"example.net\"".to_string()),
+                ("roaming_consortium", "223344".to_string()),
+                ("eap", "TTLS".to_string()),
+            ]
+        );
+        let bad = Credential {
+            eap: Some("TTLS priority 9".into()),
+            ..Default::default()
+        };
+        assert!(bad.to_fields().is_err());
+    }
+
+    #[test]
+    fn interworking_events_are_parsed() {
+        let ap = InterworkingAp::parse("02:00:00:00:01:00 type=home id=1 priority=0 sp_priority=0")
+            .unwrap();
+        assert_eq!(ap.bssid.to_string(), "02:00:00:00:01:00");
+        assert_eq!(ap.matched, InterworkingMatch::Home);
+        assert_eq!(ap.cred_id, Some(1));
+        let (_, event) =
+            EventSocket::parse("<3>RX-HS20-ANQP 02:00:00:00:01:00 Operator Friendly Name");
+        assert!(matches!(event, Event::Hs20Anqp(info) if info.info == "Operator Friendly Name"));
+        let (_, event) = EventSocket::parse("<3>RX-ANQP 02:00:00:00:01:00 Venue Name");
+        assert!(matches!(event, Event::Anqp(info) if info.info == "Venue Name"));
+        let (_, event) = EventSocket::parse("<3>RX-ANQP 02:00:00:00:01:00 ANQP Capability list");
+        assert!(matches!(event, Event::Anqp(info) if info.info == "Capability list"));
+    }
+
+    #[test]
+    fn creds_are_listed() {
+        let creds = CredentialEntry::vec_from_str(
+            "cred id / realm / username / domain / imsi\n0\texample.com\tuser\texample.com\t\n",
+        )
+        .unwrap();
+        assert_eq!(creds[0].id, 0);
+        assert_eq!(creds[0].username, "user");
+        assert_eq!(creds[0].imsi, "");
+    }
+}
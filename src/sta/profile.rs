@@ -0,0 +1,229 @@
+use super::*;
+use crate::config::{ConfBlock, ConfEntry, ConfLine, ConfValue, ConfigError};
+
+/// A station network configuration, as stored in a `network={ ... }` block of
+/// `wpa_supplicant.conf`.
+///
+/// The fields the crate understands are typed; everything else in the block is
+/// kept in [`NetworkProfile::extra`] so converting a block to a profile and
+/// back loses nothing but comments.
+///
+/// ```
+/// use wifi_ctrl::config::SupplicantConf;
+/// use wifi_ctrl::sta::{KeyMgmt, NetworkProfile};
+///
+/// let conf: SupplicantConf = "network={\n\tssid=\"home\"\n\tpsk=\"password123\"\n}\n".parse()?;
+/// let profile = NetworkProfile::try_from(conf.networks().next().unwrap())?;
+/// assert_eq!(profile.ssid, "home");
+///
+/// let mut guest = NetworkProfile::new("guest wifi");
+/// guest.key_mgmt = Some(KeyMgmt::None);
+/// let mut conf = SupplicantConf::default();
+/// conf.push_network((&guest).into());
+/// assert_eq!(conf.to_string(), "network={\n\tssid=67756573742077696669\n\tkey_mgmt=NONE\n}\n");
+/// # Ok::<(), wifi_ctrl::config::ConfigError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct NetworkProfile {
+    pub ssid: String,
+    pub bssid: Option<Bssid>,
+    pub psk: Option<Psk>,
+    pub key_mgmt: Option<KeyMgmt>,
+    pub mode: Option<NetworkMode>,
+    /// Frequency in MHz of a network we start, such as a mesh
+    pub frequency: Option<u32>,
+    /// Probe for the network by SSID (`scan_ssid=1`), since a hidden network
+    /// doesn't announce it in its beacons
+    pub scan_ssid: bool,
+    /// Fields without a typed representation, in file order
+    pub extra: Vec<ConfEntry>,
+}
+
+impl NetworkProfile {
+    pub fn new(ssid: impl Into<String>) -> Self {
+        Self {
+            ssid: ssid.into(),
+            bssid: None,
+            psk: None,
+            key_mgmt: None,
+            mode: None,
+            frequency: None,
+            scan_ssid: false,
+            extra: Vec::new(),
+        }
+    }
+
+    /// An 802.11s mesh (`mode=5`) on `frequency` MHz, secured with SAE using
+    /// `password`. SAE can't use a raw PSK, so the password is checked as a
+    /// [`Psk::passphrase`]. Once added to the mesh interface, start or join
+    /// it with [`RequestClient::mesh_group_add`].
+    ///
+    /// ```
+    /// use wifi_ctrl::config::SupplicantConf;
+    /// use wifi_ctrl::sta::NetworkProfile;
+    ///
+    /// let mesh = NetworkProfile::mesh("backhaul", "mesh secret", 5180)?;
+    /// let mut conf = SupplicantConf::default();
+    /// conf.push_network((&mesh).into());
+    /// assert_eq!(
+    ///     conf.to_string(),
+    ///     "network={\n\tssid=\"backhaul\"\n\tpsk=\"mesh secret\"\n\tkey_mgmt=SAE\n\tmode=5\n\tfrequency=5180\n}\n"
+    /// );
+    /// # Ok::<(), wifi_ctrl::error::ClientError>(())
+    /// ```
+    pub fn mesh(ssid: impl Into<String>, password: &str, frequency: u32) -> Result<Self> {
+        Ok(Self {
+            psk: Some(Psk::passphrase(password)?),
+            key_mgmt: Some(KeyMgmt::Sae),
+            mode: Some(NetworkMode::Mesh),
+            frequency: Some(frequency),
+            ..Self::new(ssid)
+        })
+    }
+
+    /// Mark the network as hidden, setting [`Self::scan_ssid`]. Selecting it
+    /// with [`RequestClient::select_network`] then makes a scan directed at
+    /// its SSID before reporting [`SelectResult::NotFound`].
+    ///
+    /// ```
+    /// use wifi_ctrl::config::SupplicantConf;
+    /// use wifi_ctrl::sta::NetworkProfile;
+    ///
+    /// let hidden = NetworkProfile::new("lab").hidden();
+    /// let mut conf = SupplicantConf::default();
+    /// conf.push_network((&hidden).into());
+    /// assert_eq!(conf.to_string(), "network={\n\tssid=\"lab\"\n\tscan_ssid=1\n}\n");
+    /// ```
+    pub fn hidden(mut self) -> Self {
+        self.scan_ssid = true;
+        self
+    }
+}
+
+impl TryFrom<&ConfBlock> for NetworkProfile {
+    type Error = ConfigError;
+
+    fn try_from(block: &ConfBlock) -> std::result::Result<Self, ConfigError> {
+        let invalid = |field: &str| ConfigError::InvalidField(field.to_string());
+        let mut ssid = None;
+        let mut profile = NetworkProfile::new("");
+        for entry in block.entries() {
+            let ConfEntry { key, value, .. } = entry;
+            match (key.as_str(), value) {
+                ("ssid", _) => ssid = Some(value.decode_string()?),
+                ("bssid", ConfValue::Raw(bssid)) => {
+                    profile.bssid = Some(bssid.parse().map_err(|_| invalid("bssid"))?)
+                }
+                ("psk", ConfValue::Quoted(passphrase)) => {
+                    profile.psk = Some(Psk::passphrase(passphrase).map_err(|_| invalid("psk"))?)
+                }
+                ("psk", ConfValue::Raw(key)) if key.len() == 64 => {
+                    profile.psk = Some(key.parse().map_err(|_| invalid("psk"))?)
+                }
+                ("key_mgmt", ConfValue::Raw(mgmt)) if KeyMgmt::from_conf(mgmt).is_some() => {
+                    profile.key_mgmt = KeyMgmt::from_conf(mgmt)
+                }
+                ("mode", ConfValue::Raw(mode)) if NetworkMode::from_conf(mode).is_some() => {
+                    profile.mode = NetworkMode::from_conf(mode)
+                }
+                ("frequency", ConfValue::Raw(freq)) => {
+                    profile.frequency = Some(freq.parse().map_err(|_| invalid("frequency"))?)
+                }
+                ("scan_ssid", ConfValue::Raw(scan)) if scan == "1" => profile.scan_ssid = true,
+                _ => profile.extra.push(entry.clone()),
+            }
+        }
+        profile.ssid = ssid.ok_or_else(|| invalid("ssid"))?;
+        Ok(profile)
+    }
+}
+
+impl From<&NetworkProfile> for ConfBlock {
+    fn from(profile: &NetworkProfile) -> Self {
+        let mut block = ConfBlock::default();
+        block.set("ssid", ConfValue::escaped(&profile.ssid));
+        if let Some(bssid) = profile.bssid {
+            block.set("bssid", ConfValue::Raw(bssid.to_string()));
+        }
+        if let Some(psk) = &profile.psk {
+            let field = psk.to_field();
+            block.set(
+                "psk",
+                match field.strip_prefix('"').and_then(|f| f.strip_suffix('"')) {
+                    Some(passphrase) => ConfValue::Quoted(passphrase.to_string()),
+                    None => ConfValue::Raw(field),
+                },
+            );
+        }
+        if let Some(mgmt) = &profile.key_mgmt {
+            block.set("key_mgmt", ConfValue::Raw(mgmt.to_string()));
+        }
+        if let Some(mode) = profile.mode {
+            block.set("mode", ConfValue::Raw(mode.to_string()));
+        }
+        if let Some(freq) = profile.frequency {
+            block.set("frequency", ConfValue::Raw(freq.to_string()));
+        }
+        if profile.scan_ssid {
+            block.set("scan_ssid", ConfValue::Raw("1".into()));
+        }
+        block
+            .lines
+            .extend(profile.extra.iter().cloned().map(ConfLine::Entry));
+        block
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SupplicantConf;
+
+    #[test]
+    fn block_round_trips_through_profile() {
+        let conf: SupplicantConf = "network={
+	ssid=6d7920686f6d65
+	bssid=CC:7B:5C:1A:D2:21
+	psk=8dbbe42cb44f21088fbb9cfbf24dc9b39787d6026d436b01b3ac7d34afb4416d
+	key_mgmt=WPA-PSK
+	priority=5
+}
+"
+        .parse()
+        .unwrap();
+        let profile = NetworkProfile::try_from(conf.networks().next().unwrap()).unwrap();
+        assert_eq!(profile.ssid, "my home");
+        assert_eq!(profile.bssid.unwrap().to_string(), "cc:7b:5c:1a:d2:21");
+        assert!(matches!(profile.key_mgmt, Some(KeyMgmt::WpaPsk)));
+        assert_eq!(profile.extra.len(), 1);
+
+        let block = ConfBlock::from(&profile);
+        assert_eq!(
+            block.get("psk"),
+            Some(&ConfValue::Raw(
+                "8dbbe42cb44f21088fbb9cfbf24dc9b39787d6026d436b01b3ac7d34afb4416d".into()
+            ))
+        );
+        assert_eq!(block.get("priority"), Some(&ConfValue::Raw("5".into())));
+    }
+
+    #[test]
+    fn untyped_key_mgmt_is_kept_verbatim() {
+        let mut block = ConfBlock::default();
+        block.set("ssid", ConfValue::Quoted("home".into()));
+        block.set("key_mgmt", ConfValue::Raw("SAE WPA-PSK".into()));
+        let profile = NetworkProfile::try_from(&block).unwrap();
+        assert!(profile.key_mgmt.is_none());
+        assert_eq!(ConfBlock::from(&profile), block);
+    }
+
+    #[test]
+    fn block_without_ssid_is_rejected() {
+        let mut block = ConfBlock::default();
+        block.set("key_mgmt", ConfValue::Raw("NONE".into()));
+        assert_eq!(
+            NetworkProfile::try_from(&block).unwrap_err(),
+            ConfigError::InvalidField("ssid".into())
+        );
+    }
+}
@@ -3,10 +3,21 @@ use super::*;
 /// Default time to wait for a reply to a control command/request before giving
 /// up. Chosen to comfortably cover slower wpa_supplicant operations while still
 /// unblocking the single-task runtime if a reply never arrives.
-const DEFAULT_COMMAND_TIMEOUT: Duration = Duration::from_secs(3);
+pub(crate) const DEFAULT_COMMAND_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Default number of attempts at each `ATTACH`/`LEVEL` handshake command. With
+/// [`DEFAULT_ATTACH_RETRY_DELAY`] between tries this bounds the wait to roughly
+/// a minute, unlike the socket-open path which retries for 5 minutes.
+pub(crate) const DEFAULT_ATTACH_RETRIES: usize = 240;
+/// Default delay between attach handshake attempts.
+pub(crate) const DEFAULT_ATTACH_RETRY_DELAY: Duration = Duration::from_millis(250);
+
+/// Default time a select waits for its outcome before resolving with
+/// [`SelectResult::Timeout`].
+pub(crate) const DEFAULT_SELECT_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// Default capacity of the request and broadcast channels.
-const DEFAULT_CHANNEL_SIZE: usize = 32;
+pub(crate) const DEFAULT_CHANNEL_SIZE: usize = 32;
 
 /// Setup struct for the WiFi Station process.
 pub struct WifiSetup {
@@ -32,34 +43,95 @@ impl WifiSetup {
 
         Self {
             wifi: WifiStation {
-                socket_path: PATH_DEFAULT_SERVER.into(),
+                control_interface: ControlInterface::Unix(PATH_DEFAULT_SERVER.into()),
                 request_receiver,
                 broadcast_sender,
                 self_sender,
-                select_timeout: Duration::from_secs(10),
+                select_timeout: DEFAULT_SELECT_TIMEOUT,
+                select_policy: SelectPolicy::default(),
                 command_timeout: DEFAULT_COMMAND_TIMEOUT,
+                keepalive_interval: None,
+                attach_options: vec![],
+                attach_level: None,
+                attach_retries: DEFAULT_ATTACH_RETRIES,
+                attach_retry_delay: DEFAULT_ATTACH_RETRY_DELAY,
             },
             request_client,
         }
     }
 
     pub fn set_socket_path<S: Into<std::path::PathBuf>>(&mut self, path: S) {
-        self.wifi.socket_path = path.into();
+        self.wifi.control_interface = ControlInterface::Unix(path.into());
+    }
+
+    /// Connect through `control_interface` instead of the default Unix
+    /// socket, e.g. `ControlInterface::Udp` for a wpa_supplicant built with
+    /// `CONFIG_CTRL_IFACE=udp` (listening on `127.0.0.1:9877` by default).
+    pub fn set_control_interface(&mut self, control_interface: ControlInterface) {
+        self.wifi.control_interface = control_interface;
     }
 
     pub fn set_select_timeout(&mut self, timeout: Duration) {
         self.wifi.select_timeout = timeout;
     }
 
+    /// Choose what happens to a select made while another is pending; see
+    /// [`SelectPolicy`]. Defaults to [`SelectPolicy::Reject`].
+    pub fn set_select_policy(&mut self, policy: SelectPolicy) {
+        self.wifi.select_policy = policy;
+    }
+
     /// Set how long to wait for a reply to a control command/request before
     /// giving up with [`ClientError::Timeout`](crate::error::ClientError::Timeout).
     pub fn set_command_timeout(&mut self, timeout: Duration) {
         self.wifi.command_timeout = timeout;
     }
 
+    /// Options to pass to `ATTACH`, such as `probe_rx_events=1`.
+    pub fn add_attach_options(&mut self, options: &[&str]) {
+        for o in options {
+            self.wifi.attach_options.push(o.to_string());
+        }
+    }
+
+    /// Only deliver events at or above `level` (wpa_supplicant's debug
+    /// levels: 2 for `MSG_DEBUG`, 3 for `MSG_INFO`, the default, 4 for
+    /// `MSG_WARNING`), set with `LEVEL <level>` after attaching.
+    pub fn set_attach_level(&mut self, level: u8) {
+        self.wifi.attach_level = Some(level);
+    }
+
+    /// Set how many times to retry each wpa_supplicant attach handshake
+    /// command before giving up with
+    /// [`SocketError::AttachFailed`](crate::error::SocketError::AttachFailed).
+    pub fn set_attach_retries(&mut self, retries: usize) {
+        self.wifi.attach_retries = retries;
+    }
+
+    /// Set how long to wait between attach handshake attempts.
+    pub fn set_attach_retry_delay(&mut self, delay: Duration) {
+        self.wifi.attach_retry_delay = delay;
+    }
+
+    /// Periodically `PING` the event socket and `DETACH` and re-attach it if
+    /// the daemon stops answering, broadcasting [`Broadcast::Reattached`].
+    /// The daemon still answers a monitor it dropped after failing to
+    /// deliver events to it, so this recovers from a stalled or restarted
+    /// daemon, not from that. Off by default.
+    pub fn set_keepalive_interval(&mut self, interval: Duration) {
+        self.wifi.keepalive_interval = Some(interval);
+    }
+
     pub fn get_broadcast_receiver(&self) -> BroadcastReceiver {
         self.wifi.broadcast_sender.subscribe()
     }
+
+    /// Like [`Self::get_broadcast_receiver`], as a [`Stream`](tokio_stream::Stream)
+    /// that can be filtered and reports lag as [`Broadcast::Lagged`].
+    pub fn event_stream(&self) -> EventStream {
+        EventStream::new(self.wifi.broadcast_sender.subscribe())
+    }
+
     pub fn get_request_client(&self) -> RequestClient {
         self.request_client.clone()
     }
@@ -1,9 +1,10 @@
 use super::{ParseResult, SocketResult};
 use super::{Result, SocketHandle, config, config::unprintf, warn};
 use crate::error::ClientError;
+use crate::{CountryCode, MacAddr};
 
 use serde::Serialize;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Display;
 use std::str::FromStr;
 use std::sync::Arc;
@@ -11,7 +12,7 @@ use std::sync::Arc;
 #[derive(Serialize, Debug, Clone)]
 /// The result from scanning for networks.
 pub struct ScanResult {
-    pub mac: String,
+    pub mac: MacAddr,
     pub frequency: String,
     pub signal: isize,
     pub flags: String,
@@ -27,7 +28,7 @@ impl ScanResult {
         let (flags, escaped_name) = rest.split_once('\t')?;
         let name = unprintf(escaped_name).ok()?;
         Some(ScanResult {
-            mac: mac.to_string(),
+            mac: mac.parse().ok()?,
             frequency: frequency.to_string(),
             signal,
             flags: flags.to_string(),
@@ -44,7 +45,7 @@ impl ScanResult {
     ///00:5f:67:90:da:64	2417	-35	[WPA-PSK-CCMP][WPA2-PSK-CCMP][ESS]	TP-Link DA64
     ///e0:91:f5:7d:11:c0	2462	-33	[WPA2-PSK-CCMP][WPS][ESS]	¯\\_(\xe3\x83\x84)_/¯
     ///"#).unwrap();
-    ///assert_eq!(results[0].mac, "00:5f:67:90:da:64");
+    ///assert_eq!(results[0].mac.to_string(), "00:5f:67:90:da:64");
     ///assert_eq!(results[0].name, "TP-Link DA64");
     ///assert_eq!(results[1].signal, -33);
     ///assert_eq!(results[1].name, r#"¯\_(ツ)_/¯"#);
@@ -73,12 +74,93 @@ pub struct NetworkResult {
     pub flags: String,
 }
 
-fn parse_get_network(resp: &str) -> ParseResult<String> {
+pub(crate) fn parse_get_network(resp: &str) -> ParseResult<String> {
     let escaped = resp.trim_matches('\"');
     Ok(unprintf(escaped)?)
 }
 
+/// The reply to `GET_NETWORK <id> scan_ssid`: whether the network is hidden.
+pub(crate) fn parse_scan_ssid(resp: &str) -> ParseResult<bool> {
+    match resp.trim() {
+        "0" => Ok(false),
+        "1" => Ok(true),
+        // a failure such as `FAIL` is classified by the caller
+        _ => Err(crate::error::ParseError::NotOK),
+    }
+}
+
+/// The reply to `GET_NETWORK <id> ssid` as the hex `SCAN ssid` takes. Only
+/// printable ASCII comes back quoted; any other SSID, e.g. UTF-8, already
+/// comes back as bare hex.
+pub(crate) fn parse_ssid_hex(resp: &str) -> ParseResult<String> {
+    let resp = resp.trim();
+    match resp
+        .strip_prefix('"')
+        .and_then(|ssid| ssid.strip_suffix('"'))
+    {
+        Some(escaped) => Ok(hex::encode(unprintf(escaped)?)),
+        None if !resp.is_empty()
+            && resp.len() % 2 == 0
+            && resp.bytes().all(|b| b.is_ascii_hexdigit()) =>
+        {
+            Ok(resp.to_ascii_lowercase())
+        }
+        // a failure such as `FAIL` is classified by the caller
+        None => Err(crate::error::ParseError::NotOK),
+    }
+}
+
+/// A scan that probes for the SSID, given in hex, by name, finding it even
+/// if hidden.
+pub(crate) fn directed_scan_command(ssid_hex: &str) -> String {
+    format!("SCAN ssid {ssid_hex}")
+}
+
+/// The outcome of looking for a network by SSID before reporting it not
+/// found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DirectedProbe {
+    /// The network isn't hidden, so there's nothing more to look for
+    NotHidden,
+    Started,
+    /// A scan was already under way (`FAIL-BUSY`); try again once it ends
+    Busy,
+}
+
+impl DirectedProbe {
+    /// Classify the reply to [`directed_scan_command`].
+    pub(crate) fn from_scan(scan: Result) -> Result<Self> {
+        match scan {
+            Ok(()) => Ok(DirectedProbe::Started),
+            Err(ClientError::Daemon {
+                error: crate::error::DaemonError::Busy,
+                ..
+            }) => Ok(DirectedProbe::Busy),
+            Err(e) => Err(e),
+        }
+    }
+}
+
 impl NetworkResult {
+    /// Network ids and flags from a `LIST_NETWORKS` response; the SSIDs are
+    /// fetched separately as the listing truncates them.
+    pub(crate) fn parse_list(response: &str) -> Vec<(usize, String)> {
+        let mut networks = Vec::new();
+        for line in response.split('\n').skip(1) {
+            let mut line_split = line.split_whitespace();
+            if let Some(network_id) = line_split.next() {
+                if let Ok(network_id) = usize::from_str(network_id) {
+                    if let Some(flags) = line_split.last() {
+                        networks.push((network_id, flags.into()));
+                    }
+                } else {
+                    warn!("Invalid network_id: {network_id}")
+                }
+            }
+        }
+        networks
+    }
+
     pub(crate) async fn request_results<const N: usize>(
         socket_handle: &mut SocketHandle<N>,
     ) -> SocketResult<Result<Vec<NetworkResult>>> {
@@ -90,29 +172,19 @@ impl NetworkResult {
             Err(e) => return Ok(Err(e)),
         };
         let mut results = Vec::new();
-        let split = response.split('\n').skip(1);
-        for line in split {
-            let mut line_split = line.split_whitespace();
-            if let Some(network_id) = line_split.next() {
-                if let Ok(network_id) = usize::from_str(network_id) {
-                    let ssid = match socket_handle
-                        .request(&format!("GET_NETWORK {network_id} ssid"), parse_get_network)
-                        .await?
-                    {
-                        Ok(x) => x,
-                        Err(e) => return Ok(Err(e)),
-                    };
-                    if let Some(flags) = line_split.last() {
-                        results.push(NetworkResult {
-                            flags: flags.into(),
-                            ssid,
-                            network_id,
-                        })
-                    }
-                } else {
-                    warn!("Invalid network_id: {network_id}")
-                }
-            }
+        for (network_id, flags) in Self::parse_list(&response) {
+            let ssid = match socket_handle
+                .request(&format!("GET_NETWORK {network_id} ssid"), parse_get_network)
+                .await?
+            {
+                Ok(x) => x,
+                Err(e) => return Ok(Err(e)),
+            };
+            results.push(NetworkResult {
+                flags,
+                ssid,
+                network_id,
+            })
         }
         Ok(Ok(results))
     }
@@ -129,10 +201,12 @@ impl NetworkResult {
 pub struct Status {
     pub wpa_state: Option<String>,
     pub ssid: Option<String>,
-    pub bssid: Option<String>,
+    pub bssid: Option<Bssid>,
     pub id: Option<usize>,
     pub freq: Option<u32>,
-    pub address: Option<String>,
+    /// The MAC address we're using, which differs from the device's own
+    /// under a [`MacRandomization`] policy
+    pub address: Option<MacAddr>,
     pub ip_address: Option<String>,
     pub key_mgmt: Option<String>,
     pub mode: Option<String>,
@@ -146,6 +220,44 @@ impl Status {
     pub fn get(&self, key: &str) -> Option<&str> {
         self.raw.get(key).map(String::as_str)
     }
+
+    /// Encode as wpa_supplicant would send it, e.g. for test fixtures. The
+    /// typed fields take precedence over [`Status::raw`]; setting one to
+    /// `None` leaves the key out.
+    pub fn to_response(&self) -> std::result::Result<String, config::ConfigError> {
+        let mut fields: BTreeMap<&str, String> = self
+            .raw
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.clone()))
+            .collect();
+        let typed = [
+            ("wpa_state", self.wpa_state.clone()),
+            ("ssid", self.ssid.clone()),
+            ("bssid", self.bssid.map(|bssid| bssid.to_string())),
+            ("id", self.id.map(|id| id.to_string())),
+            ("freq", self.freq.map(|freq| freq.to_string())),
+            ("address", self.address.map(|address| address.to_string())),
+            ("ip_address", self.ip_address.clone()),
+            ("key_mgmt", self.key_mgmt.clone()),
+            ("mode", self.mode.clone()),
+        ];
+        for (key, value) in typed {
+            match value {
+                Some(value) => fields.insert(key, value),
+                None => fields.remove(key),
+            };
+        }
+        config::to_string(&fields)
+    }
+}
+
+/// The reply to `GET country`.
+pub(crate) fn parse_country(reply: &str) -> ParseResult<CountryCode> {
+    // a failure such as `FAIL` is classified by the caller
+    reply
+        .trim()
+        .parse()
+        .map_err(|_| crate::error::ParseError::NotOK)
 }
 
 pub(crate) fn parse_status(response: &str) -> ParseResult<Status> {
@@ -153,10 +265,10 @@ pub(crate) fn parse_status(response: &str) -> ParseResult<Status> {
     Ok(Status {
         wpa_state: raw.get("wpa_state").cloned(),
         ssid: raw.get("ssid").cloned(),
-        bssid: raw.get("bssid").cloned(),
+        bssid: raw.get("bssid").and_then(|v| v.parse().ok()),
         id: raw.get("id").and_then(|v| v.parse().ok()),
         freq: raw.get("freq").and_then(|v| v.parse().ok()),
-        address: raw.get("address").cloned(),
+        address: raw.get("address").and_then(|v| v.parse().ok()),
         ip_address: raw.get("ip_address").cloned(),
         key_mgmt: raw.get("key_mgmt").cloned(),
         mode: raw.get("mode").cloned(),
@@ -164,7 +276,7 @@ pub(crate) fn parse_status(response: &str) -> ParseResult<Status> {
     })
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 /// Key management types for WiFi networks (eg: WPA-PSK, WPA-EAP, etc). In theory, more than one may
 /// be configured, but I believe `wpa_supplicant` defaults to all of them if omitted. Therefore, in
 /// practice, this is mostly important for setting `key_mgmt` to `None` for an open network.
@@ -173,6 +285,22 @@ pub enum KeyMgmt {
     WpaPsk,
     WpaEap,
     IEEE8021X,
+    /// WPA3-Personal, and the authentication of secured mesh networks
+    Sae,
+}
+
+impl KeyMgmt {
+    /// Parse a single `key_mgmt` value as written by [`Display`].
+    pub(crate) fn from_conf(s: &str) -> Option<Self> {
+        match s {
+            "NONE" => Some(KeyMgmt::None),
+            "WPA-PSK" => Some(KeyMgmt::WpaPsk),
+            "WPA-EAP" => Some(KeyMgmt::WpaEap),
+            "IEEE8021X" => Some(KeyMgmt::IEEE8021X),
+            "SAE" => Some(KeyMgmt::Sae),
+            _ => None,
+        }
+    }
 }
 
 impl Display for KeyMgmt {
@@ -182,11 +310,151 @@ impl Display for KeyMgmt {
             KeyMgmt::WpaPsk => "WPA-PSK".to_string(),
             KeyMgmt::WpaEap => "WPA-EAP".to_string(),
             KeyMgmt::IEEE8021X => "IEEE8021X".to_string(),
+            KeyMgmt::Sae => "SAE".to_string(),
         };
         write!(f, "{}", str)
     }
 }
 
+/// A network's operating mode, the `mode` field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkMode {
+    /// Connect to an access point, the default
+    Station,
+    /// Ad-hoc (IBSS)
+    Ibss,
+    /// Run an access point
+    Ap,
+    /// Join or start an 802.11s mesh, see
+    /// [`RequestClient::mesh_group_add`](super::RequestClient::mesh_group_add)
+    Mesh,
+}
+
+impl NetworkMode {
+    /// Parse a `mode` value as written by [`Display`].
+    pub(crate) fn from_conf(s: &str) -> Option<Self> {
+        match s {
+            "0" => Some(NetworkMode::Station),
+            "1" => Some(NetworkMode::Ibss),
+            "2" => Some(NetworkMode::Ap),
+            "5" => Some(NetworkMode::Mesh),
+            _ => None,
+        }
+    }
+}
+
+impl Display for NetworkMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mode = match self {
+            NetworkMode::Station => 0,
+            NetworkMode::Ibss => 1,
+            NetworkMode::Ap => 2,
+            NetworkMode::Mesh => 5,
+        };
+        write!(f, "{mode}")
+    }
+}
+
+/// Which MAC address wpa_supplicant presents, for privacy: the `mac_addr`
+/// policy of a network or the global default, and `preassoc_mac_addr` for
+/// scanning while disconnected. The address actually in use is reported in
+/// [`Status::address`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MacRandomization {
+    /// The device's own address, the default
+    Permanent,
+    /// A new random address for each connection, and for each scan while
+    /// disconnected
+    Random,
+    /// Like [`Self::Random`] but keeping the vendor [OUI](MacAddr::oui)
+    RandomKeepOui,
+    /// A random address generated once for the network and kept across
+    /// connections. Per network only.
+    Dedicated,
+}
+
+impl Display for MacRandomization {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let policy = match self {
+            MacRandomization::Permanent => 0,
+            MacRandomization::Random => 1,
+            MacRandomization::RandomKeepOui => 2,
+            MacRandomization::Dedicated => 3,
+        };
+        write!(f, "{policy}")
+    }
+}
+
+/// What prompted a [`RegdomChange`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegdomInitiator {
+    /// The kernel's own default
+    Core,
+    /// Userspace, e.g. [`RequestClient::set_country`](super::RequestClient::set_country)
+    User,
+    Driver,
+    /// The country an access point advertises in its beacons (802.11d)
+    CountryIe,
+    /// Channels enabled after seeing an access point beacon on them
+    BeaconHint,
+    Unknown,
+}
+
+/// The regulatory domain now in force.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Regdom {
+    /// The world domain, which only allows what's legal everywhere
+    World,
+    Country(CountryCode),
+    /// The intersection of two domains, e.g. ours and an access point's
+    Intersection,
+    Unknown,
+}
+
+/// A `CTRL-EVENT-REGDOM-CHANGE` event: the regulatory domain, and so the
+/// channels and transmit power allowed, changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegdomChange {
+    pub initiator: RegdomInitiator,
+    pub regdom: Regdom,
+}
+
+impl RegdomChange {
+    /// Parse the body of a `CTRL-EVENT-REGDOM-CHANGE init=<initiator>
+    /// type=<type> [alpha2=<country>]` event.
+    pub(crate) fn parse(body: &str) -> Option<Self> {
+        let mut initiator = RegdomInitiator::Unknown;
+        let mut kind = "UNKNOWN";
+        let mut alpha2 = None;
+        for (key, value) in body.split_whitespace().filter_map(|w| w.split_once('=')) {
+            match key {
+                "init" => {
+                    initiator = match value {
+                        "CORE" => RegdomInitiator::Core,
+                        "USER" => RegdomInitiator::User,
+                        "DRIVER" => RegdomInitiator::Driver,
+                        "COUNTRY_IE" => RegdomInitiator::CountryIe,
+                        "BEACON_HINT" => RegdomInitiator::BeaconHint,
+                        _ => RegdomInitiator::Unknown,
+                    }
+                }
+                "type" => kind = value,
+                // intersected and custom domains are reported as `98` and `99`,
+                // so it's the type that tells them apart
+                "alpha2" => alpha2 = value.parse().ok(),
+                _ => (),
+            }
+        }
+        let regdom = match (kind, alpha2) {
+            ("WORLD", _) => Regdom::World,
+            ("COUNTRY", Some(country)) => Regdom::Country(country),
+            ("INTERSECTION", _) => Regdom::Intersection,
+            _ => Regdom::Unknown,
+        };
+        Some(RegdomChange { initiator, regdom })
+    }
+}
+
 /// A WPA pre-shared key, validated at construction.
 ///
 /// wpa_supplicant takes the `psk` field in two distinct forms and encodes them
@@ -294,9 +562,8 @@ impl std::fmt::Debug for Psk {
 /// A BSSID (access-point MAC address).
 ///
 /// wpa_supplicant expects the `bssid` field raw and unquoted (a quoted MAC
-/// fails to parse). Parsing up front and re-emitting the canonical
-/// `xx:xx:xx:xx:xx:xx` form via [`Display`] means caller input is never echoed
-/// into an unquoted command position.
+/// fails to parse), so it's only ever sent in the canonical form [`MacAddr`]
+/// re-emits.
 ///
 /// ```
 /// use wifi_ctrl::sta::Bssid;
@@ -309,40 +576,146 @@ impl std::fmt::Debug for Psk {
 /// assert!("cc:7b:5c:1a:d2".parse::<Bssid>().is_err());
 /// # Ok::<(), wifi_ctrl::error::ClientError>(())
 /// ```
+pub type Bssid = MacAddr;
+
+/// A per-network background scan module, set with
+/// [`RequestClient::set_network_bgscan`](super::RequestClient::set_network_bgscan).
+/// It rescans every `short_interval` seconds while the signal is weaker than
+/// `signal_threshold` (dBm), and every `long_interval` seconds otherwise.
+///
+/// ```
+/// use wifi_ctrl::sta::Bgscan;
+///
+/// assert_eq!(Bgscan::simple(30, -70, 3600)?.to_string(), "simple:30:-70:3600");
+/// assert!(Bgscan::simple(3600, -70, 30).is_err());
+/// # Ok::<(), wifi_ctrl::error::ClientError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Bgscan {
+    Simple(BgscanIntervals),
+    /// Like `simple`, also learning which channels neighbouring access points
+    /// use, optionally kept in a database file across restarts
+    Learn(BgscanIntervals, Option<String>),
+    /// No background scanning for this network, overriding the global
+    /// `bgscan` setting
+    Disabled,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct Bssid([u8; 6]);
+pub struct BgscanIntervals {
+    short_interval: u32,
+    signal_threshold: i32,
+    long_interval: u32,
+}
 
-impl From<[u8; 6]> for Bssid {
-    fn from(mac: [u8; 6]) -> Self {
-        Bssid(mac)
+impl BgscanIntervals {
+    fn new(short_interval: u32, signal_threshold: i32, long_interval: u32) -> Result<Self> {
+        if short_interval == 0 || long_interval < short_interval || signal_threshold > 0 {
+            return Err(ClientError::InvalidParameter("bgscan"));
+        }
+        Ok(Self {
+            short_interval,
+            signal_threshold,
+            long_interval,
+        })
     }
 }
 
-impl FromStr for Bssid {
-    type Err = ClientError;
+impl Display for BgscanIntervals {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}",
+            self.short_interval, self.signal_threshold, self.long_interval
+        )
+    }
+}
 
-    fn from_str(s: &str) -> Result<Self> {
-        let mut mac = [0u8; 6];
-        let mut octets = s.split(':');
-        for byte in mac.iter_mut() {
-            let octet = octets.next().ok_or(ClientError::InvalidBssid)?;
-            // from_str_radix alone would admit signs and whitespace
-            if octet.len() != 2 || !octet.bytes().all(|b| b.is_ascii_hexdigit()) {
-                return Err(ClientError::InvalidBssid);
+impl Bgscan {
+    /// `short_interval` must be non-zero and at most `long_interval`, and
+    /// `signal_threshold` a dBm value, so at most 0.
+    pub fn simple(short_interval: u32, signal_threshold: i32, long_interval: u32) -> Result<Self> {
+        BgscanIntervals::new(short_interval, signal_threshold, long_interval).map(Bgscan::Simple)
+    }
+
+    /// Like [`Self::simple`]; `database` is a file path, which can't contain
+    /// `:`, quotes or control characters.
+    pub fn learn(
+        short_interval: u32,
+        signal_threshold: i32,
+        long_interval: u32,
+        database: Option<&str>,
+    ) -> Result<Self> {
+        let intervals = BgscanIntervals::new(short_interval, signal_threshold, long_interval)?;
+        if let Some(database) = database {
+            if database.is_empty()
+                || !database
+                    .bytes()
+                    .all(|b| b.is_ascii_graphic() && b != b':' && b != b'"')
+            {
+                return Err(ClientError::InvalidParameter("bgscan"));
             }
-            *byte = u8::from_str_radix(octet, 16).map_err(|_| ClientError::InvalidBssid)?;
         }
-        if octets.next().is_some() {
-            return Err(ClientError::InvalidBssid);
+        Ok(Bgscan::Learn(intervals, database.map(String::from)))
+    }
+}
+
+impl Display for Bgscan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Bgscan::Simple(intervals) => write!(f, "simple:{intervals}"),
+            Bgscan::Learn(intervals, None) => write!(f, "learn:{intervals}"),
+            Bgscan::Learn(intervals, Some(database)) => write!(f, "learn:{intervals}:{database}"),
+            Bgscan::Disabled => Ok(()),
+        }
+    }
+}
+
+/// How wpa_supplicant rescans while it isn't connected, set with
+/// [`RequestClient::set_autoscan`](super::RequestClient::set_autoscan).
+///
+/// ```
+/// use wifi_ctrl::sta::Autoscan;
+///
+/// assert_eq!(Autoscan::exponential(3, 300)?.to_string(), "exponential:3:300");
+/// assert_eq!(Autoscan::periodic(30)?.to_string(), "periodic:30");
+/// assert!(Autoscan::periodic(0).is_err());
+/// # Ok::<(), wifi_ctrl::error::ClientError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Autoscan(AutoscanInner);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AutoscanInner {
+    Exponential { base: u32, limit: u32 },
+    Periodic { interval: u32 },
+}
+
+impl Autoscan {
+    /// Wait `base`, then `base^2`, ... seconds between scans, up to `limit`.
+    /// `base` must be at least 2 and `limit` at least `base`.
+    pub fn exponential(base: u32, limit: u32) -> Result<Self> {
+        if base < 2 || limit < base {
+            return Err(ClientError::InvalidParameter("autoscan"));
+        }
+        Ok(Autoscan(AutoscanInner::Exponential { base, limit }))
+    }
+
+    /// Scan every `interval` seconds.
+    pub fn periodic(interval: u32) -> Result<Self> {
+        if interval == 0 {
+            return Err(ClientError::InvalidParameter("autoscan"));
         }
-        Ok(Bssid(mac))
+        Ok(Autoscan(AutoscanInner::Periodic { interval }))
     }
 }
 
-impl Display for Bssid {
+impl Display for Autoscan {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let [a, b, c, d, e, g] = self.0;
-        write!(f, "{a:02x}:{b:02x}:{c:02x}:{d:02x}:{e:02x}:{g:02x}")
+        match self.0 {
+            AutoscanInner::Exponential { base, limit } => write!(f, "exponential:{base}:{limit}"),
+            AutoscanInner::Periodic { interval } => write!(f, "periodic:{interval}"),
+        }
     }
 }
 
@@ -368,12 +741,115 @@ some_future_key=42";
         assert_eq!(status.id, Some(3));
         assert_eq!(status.freq, Some(2412));
         assert_eq!(status.ip_address.as_deref(), Some("192.168.1.42"));
+        assert!(status.address.unwrap().is_locally_administered());
         // key_mgmt absent from the response -> None, not a parse failure
         assert_eq!(status.key_mgmt, None);
         // unknown keys are preserved via the raw escape hatch
         assert_eq!(status.get("some_future_key"), Some("42"));
     }
 
+    #[test]
+    fn status_to_response_prefers_typed_fields() {
+        let mut status = parse_status("wpa_state=SCANNING\nid=1\nsome_future_key=42").unwrap();
+        status.wpa_state = Some("COMPLETED".into());
+        status.ssid = Some("my network".into());
+        status.id = None;
+        let resp = status.to_response().unwrap();
+        assert_eq!(
+            resp,
+            "some_future_key=42\nssid=my network\nwpa_state=COMPLETED\n"
+        );
+        let reparsed = parse_status(&resp).unwrap();
+        assert_eq!(reparsed.ssid.as_deref(), Some("my network"));
+        assert_eq!(reparsed.id, None);
+    }
+
+    #[test]
+    fn bgscan_is_validated() {
+        assert_eq!(
+            Bgscan::learn(30, -70, 3600, Some("/var/lib/bgscan.db"))
+                .unwrap()
+                .to_string(),
+            "learn:30:-70:3600:/var/lib/bgscan.db"
+        );
+        assert!(Bgscan::simple(0, -70, 3600).is_err());
+        assert!(Bgscan::simple(30, 10, 3600).is_err());
+        assert!(Bgscan::learn(30, -70, 3600, Some("a:b")).is_err());
+        let set = crate::sta::SetNetwork::Bgscan(Bgscan::Disabled);
+        assert_eq!(set.to_command(2), "SET_NETWORK 2 bgscan \"\"");
+    }
+
+    #[test]
+    fn ssid_is_hex_encoded_once_for_a_directed_scan() {
+        assert_eq!(parse_ssid_hex("\"lab\"").unwrap(), "6c6162");
+        assert_eq!(parse_ssid_hex("\"a\\\"b\"").unwrap(), "612262");
+        assert_eq!(parse_ssid_hex("636166C3A9").unwrap(), "636166c3a9");
+        assert!(parse_ssid_hex("FAIL").is_err());
+        assert!(parse_ssid_hex("abc").is_err());
+        assert_eq!(directed_scan_command("636166c3a9"), "SCAN ssid 636166c3a9");
+    }
+
+    #[test]
+    fn regdom_change_is_parsed() {
+        let change = RegdomChange::parse("init=USER type=COUNTRY alpha2=DE").unwrap();
+        assert_eq!(change.initiator, RegdomInitiator::User);
+        assert_eq!(change.regdom, Regdom::Country("DE".parse().unwrap()));
+        let change = RegdomChange::parse("init=CORE type=WORLD").unwrap();
+        assert_eq!(change.regdom, Regdom::World);
+        let change = RegdomChange::parse("init=CORE type=WORLD alpha2=00").unwrap();
+        assert_eq!(change.regdom, Regdom::World);
+        let change = RegdomChange::parse("init=COUNTRY_IE type=INTERSECTION alpha2=98").unwrap();
+        assert_eq!(change.initiator, RegdomInitiator::CountryIe);
+        assert_eq!(change.regdom, Regdom::Intersection);
+
+        let set = crate::sta::Set::Country("de".parse().unwrap());
+        assert_eq!(set.to_command().unwrap(), "SET country DE");
+        assert_eq!(parse_country("US\n").unwrap().to_string(), "US");
+    }
+
+    #[test]
+    fn network_preferences_are_encoded() {
+        use crate::sta::SetNetwork;
+        let cases = [
+            (SetNetwork::Priority(-1), "priority -1"),
+            (SetNetwork::Disabled(true), "disabled 1"),
+            (SetNetwork::ScanSsid(false), "scan_ssid 0"),
+            (SetNetwork::IdStr("home".into()), "id_str \"home\""),
+            (SetNetwork::IdStr("a\"b".into()), "id_str 612262"),
+            (
+                SetNetwork::FreqList(vec![2412, 5180]),
+                "freq_list 2412 5180",
+            ),
+        ];
+        for (set, field) in cases {
+            assert_eq!(set.to_command(4), format!("SET_NETWORK 4 {field}"));
+        }
+    }
+
+    #[test]
+    fn mac_randomization_commands_are_built() {
+        use crate::sta::{Set, SetNetwork};
+        let set = SetNetwork::MacAddr(MacRandomization::Dedicated);
+        assert_eq!(set.to_command(0), "SET_NETWORK 0 mac_addr 3");
+        assert_eq!(
+            Set::PreassocMacAddr(MacRandomization::RandomKeepOui)
+                .to_command()
+                .unwrap(),
+            "SET preassoc_mac_addr 2"
+        );
+        assert_eq!(
+            Set::RandAddrLifetime(std::time::Duration::from_secs(300))
+                .to_command()
+                .unwrap(),
+            "SET rand_addr_lifetime 300"
+        );
+        // a dedicated address belongs to a network
+        assert!(matches!(
+            Set::MacAddr(MacRandomization::Dedicated).to_command(),
+            Err(ClientError::InvalidParameter("mac_addr"))
+        ));
+    }
+
     #[test]
     fn parse_status_tolerates_sparse_response() {
         let status = parse_status("wpa_state=SCANNING").unwrap();
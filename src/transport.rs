@@ -0,0 +1,172 @@
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::ReadBuf;
+use tokio::net::{UdpSocket, UnixDatagram};
+
+/// How to reach a hostapd or wpa_supplicant control interface.
+///
+/// ```no_run
+/// use wifi_ctrl::{ControlInterface, sta};
+///
+/// let mut setup = sta::WifiSetup::new();
+/// // wpa_supplicant built with CONFIG_CTRL_IFACE=udp, e.g. in another network
+/// // namespace reachable over loopback
+/// setup.set_control_interface(ControlInterface::Udp("127.0.0.1:9877".parse().unwrap()));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControlInterface {
+    /// A Unix datagram socket, e.g. `/var/run/wpa_supplicant/wlan0`
+    Unix(PathBuf),
+    /// The UDP control interface of a daemon built with
+    /// `CONFIG_CTRL_IFACE=udp`, by default on port 9877 for wpa_supplicant
+    /// and 8877 for hostapd. Commands are authenticated with the cookie the
+    /// daemon hands out in reply to `GET_COOKIE`.
+    Udp(SocketAddr),
+}
+
+/// A datagram socket connected to a control interface.
+///
+/// Written in poll form so [`SocketHandle`](crate::socket_handle::SocketHandle)
+/// can hold any transport behind a `Box<dyn Transport>`.
+pub(crate) trait Transport: Send + Sync {
+    fn poll_send(&self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>>;
+    fn poll_recv(&self, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>>;
+}
+
+impl ControlInterface {
+    /// Make a single attempt at connecting; the caller retries.
+    pub(crate) async fn connect(
+        &self,
+        label: &str,
+        command_timeout: Duration,
+    ) -> io::Result<Box<dyn Transport>> {
+        Ok(match self {
+            ControlInterface::Unix(path) => Box::new(UnixTransport::connect(path, label)?),
+            ControlInterface::Udp(addr) => {
+                Box::new(UdpTransport::connect(*addr, command_timeout).await?)
+            }
+        })
+    }
+}
+
+struct UnixTransport {
+    #[allow(unused)]
+    /// Temporary directory for socket. If it drops, socket breaks.
+    tmp_dir: tempfile::TempDir,
+    socket: UnixDatagram,
+}
+
+impl UnixTransport {
+    fn connect(path: &Path, label: &str) -> io::Result<Self> {
+        let tmp_dir = tempfile::tempdir()?;
+        let socket = UnixDatagram::bind(tmp_dir.path().join(label))?;
+        socket.connect(path)?;
+        Ok(Self { tmp_dir, socket })
+    }
+}
+
+impl Transport for UnixTransport {
+    fn poll_send(&self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        self.socket.poll_send(cx, buf)
+    }
+
+    fn poll_recv(&self, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        self.socket.poll_recv(cx, buf)
+    }
+}
+
+struct UdpTransport {
+    socket: UdpSocket,
+    /// `COOKIE=<hex> ` prefix the daemon requires on every command
+    cookie: String,
+}
+
+impl UdpTransport {
+    async fn connect(addr: SocketAddr, command_timeout: Duration) -> io::Result<Self> {
+        let local: SocketAddr = match addr {
+            SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+            SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+        };
+        let socket = UdpSocket::bind(local).await?;
+        socket.connect(addr).await?;
+        socket.send(b"GET_COOKIE").await?;
+        let mut buffer = [0; 128];
+        let n = tokio::time::timeout(command_timeout, socket.recv(&mut buffer))
+            .await
+            .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "no reply to GET_COOKIE"))??;
+        let cookie = parse_cookie(&buffer[..n]).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "invalid reply to GET_COOKIE")
+        })?;
+        Ok(Self {
+            socket,
+            cookie: format!("COOKIE={cookie} "),
+        })
+    }
+}
+
+fn parse_cookie(reply: &[u8]) -> Option<&str> {
+    let cookie = std::str::from_utf8(reply)
+        .ok()?
+        .trim_end()
+        .strip_prefix("COOKIE=")?;
+    (!cookie.is_empty() && cookie.bytes().all(|b| b.is_ascii_hexdigit())).then_some(cookie)
+}
+
+impl Transport for UdpTransport {
+    fn poll_send(&self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let msg = [self.cookie.as_bytes(), buf].concat();
+        // report how much of the caller's command went out, not the cookie
+        self.socket
+            .poll_send(cx, &msg)
+            .map_ok(|n| n.saturating_sub(self.cookie.len()))
+    }
+
+    fn poll_recv(&self, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        self.socket.poll_recv(cx, buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cookie_reply_is_validated() {
+        assert_eq!(
+            parse_cookie(b"COOKIE=0123456789abcdef\n"),
+            Some("0123456789abcdef")
+        );
+        assert_eq!(parse_cookie(b"FAIL\n"), None);
+        assert_eq!(parse_cookie(b"COOKIE=\n"), None);
+        assert_eq!(parse_cookie(b"COOKIE=01 STATUS"), None);
+    }
+
+    #[tokio::test]
+    async fn udp_commands_carry_the_cookie() {
+        let daemon = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = daemon.local_addr().unwrap();
+        let fake_daemon = async {
+            let mut buffer = [0; 128];
+            let (n, client) = daemon.recv_from(&mut buffer).await.unwrap();
+            assert_eq!(&buffer[..n], b"GET_COOKIE");
+            daemon.send_to(b"COOKIE=c0ffee\n", client).await.unwrap();
+            let n = daemon.recv(&mut buffer).await.unwrap();
+            buffer[..n].to_vec()
+        };
+        let client = async {
+            let transport = ControlInterface::Udp(addr)
+                .connect("unused", Duration::from_secs(1))
+                .await
+                .unwrap();
+            let sent = std::future::poll_fn(|cx| transport.poll_send(cx, b"PING"))
+                .await
+                .unwrap();
+            assert_eq!(sent, 4);
+        };
+        let (received, ()) = tokio::join!(fake_daemon, client);
+        assert_eq!(received, b"COOKIE=c0ffee PING");
+    }
+}